@@ -0,0 +1,159 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek},
+    path::Path,
+};
+
+use binrw::BinReaderExt;
+
+use crate::error::{Error, Result};
+use crate::evaluate::{evaluate_entry, EntryValue};
+use crate::structure::anm::{AnmEntry, Curve, NuccAnm};
+use crate::structure::anmstrm::*;
+use crate::structure::anm_utils::*;
+
+
+/// Reads an ANM chunk from any seekable reader.
+pub fn read_anm<R: Read + Seek>(reader: &mut R) -> Result<NuccAnm> {
+    reader.read_be::<NuccAnm>().map_err(|err| Error::parse(reader, err))
+}
+
+/// Reads an ANM chunk from a file.
+pub fn read_anm_file<P: AsRef<Path>>(path: P) -> Result<NuccAnm> {
+    let path = path.as_ref();
+    let mut reader = BufReader::new(File::open(path).map_err(|err| Error::from(err).with_path(path))?);
+
+    read_anm(&mut reader).map_err(|err| err.with_path(path))
+}
+
+/// Converts an ANM back into an ANMSTRM and its frames by sampling every entry's curves once per frame.
+/// Entries with a format the ANMSTRM can't hold are left out.
+pub fn convert_anm(anm: &NuccAnm) -> Result<(NuccAnmStrm, Vec<NuccAnmStrmFrame>)> {
+    let frame_size = anm.frame_size.max(1);
+    let frame_count = anm.anm_length.div_ceil(frame_size).max(1);
+
+    let mut anmstrmframes = Vec::with_capacity(frame_count as usize);
+
+    for frame in 0..frame_count {
+        let mut entries = Vec::with_capacity(anm.entries.len());
+
+        for entry in &anm.entries {
+            if let Some(entry_data) = sample_entry(entry, frame, frame_size) {
+                entries.push(AnmStrmEntry {
+                    coord: entry.coord.clone(),
+                    entry_format: entry.entry_format,
+                    entry_size: entry_data.entry_size(),
+                    entry_data,
+                });
+            }
+        }
+
+        anmstrmframes.push(NuccAnmStrmFrame {
+            frame_number: frame,
+            entry_count: entries.len() as u16,
+            unknown: 0,
+            entries,
+        });
+    }
+
+    let clumps = anm
+        .clumps
+        .iter()
+        .map(|clump| AnmStrmClump {
+            clump_index: clump.clump_index,
+            bone_material_count: clump.bone_material_count,
+            model_count: clump.model_count,
+            bone_material_indices: clump.bone_material_indices.clone(),
+            model_indices: clump.model_indices.clone(),
+            unknown: vec![0; clump.model_indices.len()],
+        })
+        .collect();
+
+    let mut anmstrm = NuccAnmStrm {
+        anm_length: anm.anm_length,
+        frame_size: anm.frame_size,
+        frame_count: frame_count as u16,
+        is_looped: anm.looped,
+        clump_count: anm.clump_count,
+        other_entry_count: anm.other_entry_count,
+        other_index_count: anm.other_index_count,
+        coord_count: anm.coord_count,
+        clumps,
+        other_entries_indices: anm.other_entries_indices.clone(),
+        coord_parents: anm.coord_parents.clone(),
+        frames: Vec::new(),
+    };
+
+    anmstrm.update_frame_infos(&anmstrmframes);
+
+    Ok((anmstrm, anmstrmframes))
+}
+
+/// Samples one ANM entry at the given frame into ANMSTRM entry data.
+fn sample_entry(entry: &AnmEntry, frame: u32, frame_size: u32) -> Option<Entry> {
+    let Some(value) = evaluate_entry(entry, frame as f32, frame_size) else {
+        // Entries passed through as a raw curve holding the entry data of every frame
+        return match entry.curves.first() {
+            Some(Curve::Unknown { frame_count, data, .. }) if *frame_count > 0 => {
+                let frame_count = *frame_count as usize;
+                let size = data.len() / frame_count;
+                let start = (frame as usize).min(frame_count - 1) * size;
+
+                Some(Entry::Unknown(data[start..start + size].to_vec()))
+            }
+            _ => None,
+        };
+    };
+
+    let entry_data = match value {
+        EntryValue::Bone { location, rotation, scale, toggled } => Entry::Bone(AnmEntryBone {
+            frame_count: 1,
+            location,
+            rotation,
+            scale,
+            toggled,
+        }),
+
+        EntryValue::Camera { location, rotation, fov } => Entry::Camera(AnmEntryCamera {
+            frame_count: 1,
+            location,
+            rotation,
+            fov,
+            scale: Vector3 { x: 1.0, y: 1.0, z: 1.0 },
+        }),
+
+        EntryValue::Material { values } => Entry::Material(AnmEntryMaterial {
+            frame_count: 1,
+            ambient_color: values,
+        }),
+
+        EntryValue::LightDirc { color, intensity, direction } => Entry::LightDirc(AnmEntryLightDirc {
+            frame_count: 1,
+            color,
+            intensity,
+            direction,
+        }),
+
+        EntryValue::LightPoint { color, position, intensity, radius, falloff } => Entry::LightPoint(AnmEntryLightPoint {
+            frame_count: 1,
+            color,
+            position,
+            intensity,
+            radius,
+            falloff,
+        }),
+
+        EntryValue::Ambient { color, intensity } => Entry::Ambient(AnmEntryAmbient {
+            frame_count: 1,
+            color,
+            intensity,
+        }),
+
+        EntryValue::MorphModel { weights } => Entry::MorphModel(AnmEntryMorphModel {
+            frame_count: weights.len() as i32,
+            morph_weight: weights,
+        }),
+    };
+
+    Some(entry_data)
+}
@@ -0,0 +1,215 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Instant,
+};
+
+use rayon::prelude::*;
+
+use hashbrown::HashMap;
+
+use anmstrm2anm::{output::OutputLayout, utils::macros::find_subfolders, Error, Result};
+
+use crate::cli::{BatchArgs, Verbosity};
+use crate::{convert_folder, exit_code, FolderReport, ANMSTRM_FOLDER_PATTERN};
+
+/// A folder found below one of the batch roots.
+struct BatchFolder {
+    xfbin_dir: PathBuf,
+    anmstrm_dir: PathBuf,
+    /// The output directory, followed by the path of `xfbin_dir`'s parent below its root.
+    output_dir: PathBuf,
+}
+
+/// Result of converting one folder in a batch.
+struct BatchEntry {
+    xfbin_dir: PathBuf,
+    result: Result<FolderReport>,
+    elapsed: f32,
+}
+
+/// Converts every extracted XFBIN folder found from the batch inputs and prints a summary table.
+pub fn run(args: &BatchArgs, verbosity: Verbosity) -> ExitCode {
+    let roots = match batch_roots(args) {
+        Ok(roots) => roots,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return ExitCode::from(exit_code(&err));
+        }
+    };
+
+    let folders = match args.settings.output_dir() {
+        Ok(output_dir) => batch_folders(&roots, &output_dir),
+        Err(err) => {
+            let err = Error::from(err);
+            eprintln!("error: {}", err);
+            return ExitCode::from(exit_code(&err));
+        }
+    };
+
+    if folders.is_empty() {
+        eprintln!("error: no {} chunk folders found", ANMSTRM_FOLDER_PATTERN);
+        return ExitCode::from(3);
+    }
+
+    let collisions = output_collisions(&folders);
+    if !collisions.is_empty() {
+        for (output, xfbin_dirs) in &collisions {
+            let xfbin_dirs: Vec<String> = xfbin_dirs.iter().map(|dir| dir.display().to_string()).collect();
+            eprintln!("error: {} would all be written to {}", xfbin_dirs.join(", "), output.display());
+        }
+        return ExitCode::from(2);
+    }
+
+    if verbosity > Verbosity::Quiet {
+        println!("converting {} folders...", folders.len());
+    }
+
+    let pool = match rayon::ThreadPoolBuilder::new().num_threads(args.jobs.unwrap_or(0)).build() {
+        Ok(pool) => pool,
+        Err(err) => {
+            eprintln!("error: failed to start worker threads: {}", err);
+            return ExitCode::from(1);
+        }
+    };
+
+    let now = Instant::now();
+
+    let entries: Vec<BatchEntry> = pool.install(|| {
+        folders
+            .par_iter()
+            .map(|folder| {
+                let xfbin_dir = folder.xfbin_dir.clone();
+
+                let start = Instant::now();
+                let result = convert_folder(&xfbin_dir, &folder.anmstrm_dir, &folder.output_dir, &args.settings, verbosity, false);
+                let elapsed = start.elapsed().as_secs_f32();
+
+                match &result {
+                    Ok(_) if verbosity > Verbosity::Quiet => println!("converted {} in {:.2}s", xfbin_dir.display(), elapsed),
+                    Err(err) => eprintln!("failed {}: {}", xfbin_dir.display(), err),
+                    _ => {}
+                }
+
+                BatchEntry { xfbin_dir, result, elapsed }
+            })
+            .collect()
+    });
+
+    let failed = entries.iter().filter(|entry| entry.result.is_err()).count();
+
+    if verbosity > Verbosity::Quiet {
+        print_summary(&entries, now.elapsed().as_secs_f32());
+    }
+
+    if failed > 0 {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Collects the root folders from the positional root and the list file.
+fn batch_roots(args: &BatchArgs) -> Result<Vec<PathBuf>> {
+    let mut roots: Vec<PathBuf> = args.root.iter().cloned().collect();
+
+    if let Some(list) = &args.list {
+        let contents = fs::read_to_string(list).map_err(|err| Error::from(err).with_path(list))?;
+        let base = list.parent().unwrap_or(Path::new(""));
+
+        roots.extend(
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(|line| base.join(line)),
+        );
+    }
+
+    Ok(roots)
+}
+
+/// Finds the chunk folders below every root, each found once, with the output directory mirroring its
+/// place below the root so same-named folders in different subfolders don't overwrite each other.
+fn batch_folders(roots: &[PathBuf], output_dir: &Path) -> Vec<BatchFolder> {
+    let mut folders: Vec<BatchFolder> = roots
+        .iter()
+        .flat_map(|root| {
+            find_subfolders(root, ANMSTRM_FOLDER_PATTERN).into_iter().map(move |anmstrm_dir| {
+                let xfbin_dir = anmstrm_dir.parent().unwrap_or(&anmstrm_dir).to_path_buf();
+                let relative_dir = xfbin_dir
+                    .parent()
+                    .and_then(|parent| parent.strip_prefix(root).ok())
+                    .unwrap_or(Path::new(""));
+
+                BatchFolder {
+                    output_dir: output_dir.join(relative_dir),
+                    xfbin_dir,
+                    anmstrm_dir,
+                }
+            })
+        })
+        .collect();
+
+    folders.sort_by(|a, b| a.anmstrm_dir.cmp(&b.anmstrm_dir));
+    folders.dedup_by(|a, b| a.anmstrm_dir == b.anmstrm_dir);
+    folders
+}
+
+/// Output folders more than one input folder would be converted to, with those input folders.
+fn output_collisions(folders: &[BatchFolder]) -> Vec<(PathBuf, Vec<PathBuf>)> {
+    let mut outputs: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+
+    for folder in folders {
+        let layout = OutputLayout::new(&folder.output_dir, &folder_name(&folder.xfbin_dir));
+        outputs.entry(layout.root().to_path_buf()).or_default().push(folder.xfbin_dir.clone());
+    }
+
+    let mut collisions: Vec<(PathBuf, Vec<PathBuf>)> = outputs.into_iter().filter(|(_, xfbin_dirs)| xfbin_dirs.len() > 1).collect();
+    collisions.sort();
+    collisions
+}
+
+fn print_summary(entries: &[BatchEntry], total_elapsed: f32) {
+    let name_width = entries
+        .iter()
+        .map(|entry| folder_name(&entry.xfbin_dir).len())
+        .max()
+        .unwrap_or(0)
+        .max("folder".len());
+
+    println!();
+    println!("{:<6}  {:<name_width$}  {:>7}  {:>4}  {:>8}", "status", "folder", "frames", "anms", "time");
+
+    for entry in entries {
+        let name = folder_name(&entry.xfbin_dir);
+
+        match &entry.result {
+            Ok(report) => println!(
+                "{:<6}  {:<name_width$}  {:>7}  {:>4}  {:>7.2}s",
+                "ok", name, report.frame_count, report.anm_count, entry.elapsed
+            ),
+            Err(err) => println!(
+                "{:<6}  {:<name_width$}  {:>7}  {:>4}  {:>7.2}s  {}",
+                "FAILED", name, "-", "-", entry.elapsed, err
+            ),
+        }
+    }
+
+    let failed = entries.iter().filter(|entry| entry.result.is_err()).count();
+
+    println!();
+    println!(
+        "{} converted, {} failed, {:.2}s total",
+        entries.len() - failed,
+        failed,
+        total_elapsed
+    );
+}
+
+fn folder_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
@@ -1,94 +1,52 @@
-use std::fmt::format;
-
-use crate::structure::page::*;
-
-pub fn build_anm_page(filepath: &str) -> Page {
-    let mut page = Page::from_json_file(filepath);
-
-    page.chunk_maps.retain(|chunk| !chunk.types.contains("nuccChunkMorphModel"));
-    page.chunk_maps.retain(|chunk| !chunk.types.contains("nuccChunkAnmStrmFrame"));
-    
-
-    for map in page.chunk_maps.iter_mut() {
-        if map.types.contains("nuccChunkAnmStrm") {
-            map.types = "nuccChunkAnm".to_string();
-        }
-    }
-
-    // Remove 1cmn references
-    page.chunk_maps.retain(|chunk| !chunk.name.contains("1cmn"));
-    page.chunk_references.retain(|reference| !reference.name.contains("1cmn"));
-
-    page.files.retain(|file| !file.file_name.contains(".morphmodel"));
-    page.files.retain(|file| !file.file_name.contains(".anmstrmframe"));
-
-    for file in page.files.iter_mut() {
-        if file.file_name.contains("anmstrm") {
-            file.file_name = file.file_name.replace("anmstrm", "anm");
-            file.chunk.types = "nuccChunkAnm".to_string();
-
-        }
-    }
-
-    page
-}
-
-
-pub fn build_dmg_page(filepath: &str) -> Page {
-    let mut dmg_page = Page::from_json_file(filepath);
-
-    // remove morphmodel chunks
-    dmg_page.chunk_maps.retain(|chunk| !chunk.types.contains("nuccChunkMorphModel"));
-    dmg_page.chunk_maps.retain(|chunk| !chunk.types.contains("nuccChunkAnmStrmFrame"));
-    dmg_page.chunk_maps.retain(|chunk| !chunk.types.contains("nuccChunkCamera"));
-    dmg_page.chunk_maps.retain(|chunk| !chunk.types.contains("nuccChunkLightDirc"));
-    dmg_page.chunk_maps.retain(|chunk| !chunk.types.contains("nuccChunkLightPoint"));
-    dmg_page.chunk_maps.retain(|chunk| !chunk.types.contains("nuccChunkAmbient"));
-    dmg_page.chunk_maps.retain(|chunk| !chunk.types.contains("nuccChunkLayerSet"));
-
-    dmg_page.files.retain(|file| !file.file_name.contains(".morphmodel"));
-    dmg_page.files.retain(|file| !file.file_name.contains(".anmstrmframe"));
-    dmg_page.files.retain(|file| !file.file_name.contains(".camera"));
-    dmg_page.files.retain(|file| !file.file_name.contains(".lightdirc"));
-    dmg_page.files.retain(|file| !file.file_name.contains(".lightpoint"));
-    dmg_page.files.retain(|file| !file.file_name.contains(".ambient"));
-    dmg_page.files.retain(|file| !file.file_name.contains(".layerset"));
-
-    for map in dmg_page.chunk_maps.iter_mut() {
-        if map.types.contains("nuccChunkAnmStrm") {
-            map.types = "nuccChunkAnm".to_string();
-            map.name = format!("{}{}", map.name, "_dmg");
-            map.path = map.path.replace(".max", "_dmg.max");
-        }
-
-        
-
-    }
-
-    for file in dmg_page.files.iter_mut() {
-        if file.file_name.contains("anmstrm") {
-            file.file_name = file.file_name.replace(".anmstrm", "_dmg.anm");
-            file.chunk.types = "nuccChunkAnm".to_string();
-            file.chunk.name = format!("{}{}", file.chunk.name, "_dmg");
-            file.chunk.path = file.chunk.path.replace(".max", "_dmg.max");
-
-        }
-    }
-
-    // remove nuccChunkAnmStrmFrame
-    dmg_page.chunk_maps.retain(|chunk| !chunk.types.contains("nuccChunkAnmStrmFrame"));
-    dmg_page.chunk_maps.retain(|chunk| chunk.name.contains("1cmn") 
-    || chunk.types.contains("nuccChunkNull")
-    || chunk.types.contains("nuccChunkAnm")
-    || chunk.types.contains("nuccChunkIndex")
-    || chunk.types.contains("nuccChunkPage"));
-
-
-
-    
-    dmg_page.chunk_references.retain(|reference| reference.name.contains("1cmn"));
-
-    
-    dmg_page
- 
-}
\ No newline at end of file
+use std::path::Path;
+
+use crate::error::Result;
+use crate::page_rules::PageRules;
+use crate::structure::page::*;
+
+/// Builds the anm page for an anm the DMG clumps were split off, leaving out the chunk maps at
+/// `removed_indices`, see [`removed_indices`](crate::clumps::removed_indices).
+pub fn build_anm_page<P: AsRef<Path>>(filepath: P, removed_indices: &[u32]) -> Result<Page> {
+    Ok(anm_page(Page::from_json_file(filepath)?, Some(removed_indices)))
+}
+
+/// Builds the anm page for an anm that still contains the DMG clumps.
+pub fn build_anm_page_with_dmg<P: AsRef<Path>>(filepath: P) -> Result<Page> {
+    Ok(anm_page(Page::from_json_file(filepath)?, None))
+}
+
+pub fn build_dmg_page<P: AsRef<Path>>(filepath: P, removed_indices: &[u32]) -> Result<Page> {
+    Ok(dmg_page(Page::from_json_file(filepath)?, removed_indices))
+}
+
+/// Builds the page of one anm out of [`split_every_clump`](crate::clumps::split_every_clump).
+pub fn build_clump_page<P: AsRef<Path>>(filepath: P, removed_indices: &[u32], suffix: &str) -> Result<Page> {
+    Ok(clump_page(Page::from_json_file(filepath)?, removed_indices, suffix))
+}
+
+pub fn build_anmstrm_page<P: AsRef<Path>>(filepath: P, frame_count: usize) -> Result<Page> {
+    Ok(anmstrm_page(Page::from_json_file(filepath)?, frame_count))
+}
+
+/// Turns an anmstrm page into the page of the converted anm with the built-in [`PageRules`], see
+/// [`PageRules::anm_page`]. MorphModel chunks are kept since their weights are converted into MORPHMODEL entries.
+pub fn anm_page(page: Page, removed_indices: Option<&[u32]>) -> Page {
+    PageRules::built_in().anm_page(page, removed_indices)
+}
+
+/// Turns an anmstrm page into the page of the DMG anm split off the converted anm with the built-in [`PageRules`].
+pub fn dmg_page(page: Page, removed_indices: &[u32]) -> Page {
+    PageRules::built_in().dmg_page(page, removed_indices)
+}
+
+/// Turns an anmstrm page into the page of an anm split off the converted anm with the built-in
+/// [`PageRules`], see [`split_every_clump`](crate::clumps::split_every_clump).
+pub fn clump_page(page: Page, removed_indices: &[u32], suffix: &str) -> Page {
+    PageRules::built_in().clump_page(page, removed_indices, suffix)
+}
+
+/// Turns the page of an anm back into the page of an anmstrm with `frame_count` frame chunks with the
+/// built-in [`PageRules`].
+pub fn anmstrm_page(page: Page, frame_count: usize) -> Page {
+    PageRules::built_in().anmstrm_page(page, frame_count)
+}
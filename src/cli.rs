@@ -0,0 +1,316 @@
+use std::path::PathBuf;
+
+use clap::{error::ErrorKind, parser::ValueSource, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+
+use anmstrm2anm::{ClumpSelector, Page};
+
+/// Converts streamed animations (nuccChunkAnmStrm) from .xfbin files or extracted XFBIN folders into regular animations (nuccChunkAnm).
+///
+/// Running the tool with just a folder (e.g. by dropping it onto the exe) is the same as `anmstrm2anm convert <folder>`.
+/// Conversion options go after the subcommand when one is given.
+#[derive(Debug, Parser)]
+#[command(name = "anmstrm2anm", version, subcommand_negates_reqs = true)]
+#[command(after_help = "Exit codes:\n  0  conversion succeeded\n  1  conversion failed\n  2  invalid arguments\n  3  input folder or chunk files not found")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// .xfbin file, or extracted XFBIN folder containing a (nuccChunkAnmStrmFrame) chunk folder.
+    #[arg(required = true)]
+    pub input: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub settings: ConvertSettings,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+impl Cli {
+    /// Parses the command line, rejecting conversion options given before a subcommand since only the
+    /// subcommand's own options are used.
+    pub fn parse_checked() -> Cli {
+        let mut command = Cli::command();
+        let matches = command.get_matches_mut();
+        let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+        if let Some((subcommand, _)) = matches.subcommand() {
+            let misplaced: Vec<String> = command
+                .get_arguments()
+                .filter(|arg| !arg.is_global_set() && matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine))
+                .map(|arg| match arg.get_long() {
+                    Some(long) => format!("--{}", long),
+                    None => format!("<{}>", arg.get_id().as_str().to_uppercase()),
+                })
+                .collect();
+
+            if !misplaced.is_empty() {
+                command
+                    .error(
+                        ErrorKind::ArgumentConflict,
+                        format!("{} must go after the `{}` subcommand", misplaced.join(", "), subcommand),
+                    )
+                    .exit();
+            }
+        }
+
+        cli
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Convert the nuccChunkAnmStrm found in an .xfbin file or an extracted XFBIN folder.
+    Convert(ConvertArgs),
+
+    /// Convert every extracted XFBIN folder found below a root folder, in parallel.
+    Batch(BatchArgs),
+
+    /// Convert the nuccChunkAnm in an .xfbin file or an extracted XFBIN folder back into a streamed animation.
+    #[command(name = "anm2anmstrm")]
+    Anm2AnmStrm(ReverseArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ConvertArgs {
+    /// .xfbin file, or extracted XFBIN folder containing a (nuccChunkAnmStrmFrame) chunk folder.
+    pub input: PathBuf,
+
+    #[command(flatten)]
+    pub settings: ConvertSettings,
+}
+
+#[derive(Debug, Args)]
+pub struct BatchArgs {
+    /// Root folder searched recursively for (nuccChunkAnmStrmFrame) chunk folders. The converted folders are
+    /// written to the same subfolders of the output directory as their inputs are below the root.
+    #[arg(required_unless_present = "list")]
+    pub root: Option<PathBuf>,
+
+    /// Text file listing extracted XFBIN folders (or root folders to search), one per line.
+    #[arg(short, long, value_name = "FILE")]
+    pub list: Option<PathBuf>,
+
+    /// Number of folders converted at the same time. Defaults to the number of CPUs.
+    #[arg(short, long)]
+    pub jobs: Option<usize>,
+
+    #[command(flatten)]
+    pub settings: ConvertSettings,
+}
+
+#[derive(Debug, Args)]
+pub struct ReverseArgs {
+    /// .xfbin file, or extracted XFBIN folder containing a (nuccChunkAnm) chunk folder.
+    pub input: PathBuf,
+
+    /// Directory the `<name>_converted` folder is written to. Defaults to the current directory.
+    #[arg(short, long, value_name = "OUT_DIR")]
+    pub output: Option<PathBuf>,
+
+    /// Don't copy any auxiliary chunk files.
+    #[arg(long)]
+    pub no_copy: bool,
+
+    /// Also pack the anmstrm and its frames into a `<name>.xfbin` inside the `<name>_converted` folder.
+    #[arg(long)]
+    pub xfbin: bool,
+
+    /// JSON file with the rules turning the anm page into the anmstrm page, instead of the built-in rules.
+    #[arg(long, value_name = "FILE")]
+    pub page_rules: Option<PathBuf>,
+}
+
+/// Options shared by `convert` and `batch`.
+#[derive(Debug, Args)]
+pub struct ConvertSettings {
+    /// Directory the `<name>_converted` folders are written to. Defaults to the current directory.
+    #[arg(short, long, value_name = "OUT_DIR")]
+    pub output: Option<PathBuf>,
+
+    /// Keep the DMG clump in the main anm instead of splitting it into a separate `_dmg` anm.
+    #[arg(long)]
+    pub no_dmg: bool,
+
+    /// Clumps split into the `_dmg` anm: clump positions (e.g. `0,2`), `name:<TEXT>` for clumps whose chunk name contains TEXT, or `bones:<COUNT>` for clumps with COUNT bones and materials.
+    #[arg(long, value_name = "SELECTOR", default_value = "bones:97", value_parser = parse_clump_rule, conflicts_with = "no_dmg")]
+    pub dmg_clumps: ClumpRule,
+
+    /// Write one anm per clump, named after the clump's chunk in the page, instead of the main and `_dmg` anm.
+    #[arg(long, conflicts_with_all = ["no_dmg", "dmg_clumps"])]
+    pub split_clumps: bool,
+
+    /// With --split-clumps, put the camera and light entries in the anm of the clump at this position instead of a separate `_scene` anm.
+    #[arg(long, value_name = "POSITION", requires = "split_clumps")]
+    pub scene_clump: Option<usize>,
+
+    /// Auxiliary chunk files copied next to the converted anm (comma separated).
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "KINDS", default_values_t = AuxFile::all())]
+    pub copy: Vec<AuxFile>,
+
+    /// Don't copy any auxiliary chunk files.
+    #[arg(long, conflicts_with = "copy")]
+    pub no_copy: bool,
+
+    /// Pass anmstrm entries with an unknown format through to the converted anm as raw data instead of dropping them.
+    #[arg(long)]
+    pub keep_unknown: bool,
+
+    /// Also pack the converted anms into a `<name>.xfbin` inside the `<name>_converted` folder, ready to drop into a mod.
+    #[arg(long)]
+    pub xfbin: bool,
+
+    /// JSON file with the rules turning the anmstrm page into the pages of the converted anms, instead of the built-in rules.
+    #[arg(long, value_name = "FILE")]
+    pub page_rules: Option<PathBuf>,
+
+    /// Write every frame of curves that never change instead of collapsing them to a single value.
+    #[arg(long)]
+    pub keep_constant_curves: bool,
+
+    /// Leave out entries that never move from their rest pose (no offset, no rotation, a scale of 1).
+    #[arg(long)]
+    pub drop_rest_entries: bool,
+
+    /// Ticks between frames used for keyframe times. Defaults to the anmstrm's frame size.
+    #[arg(long, value_name = "TICKS")]
+    pub frame_ticks: Option<u32>,
+
+    /// Remove keyframes that can be rebuilt by interpolating their neighbours within this tolerance.
+    #[arg(long, value_name = "TOLERANCE")]
+    pub reduce_keyframes: Option<f32>,
+
+    /// Tolerance for rotations when reducing keyframes, per quaternion component. Per-frame rotations are
+    /// written as keyframes when that makes them smaller. Defaults to the --reduce-keyframes tolerance.
+    #[arg(long, value_name = "TOLERANCE", requires = "reduce_keyframes")]
+    pub rotation_tolerance: Option<f32>,
+}
+
+impl ConvertSettings {
+    /// The directory `<name>_converted` folders are written to.
+    pub fn output_dir(&self) -> std::io::Result<PathBuf> {
+        match &self.output {
+            Some(output_dir) => Ok(output_dir.clone()),
+            None => std::env::current_dir(),
+        }
+    }
+
+    /// Whether auxiliary chunk files with this extension are copied to the output.
+    pub fn copies(&self, extension: &str) -> bool {
+        !self.no_copy && self.copy.iter().any(|aux_file| aux_file.extension() == extension)
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct OutputArgs {
+    /// Only print errors.
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Print every file that is read and written.
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+
+    /// Exit as soon as the conversion finishes instead of pausing so the console window stays open.
+    /// Batch runs and runs whose output isn't a terminal never pause.
+    #[arg(short = 'y', long, global = true)]
+    pub non_interactive: bool,
+}
+
+/// Which clumps to split off, as given on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClumpRule {
+    Positions(Vec<usize>),
+    Name(String),
+    BoneMaterialCount(u16),
+}
+
+impl ClumpRule {
+    /// The selector for this rule, looking clump names up in `page`.
+    pub fn selector(&self, page: &Page) -> ClumpSelector {
+        match self {
+            ClumpRule::Positions(positions) => ClumpSelector::Positions(positions.clone()),
+            ClumpRule::Name(pattern) => ClumpSelector::Name {
+                pattern: pattern.clone(),
+                page: page.clone(),
+            },
+            ClumpRule::BoneMaterialCount(count) => ClumpSelector::BoneMaterialCount(*count),
+        }
+    }
+}
+
+fn parse_clump_rule(value: &str) -> Result<ClumpRule, String> {
+    if let Some(pattern) = value.strip_prefix("name:") {
+        return Ok(ClumpRule::Name(pattern.to_string()));
+    }
+
+    if let Some(count) = value.strip_prefix("bones:") {
+        return count
+            .parse()
+            .map(ClumpRule::BoneMaterialCount)
+            .map_err(|_| format!("invalid bone count `{}`", count));
+    }
+
+    value
+        .split(',')
+        .map(|position| position.trim().parse().map_err(|_| format!("invalid clump position `{}`", position)))
+        .collect::<Result<_, _>>()
+        .map(ClumpRule::Positions)
+}
+
+/// Auxiliary chunk files that live next to the anmstrm and can be copied to the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AuxFile {
+    Camera,
+    Lightdirc,
+    Lightpoint,
+    Ambient,
+    Layerset,
+    Morphmodel,
+    Json,
+}
+
+impl AuxFile {
+    pub fn all() -> Vec<AuxFile> {
+        vec![
+            AuxFile::Camera,
+            AuxFile::Lightdirc,
+            AuxFile::Lightpoint,
+            AuxFile::Ambient,
+            AuxFile::Layerset,
+            AuxFile::Morphmodel,
+            AuxFile::Json,
+        ]
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AuxFile::Camera => "camera",
+            AuxFile::Lightdirc => "lightdirc",
+            AuxFile::Lightpoint => "lightpoint",
+            AuxFile::Ambient => "ambient",
+            AuxFile::Layerset => "layerset",
+            AuxFile::Morphmodel => "morphmodel",
+            AuxFile::Json => "json",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+impl OutputArgs {
+    pub fn verbosity(&self) -> Verbosity {
+        if self.quiet {
+            Verbosity::Quiet
+        } else if self.verbose {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        }
+    }
+}
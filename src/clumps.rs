@@ -0,0 +1,417 @@
+//! Selecting, removing, inserting and reordering the clumps of an ANM, and splitting them into ANMs of their own.
+//!
+//! The clump, bone/material and model indices of every clump, and the other entry indices, share a single
+//! index space. Removing a clump removes its indices from that space, so every index after them moves down
+//! by the number of indices removed before it. Coords and entries refer to clumps by their position in the
+//! clump list instead, with `-1` for other entries like cameras and lights.
+
+use hashbrown::HashSet;
+
+use crate::error::{Error, Result};
+use crate::structure::anm::{AnmClump, AnmCoord, NuccAnm};
+use crate::structure::page::Page;
+
+
+/// Chooses clumps of an ANM.
+#[derive(Debug, Clone)]
+pub enum ClumpSelector {
+    /// Clumps at these positions in the clump list.
+    Positions(Vec<usize>),
+    /// Clumps whose chunk name in `page` contains `pattern`, see [`clump_name`].
+    Name { pattern: String, page: Page },
+    /// Clumps with this many bones and materials, like the 97 of the DMG clump.
+    BoneMaterialCount(u16),
+    /// Clumps the function returns `true` for.
+    Rule(fn(&AnmClump) -> bool),
+}
+
+impl ClumpSelector {
+    /// The default DMG clump selection, the clump with 97 bones and materials.
+    pub fn dmg() -> Self {
+        ClumpSelector::BoneMaterialCount(97)
+    }
+
+    /// Positions of the selected clumps in `clumps`, in ascending order.
+    pub fn select(&self, clumps: &[AnmClump]) -> Vec<usize> {
+        clumps
+            .iter()
+            .enumerate()
+            .filter(|(position, clump)| match self {
+                ClumpSelector::Positions(positions) => positions.contains(position),
+                ClumpSelector::Name { pattern, page } => clump_name(page, clump).is_some_and(|name| name.contains(pattern.as_str())),
+                ClumpSelector::BoneMaterialCount(count) => clump.bone_material_indices.len() == *count as usize,
+                ClumpSelector::Rule(rule) => rule(clump),
+            })
+            .map(|(position, _)| position)
+            .collect()
+    }
+}
+
+/// Name of the clump's chunk, the page chunk map at the clump's index.
+pub fn clump_name<'a>(page: &'a Page, clump: &AnmClump) -> Option<&'a str> {
+    page.chunk_maps.get(clump.clump_index as usize).map(|chunk| chunk.name.as_str())
+}
+
+/// Moves the clumps at `positions`, with their coord parents and entries, out of `anm` into a new ANM.
+/// Other entries (cameras, lights) stay in `anm`. The indices of both ANMs are recomputed from the
+/// indices that were actually removed from each.
+pub fn split_clumps(anm: &mut NuccAnm, positions: &[usize]) -> NuccAnm {
+    let rest: Vec<usize> = (0..anm.clumps.len()).filter(|position| !positions.contains(position)).collect();
+
+    let mut split = anm.clone();
+    split.remove_clumps(&rest);
+    split.remove_other_entries();
+
+    anm.remove_clumps(positions);
+
+    split
+}
+
+/// Where the other entries (cameras, lights, ambients) go when every clump is split into its own ANM.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SceneAnm {
+    /// A separate ANM without clumps.
+    #[default]
+    Separate,
+    /// The ANM of the clump at this position.
+    Clump(usize),
+}
+
+/// One of the ANMs [`split_every_clump`] splits an ANM into.
+#[derive(Debug, Clone)]
+pub struct ClumpAnm {
+    /// Position of the clump in the split ANM, `None` for a separate scene ANM.
+    pub position: Option<usize>,
+    pub anm: NuccAnm,
+    /// Indices of the split ANM left out of this one, in ascending order. The chunk maps at these
+    /// indices are the ones to drop from the page.
+    pub removed_indices: Vec<u32>,
+}
+
+/// Splits every clump, with its coord parents and entries, into its own ANM. The other entries go to the
+/// ANM chosen by `scene`; a separate scene ANM is only made if there are other entries.
+pub fn split_every_clump(anm: &NuccAnm, scene: SceneAnm) -> Vec<ClumpAnm> {
+    let has_other_entries = !anm.other_entries_indices.is_empty() || anm.entries.iter().any(|entry| entry.coord.clump_index < 0);
+
+    let scene_position = match scene {
+        SceneAnm::Clump(position) if position < anm.clumps.len() => Some(position),
+        _ => None,
+    };
+
+    let mut clump_anms: Vec<ClumpAnm> = (0..anm.clumps.len())
+        .map(|position| extract_clumps(anm, Some(position), scene_position == Some(position)))
+        .collect();
+
+    if scene_position.is_none() && has_other_entries {
+        clump_anms.push(extract_clumps(anm, None, true));
+    }
+
+    clump_anms
+}
+
+/// A copy of `anm` with only the clump at `position` and, if `keep_other_entries` is set, the other entries.
+fn extract_clumps(anm: &NuccAnm, position: Option<usize>, keep_other_entries: bool) -> ClumpAnm {
+    let positions: Vec<usize> = position.into_iter().collect();
+    let rest: Vec<usize> = (0..anm.clumps.len()).filter(|other| !positions.contains(other)).collect();
+
+    let mut split = anm.clone();
+    split.remove_clumps(&rest);
+    if !keep_other_entries {
+        split.remove_other_entries();
+    }
+
+    ClumpAnm {
+        position,
+        anm: split,
+        removed_indices: removed_indices(anm, &positions, keep_other_entries),
+    }
+}
+
+/// Indices of `anm` left out of a copy keeping only the clumps at `positions` and, if `keep_other_entries`
+/// is set, the other entries, in ascending order. The chunk maps at these indices are the ones to drop
+/// from the page of that copy, like the anm and DMG anm [`split_clumps`] makes.
+pub fn removed_indices(anm: &NuccAnm, positions: &[usize], keep_other_entries: bool) -> Vec<u32> {
+    let mut kept_indices: HashSet<u32> = positions.iter().filter_map(|position| anm.clumps.get(*position)).flat_map(clump_indices).collect();
+    if keep_other_entries {
+        kept_indices.extend(anm.other_entries_indices.iter().copied());
+    }
+
+    let mut removed_indices: Vec<u32> = anm
+        .clumps
+        .iter()
+        .flat_map(clump_indices)
+        .chain(anm.other_entries_indices.iter().copied())
+        .filter(|index| !kept_indices.contains(index))
+        .collect();
+    removed_indices.sort_unstable();
+    removed_indices.dedup();
+
+    removed_indices
+}
+
+impl NuccAnm {
+    /// Removes the clumps at `positions` along with the coord parents and entries on them. Indices shared
+    /// with the remaining clumps or other entries are kept.
+    pub fn remove_clumps(&mut self, positions: &[usize]) {
+        let (removed, kept): (Vec<_>, Vec<_>) = self
+            .clumps
+            .iter()
+            .enumerate()
+            .partition(|(position, _)| positions.contains(position));
+
+        let kept_indices: HashSet<u32> = kept
+            .iter()
+            .flat_map(|(_, clump)| clump_indices(clump))
+            .chain(self.other_entries_indices.iter().copied())
+            .collect();
+
+        let removed_indices: Vec<u32> = removed
+            .iter()
+            .flat_map(|(_, clump)| clump_indices(clump))
+            .filter(|index| !kept_indices.contains(index))
+            .collect();
+
+        let new_positions = positions_after_removal(self.clumps.len(), positions);
+
+        let mut position = 0;
+        self.clumps.retain(|_| {
+            position += 1;
+            !positions.contains(&(position - 1))
+        });
+
+        self.remove_indices(removed_indices);
+        self.move_coords(|clump_index| match clump_index {
+            -1 => Some(-1),
+            _ => new_positions.get(clump_index as usize).copied().flatten(),
+        });
+    }
+
+    /// Removes the other entries (cameras, lights) and their indices, along with the coord parents and
+    /// entries on them.
+    pub fn remove_other_entries(&mut self) {
+        let kept_indices: HashSet<u32> = self.clumps.iter().flat_map(clump_indices).collect();
+
+        let removed_indices: Vec<u32> = self
+            .other_entries_indices
+            .drain(..)
+            .filter(|index| !kept_indices.contains(index))
+            .collect();
+
+        self.other_entry_count = 0;
+        self.other_index_count = 0;
+
+        self.remove_indices(removed_indices);
+        self.move_coords(|clump_index| (clump_index >= 0).then_some(clump_index));
+    }
+
+    /// Inserts the clumps of `anm`, with the coord parents and entries on them, before the clump at
+    /// `position` (or after the last clump). Their indices take the place of the indices of the clump at
+    /// `position`, moving every later index up. Other entries of `anm` are left out.
+    pub fn insert_clumps(&mut self, position: usize, anm: NuccAnm) {
+        let position = position.min(self.clumps.len());
+
+        let mut inserted_indices: Vec<u32> = anm.clumps.iter().flat_map(clump_indices).collect();
+        inserted_indices.sort_unstable();
+        inserted_indices.dedup();
+
+        let base = match self.clumps.get(position) {
+            Some(clump) => clump_indices(clump).min().unwrap_or(0),
+            None => self.clumps.iter().flat_map(clump_indices).max().map_or(0, |index| index + 1),
+        };
+
+        let offset = inserted_indices.len() as u32;
+        self.remap_indices(|index| {
+            if *index >= base {
+                *index += offset;
+            }
+        });
+
+        let inserted_count = anm.clumps.len() as i16;
+        self.move_coords(|clump_index| match clump_index {
+            -1 => Some(-1),
+            _ if clump_index >= position as i16 => Some(clump_index + inserted_count),
+            _ => Some(clump_index),
+        });
+
+        // The inserted indices keep their order, packed together from `base`
+        let rebase = |index: &mut u32| *index = base + inserted_indices.binary_search(index).unwrap_or(0) as u32;
+
+        let mut clumps = anm.clumps;
+        for clump in &mut clumps {
+            rebase(&mut clump.clump_index);
+            clump.bone_material_indices.iter_mut().for_each(rebase);
+            clump.model_indices.iter_mut().for_each(rebase);
+        }
+        self.clumps.splice(position..position, clumps);
+
+        let moved = |coord: &mut AnmCoord| {
+            if coord.clump_index < 0 {
+                return false;
+            }
+
+            coord.clump_index += position as i16;
+            true
+        };
+
+        let mut coord_parents = anm.coord_parents;
+        coord_parents.retain_mut(|coord_parent| moved(&mut coord_parent.parent) && moved(&mut coord_parent.child));
+        self.coord_parents.extend(coord_parents);
+
+        let mut entries = anm.entries;
+        entries.retain_mut(|entry| moved(&mut entry.coord));
+        self.entries.extend(entries);
+
+        self.update_counts();
+    }
+
+    /// Puts the clump at position `order[i]` at position `i`. The clumps keep their indices, only the
+    /// clump positions in the coord parents and entries change.
+    pub fn reorder_clumps(&mut self, order: &[usize]) -> Result<()> {
+        let invalid_order = || Error::InvalidAnm(format!("{:?} is not an order of {} clumps", order, self.clumps.len()));
+
+        if order.len() != self.clumps.len() {
+            return Err(invalid_order());
+        }
+
+        let mut new_positions = vec![None; self.clumps.len()];
+        for (new_position, &position) in order.iter().enumerate() {
+            match new_positions.get_mut(position) {
+                Some(slot @ None) => *slot = Some(new_position as i16),
+                _ => return Err(invalid_order()),
+            }
+        }
+
+        let mut clumps: Vec<Option<AnmClump>> = self.clumps.drain(..).map(Some).collect();
+        self.clumps = order.iter().filter_map(|&position| clumps[position].take()).collect();
+
+        self.move_coords(|clump_index| match clump_index {
+            -1 => Some(-1),
+            _ => new_positions.get(clump_index as usize).copied().flatten(),
+        });
+
+        Ok(())
+    }
+
+    /// Checks that the counts match the lists they count, that no index belongs to two clumps or other
+    /// entries, and that every coord points at an existing clump or other entry.
+    pub fn validate(&self) -> Result<()> {
+        let counts = [
+            ("clump count", self.clump_count as usize, self.clumps.len()),
+            ("coord count", self.coord_count as usize, self.coord_parents.len()),
+            ("entry count", self.entry_count as usize, self.entries.len()),
+            (
+                "other entry and index count",
+                self.other_entry_count as usize + self.other_index_count as usize,
+                self.other_entries_indices.len(),
+            ),
+        ];
+
+        let clump_counts = self.clumps.iter().flat_map(|clump| {
+            [
+                ("clump bone/material count", clump.bone_material_count as usize, clump.bone_material_indices.len()),
+                ("clump model count", clump.model_count as usize, clump.model_indices.len()),
+            ]
+        });
+
+        for (what, expected, found) in counts.into_iter().chain(clump_counts) {
+            if expected != found {
+                return Err(Error::CountMismatch { what: what.to_string(), expected, found });
+            }
+        }
+
+        let mut indices = HashSet::new();
+        for index in self.clumps.iter().flat_map(clump_indices).chain(self.other_entries_indices.iter().copied()) {
+            if !indices.insert(index) {
+                return Err(Error::InvalidAnm(format!("index {} is used more than once", index)));
+            }
+        }
+
+        let coords = self
+            .coord_parents
+            .iter()
+            .flat_map(|coord_parent| [&coord_parent.parent, &coord_parent.child])
+            .chain(self.entries.iter().map(|entry| &entry.coord));
+
+        for coord in coords {
+            let coord_count = match coord.clump_index {
+                -1 => Some(self.other_entries_indices.len()),
+                clump_index => usize::try_from(clump_index)
+                    .ok()
+                    .and_then(|position| self.clumps.get(position))
+                    .map(|clump| clump.bone_material_indices.len() + clump.model_indices.len()),
+            };
+
+            match coord_count {
+                Some(count) if (coord.coord_index as usize) < count => {}
+                Some(_) => return Err(Error::InvalidAnm(format!("coord ({}, {}) is out of range", coord.clump_index, coord.coord_index))),
+                None => return Err(Error::InvalidAnm(format!("coord ({}, {}) points at no clump", coord.clump_index, coord.coord_index))),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Takes `removed` out of the index space, moving every later index down.
+    fn remove_indices(&mut self, mut removed: Vec<u32>) {
+        removed.sort_unstable();
+        removed.dedup();
+
+        self.remap_indices(|index| *index -= removed.partition_point(|removed| *removed < *index) as u32);
+    }
+
+    fn remap_indices(&mut self, remap: impl Fn(&mut u32)) {
+        for clump in &mut self.clumps {
+            remap(&mut clump.clump_index);
+            clump.bone_material_indices.iter_mut().for_each(&remap);
+            clump.model_indices.iter_mut().for_each(&remap);
+        }
+
+        self.other_entries_indices.iter_mut().for_each(&remap);
+    }
+
+    /// Moves the clump positions of every coord parent and entry, dropping the ones moved to `None`.
+    fn move_coords(&mut self, new_position: impl Fn(i16) -> Option<i16>) {
+        let moved = |coord: &mut AnmCoord| match new_position(coord.clump_index) {
+            Some(position) => {
+                coord.clump_index = position;
+                true
+            }
+            None => false,
+        };
+
+        self.coord_parents.retain_mut(|coord_parent| moved(&mut coord_parent.parent) && moved(&mut coord_parent.child));
+
+        self.entries.retain_mut(|entry| moved(&mut entry.coord));
+
+        self.update_counts();
+    }
+
+    fn update_counts(&mut self) {
+        self.clump_count = self.clumps.len() as u16;
+        self.coord_count = self.coord_parents.len() as u16;
+        self.entry_count = self.entries.len() as u16;
+    }
+}
+
+/// New position of every clump after the clumps at `positions` are removed.
+fn positions_after_removal(len: usize, positions: &[usize]) -> Vec<Option<i16>> {
+    let mut next_position = 0;
+
+    (0..len)
+        .map(|position| {
+            if positions.contains(&position) {
+                return None;
+            }
+
+            next_position += 1;
+            Some(next_position - 1)
+        })
+        .collect()
+}
+
+/// The clump's own index followed by its bone/material and model indices.
+fn clump_indices(clump: &AnmClump) -> impl Iterator<Item = u32> + '_ {
+    std::iter::once(clump.clump_index)
+        .chain(clump.bone_material_indices.iter().copied())
+        .chain(clump.model_indices.iter().copied())
+}
@@ -1,902 +1,1167 @@
-use std::{
-    path::Path,
-    fs::File,
-    io::{BufReader, Error},
-    mem, vec,
-};
-
-use hashbrown::HashMap;
-use rayon::prelude::*; // Parallel iterator
-use binrw::BinReaderExt;
-use indicatif::{ProgressBar, ProgressStyle};
-
-
-use crate::structure::anm::{NuccAnm, AnmEntry, AnmEntryFormat, AnmCurveFormat, Curve, CurveHeader, AnmClump};
-use crate::structure::anmstrm::{NuccAnmStrm, NuccAnmStrmFrame, AnmStrmEntry, Entry};
-use crate::structure::anm_utils::*;
-
-
-const SCALE_COMPRESS: f32 = 4096.0;
-const QUAT_COMPRESS: f32 = 32767.0;
-const RGB_CONVERT: f32 = 255.0;
-
-
-/// Converts ANMSTRM data into a vector of ANM data (ANM and DMG ANM)
-pub fn convert_anmstrm(anmstrm: &NuccAnmStrm, mut anmstrm_frame_filepaths: Vec<String>) -> Result<Vec<NuccAnm>, Error> {
-    anmstrm_frame_filepaths.sort_by(|a, b| {
-        let a = Path::new(a).file_name().unwrap().to_str().unwrap();
-        let b = Path::new(b).file_name().unwrap().to_str().unwrap();
-
-        let a = a.split("_").collect::<Vec<&str>>()[1]
-            .split(".")
-            .collect::<Vec<&str>>()[0]
-            .parse::<u32>()
-            .unwrap();
-
-        let b = b.split("_").collect::<Vec<&str>>()[1]
-            .split(".")
-            .collect::<Vec<&str>>()[0]
-            .parse::<u32>()
-            .unwrap();
-
-        a.cmp(&b)
-    });
-
-    let anmstrmframes = parse_anmstrm_frames(anmstrm_frame_filepaths)?;
-    let anmstrm_entries = build_anmstrm_entries_map(&anmstrmframes)?;
-
-    let anm_entries = convert_entries(anmstrm_entries);
-
-
-    println!("building anm files...");
-    let mut anm = build_anm(anmstrm, anm_entries)?;
-    let dmg_anm = build_dmg_anm(&mut anm, anmstrm); // Consumes the original anm to create the anm from the dmg clump and mutates the original anm
-
-    let mut anms: Vec<NuccAnm> = Vec::with_capacity(2);
-    
-    anms.push(anm);
-    anms.push(dmg_anm);
- 
-
-    Ok(anms)
-}
-
-/// Parses ANMSTRM frame files and returns a vector of parsed frames.
-fn parse_anmstrm_frames(anmstrm_frame_filepaths: Vec<String>) -> Result<Vec<NuccAnmStrmFrame>, Error> {
-    let mut anmstrmframes: Vec<NuccAnmStrmFrame> = Vec::with_capacity(anmstrm_frame_filepaths.len());
-
-    let pb = ProgressBar::new(anmstrm_frame_filepaths.len() as u64);
-    pb.set_style(ProgressStyle::with_template("parsing anmstrm...    {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}")
-    .unwrap()
-    .progress_chars("||-"));
-
-    for (i,file) in anmstrm_frame_filepaths.iter().enumerate() {
-        let mut buf = BufReader::new(File::open(file)?);
-        anmstrmframes.push(buf.read_be::<NuccAnmStrmFrame>().unwrap());
-        pb.set_message(format!("parsed #{}", i + 1));
-        pb.inc(1);
-    }
-    pb.finish_with_message("done");
-    
-    Ok(anmstrmframes)
-}
-
-/// Builds entries from ANMSTRM frames and returns a vector of entries.
-fn build_entries_from_frames(anmstrmframes: &Vec<NuccAnmStrmFrame>) -> Vec<Vec<AnmStrmEntry>> {
-    let entry_count = anmstrmframes.first().map_or(0, |frame| frame.entry_count);
-
-    let mut anmstrm_entries: Vec<Vec<AnmStrmEntry>> = vec![Vec::new(); entry_count as usize];
-
-    anmstrm_entries.par_iter_mut().for_each(|entry| *entry = Vec::new());
-
-    let pb = ProgressBar::new(anmstrmframes.len() as u64);
-    pb.set_style(ProgressStyle::with_template("gathering frames...   {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}")
-    .unwrap()
-    .progress_chars("||-"));
-
-    for (i, anmstrmframe) in anmstrmframes.iter().enumerate() {
-        for (entry_index, entry) in anmstrmframe.entries.iter().enumerate() {
-            match &entry.entry_data {
-                Entry::Bone(_) | Entry::Material(_) | 
-                Entry::Camera(_) | Entry::LightDirc(_) | 
-                Entry::LightPoint(_) | Entry::Ambient(_)
-                 => {
-                    anmstrm_entries[entry_index].push(entry.clone());
-                }
-                _ => {}
-            }    
-        }
-
-        pb.set_message(format!("frame #{}", i + 1));
-        pb.inc(1);
-    }
-
-    pb.finish_with_message("done");
-
-   
-    anmstrm_entries
-}
-
-/// Builds a map of ANMSTRM entries, where the key is the entry index.
-fn build_anmstrm_entries_map(anmstrmframes: &Vec<NuccAnmStrmFrame>) -> Result<HashMap<u16, Vec<AnmStrmEntry>>, Error> {
-    let anmstrm_entries = build_entries_from_frames(anmstrmframes);
-
-    Ok(anmstrm_entries
-        .into_iter()
-        .enumerate()
-        .filter(|(_, entries)| !entries.is_empty())
-        .map(|(entry_index, entries)| (entry_index.try_into().unwrap(), entries))
-        .collect()) 
-}
-
-/// Converts ANMSTRM entries map into a vector of ANM entries.
-fn convert_entries(anmstrm_entries: HashMap<u16, Vec<AnmStrmEntry>>) -> Vec<AnmEntry> {
-    let mut anm_entries: Vec<AnmEntry> = Vec::with_capacity(anmstrm_entries.len());
-
-    let pb = ProgressBar::new(anmstrm_entries.len() as u64);
-    pb.set_style(ProgressStyle::with_template("converting entries... {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}")
-    .unwrap()
-    .progress_chars("||-"));
-
-    for (i, entry) in &anmstrm_entries {
-        let mut anm_entry = AnmEntry {
-            coord: entry[0].coord.clone(),
-            entry_format: 0,
-            curve_count: 0,
-            curve_headers: Vec::new(),
-            curves: Vec::new(),
-        };
-
-        let mut curve_index = 0; // Index for the curve headers
-
-        for (frame, anmstrm_entry) in entry.iter().enumerate().map(|(frame, entry)| (frame * 100, entry)) {
-            match &anmstrm_entry.entry_data {
-
-                // ----------------- BONE -----------------
-                Entry::Bone(anmstrm_entry_bone) => {
-                    anm_entry.entry_format = AnmEntryFormat::BONE as u16;
-
-                    if frame == 0 {
-                        // Create curves and curve headers for location, rotation, scale, and toggled
-                        anm_entry.curves.push(Curve::KeyframeVector3(Vec::new()));
-                        anm_entry.curves.push(Curve::QuaternionShort(Vec::new()));
-                        anm_entry.curves.push(Curve::Vector3Short(Vec::new()));
-                        anm_entry.curves.push(Curve::Float(Vec::new()));
-    
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index,
-                            curve_format: AnmCurveFormat::INT1_FLOAT3 as u16, // Curve format for keyframe loc
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 1,
-                            curve_format: AnmCurveFormat::SHORT4 as u16, // Curve format for rot
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 2,
-                            curve_format: AnmCurveFormat::SHORT3 as u16, // Curve format for scale
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 3,
-                            curve_format: AnmCurveFormat::FLOAT1 as u16, // Curve format for toggled
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-    
-                       curve_index += 4;
-                    }
-                    // Push keyframes for location, rotation, scale, and toggled
-                    if let Curve::KeyframeVector3(location_keyframes) = &mut anm_entry.curves[0] {
-                        location_keyframes.push(KeyframeVector3 {
-                            frame: frame as i32,
-                            value: anmstrm_entry_bone.location.clone(),
-                        });
-                    }
-                    if let Curve::QuaternionShort(rotation_keyframes) = &mut anm_entry.curves[1] {
-                        rotation_keyframes.push(QuaternionShort {
-                            x: (anmstrm_entry_bone.rotation.x * QUAT_COMPRESS) as i16,
-                            y: (anmstrm_entry_bone.rotation.y * QUAT_COMPRESS) as i16,
-                            z: (anmstrm_entry_bone.rotation.z * QUAT_COMPRESS) as i16,
-                            w: (anmstrm_entry_bone.rotation.w * QUAT_COMPRESS) as i16,
-                            
-                        });
-                    }
-                    if let Curve::Vector3Short(scale_keyframes) = &mut anm_entry.curves[2] {
-                        scale_keyframes.push(Vector3Short {
-                            x: (anmstrm_entry_bone.scale.x * SCALE_COMPRESS) as i16,
-                            y: (anmstrm_entry_bone.scale.y * SCALE_COMPRESS) as i16,
-                            z: (anmstrm_entry_bone.scale.z * SCALE_COMPRESS) as i16,
-                        });
-                    }
-
-                    if let Curve::Float(toggled_value) = &mut anm_entry.curves[3] {
-                        toggled_value.push(anmstrm_entry_bone.toggled);
-                    }
-                }
-                
-                // ----------------- MATERIAL -----------------
-                Entry::Material(anmstrm_entry_material) => {
-                    anm_entry.entry_format = AnmEntryFormat::MATERIAL as u16;
-
-                    if frame == 0 {
-                        anm_entry.curves.push(Curve::KeyframeFloat(Vec::new()));
-                        anm_entry.curves.push(Curve::KeyframeFloat(Vec::new()));
-
-                        anm_entry.curves.push(Curve::Float(Vec::new()));
-                        anm_entry.curves.push(Curve::Float(Vec::new()));
-                        anm_entry.curves.push(Curve::Float(Vec::new()));
-                        anm_entry.curves.push(Curve::Float(Vec::new()));
-                        anm_entry.curves.push(Curve::Float(Vec::new()));
-                        anm_entry.curves.push(Curve::Float(Vec::new()));
-
-                        anm_entry.curves.push(Curve::KeyframeFloat(Vec::new()));
-                        anm_entry.curves.push(Curve::KeyframeFloat(Vec::new()));
-
-                        anm_entry.curves.push(Curve::Float(Vec::new()));
-                        anm_entry.curves.push(Curve::Float(Vec::new()));
-                        anm_entry.curves.push(Curve::Float(Vec::new()));
-                        anm_entry.curves.push(Curve::Float(Vec::new()));
-                        anm_entry.curves.push(Curve::Float(Vec::new()));
-                        anm_entry.curves.push(Curve::Float(Vec::new()));
-                        anm_entry.curves.push(Curve::Float(Vec::new())); 
-                        anm_entry.curves.push(Curve::Float(Vec::new()));
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index,
-                            curve_format: AnmCurveFormat::INT1_FLOAT1 as u16, // Curve format for KeyframeFloat
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 1,
-                            curve_format: AnmCurveFormat::INT1_FLOAT1 as u16, // Curve format for KeyframeFloat
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 2,
-                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 3,
-                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 4,
-                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 5,
-                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 6,
-                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 7,
-                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 8,
-                            curve_format: AnmCurveFormat::INT1_FLOAT1 as u16, // Curve format for KeyframeFloat
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 9,
-                            curve_format: AnmCurveFormat::INT1_FLOAT1 as u16, // Curve format for KeyframeFloat
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index:  curve_index + 10,
-                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                             curve_index: curve_index + 11,
-                             curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
-                             frame_count: 0,
-                             curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                             curve_index: curve_index + 12,
-                             curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
-                             frame_count: 0,
-                             curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                             curve_index: curve_index + 13,
-                             curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
-                             frame_count: 0,
-                             curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                             curve_index: curve_index + 14,
-                             curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
-                             frame_count: 0,
-                             curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                             curve_index: curve_index + 15,
-                             curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
-                             frame_count: 0,
-                             curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                             curve_index: curve_index + 16,
-                             curve_format: AnmCurveFormat::FLOAT1 as u16, // Curve format for toggle
-                             frame_count: 0,
-                             curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                             curve_index: curve_index + 17,
-                             curve_format: AnmCurveFormat::FLOAT1 as u16, // Curve format for toggle
-                             frame_count: 0,
-                             curve_size: 0,
-                        });
-
-                        curve_index += 18;
-                    }
-
-                    // Push keyframes for material color and toggled
-                    if let Curve::KeyframeFloat(color_values) = &mut anm_entry.curves[0] {
-                        color_values.push(KeyframeFloat {
-                            frame: frame as i32,
-                            value: anmstrm_entry_material.ambient_color[0],
-                        });
-
-                        color_values.push(KeyframeFloat {
-                            frame: frame as i32 + 50,
-                            value: anmstrm_entry_material.ambient_color[0],
-                        });
-                    }
-
-                    if let Curve::KeyframeFloat(color_values) = &mut anm_entry.curves[1] {
-                        color_values.push(KeyframeFloat {
-                            frame: frame as i32,
-                            value: anmstrm_entry_material.ambient_color[1],
-                        });
-
-                        color_values.push(KeyframeFloat {
-                            frame: frame as i32 + 50,
-                            value: anmstrm_entry_material.ambient_color[1],
-                        });
-                    }
-
-                    if let Curve::Float(color_values) = &mut anm_entry.curves[2] {
-                        color_values.push(anmstrm_entry_material.ambient_color[2]);
-                    }
-
-                    if let Curve::Float(color_values) = &mut anm_entry.curves[3] {
-                        color_values.push(anmstrm_entry_material.ambient_color[3]);
-                    }
-
-
-                    if let Curve::Float(color_values) = &mut anm_entry.curves[4] {
-                        color_values.push(anmstrm_entry_material.ambient_color[4]);
-                    }
-
-                    if let Curve::Float(color_values) = &mut anm_entry.curves[5] {
-                        color_values.push(anmstrm_entry_material.ambient_color[5]);
-                    }
-
-                    if let Curve::Float(color_values) = &mut anm_entry.curves[6] {
-                        color_values.push(anmstrm_entry_material.ambient_color[6]);
-                    }
-
-                    if let Curve::Float(color_values) = &mut anm_entry.curves[7] {
-                        color_values.push(anmstrm_entry_material.ambient_color[7]);
-                    }
-
-                    if let Curve::KeyframeFloat(color_values) = &mut anm_entry.curves[8] {
-                        color_values.push(KeyframeFloat {
-                            frame: frame as i32,
-                            value: anmstrm_entry_material.ambient_color[8],
-                        });
-
-                        color_values.push(KeyframeFloat {
-                            frame: frame as i32 + 50,
-                            value: anmstrm_entry_material.ambient_color[8],
-                        });
-                    }
-
-                    if let Curve::KeyframeFloat(color_values) = &mut anm_entry.curves[9] {
-                        color_values.push(KeyframeFloat {
-                            frame: frame as i32,
-                            value: anmstrm_entry_material.ambient_color[9],
-                        });
-
-                        color_values.push(KeyframeFloat {
-                            frame: frame as i32 + 50,
-                            value: anmstrm_entry_material.ambient_color[9],
-                        });
-                    }
-
-                    if let Curve::Float(color_values) = &mut anm_entry.curves[10] {
-                        color_values.push(anmstrm_entry_material.ambient_color[10]);
-                    }
-
-                    if let Curve::Float(color_values) = &mut anm_entry.curves[11] {
-                        color_values.push(anmstrm_entry_material.ambient_color[11]);
-                    }
-
-                    if let Curve::Float(color_values) = &mut anm_entry.curves[12] {
-                        color_values.push(anmstrm_entry_material.ambient_color[12]);
-                    }
-
-
-                    if let Curve::Float(color_values) = &mut anm_entry.curves[13] {
-                        color_values.push(anmstrm_entry_material.ambient_color[13]);
-                    }
-
-                    if let Curve::Float(color_values) = &mut anm_entry.curves[14] {
-                        color_values.push(anmstrm_entry_material.ambient_color[14]);
-                    }
-
-
-                    if let Curve::Float(color_values) = &mut anm_entry.curves[15] {
-                        color_values.push(anmstrm_entry_material.ambient_color[15]);
-                    }
-
-
-                    if let Curve::Float(color_values) = &mut anm_entry.curves[16] {
-                        color_values.push(0.0);
-                    }
-
-                    if let Curve::Float(color_values) = &mut anm_entry.curves[17] {
-                        color_values.push(1.0);
-                    }                       
-                }
-
-                Entry::Camera(anmstrm_entry_camera) => {
-                    anm_entry.entry_format = AnmEntryFormat::CAMERA as u16;
-
-                    if frame == 0 {
-                        // Create curves and curve headers for location, rotation, fov
-                        anm_entry.curves.push(Curve::KeyframeVector3(Vec::new()));
-                        anm_entry.curves.push(Curve::QuaternionShort(Vec::new()));
-                        anm_entry.curves.push(Curve::KeyframeFloat(Vec::new()));
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index,
-                            curve_format: AnmCurveFormat::INT1_FLOAT3 as u16, // Curve format for KeyframeVector3
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-    
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 1,
-                            curve_format: AnmCurveFormat::SHORT4 as u16, // Curve format for Short4
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-    
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 2,
-                            curve_format: AnmCurveFormat::INT1_FLOAT1 as u16, // Curve format for KeyframeFloat
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-    
-                        curve_index += 3;
-                    }
-
-                    // Push keyframes for location, rotation, fov
-                    if let Curve::KeyframeVector3(location_keyframes) = &mut anm_entry.curves[0] {
-                        location_keyframes.push(KeyframeVector3 {
-                            frame: frame as i32,
-                            value: anmstrm_entry_camera.location.clone(),
-                        });
-                    }
-                    if let Curve::QuaternionShort(rotation_keyframes) = &mut anm_entry.curves[1] {
-                        rotation_keyframes.push(QuaternionShort {
-                            x: (anmstrm_entry_camera.rotation.x * QUAT_COMPRESS) as i16,
-                            y: (anmstrm_entry_camera.rotation.y * QUAT_COMPRESS) as i16,
-                            z: (anmstrm_entry_camera.rotation.z * QUAT_COMPRESS) as i16,
-                            w: (anmstrm_entry_camera.rotation.w * QUAT_COMPRESS) as i16,
-                        });
-                    }
-                    if let Curve::KeyframeFloat(fov_keyframes) = &mut anm_entry.curves[2] {
-                        fov_keyframes.push(KeyframeFloat {
-                            frame: frame as i32,
-                            value: anmstrm_entry_camera.fov,
-                        });
-                    }
-                }
-
-
-                // ----------------- LIGHTDIRC -----------------
-                Entry::LightDirc(anmstrm_entry_lightdir) => {
-                    anm_entry.entry_format = AnmEntryFormat::LIGHTDIRC as u16;
-
-                    if frame == 0 {
-                        anm_entry.curves.push(Curve::RGB(Vec::new()));
-                        anm_entry.curves.push(Curve::Float(Vec::new()));
-                        anm_entry.curves.push(Curve::QuaternionShort(Vec::new()));
-                        
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index,
-                            curve_format: AnmCurveFormat::BYTE3 as u16, // Curve format for RGB
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 1,
-                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 2,
-                            curve_format: AnmCurveFormat::SHORT4 as u16, // Curve format for KeyframeVector4
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-    
-                        curve_index += 3;
-                    }
-                    // Push keyframes for color, light strength, rotations
-                    if let Curve::RGB(color_values) = &mut anm_entry.curves[0] {
-                        color_values.push(RGB {
-                            r: (anmstrm_entry_lightdir.color.x * RGB_CONVERT) as u8,
-                            g: (anmstrm_entry_lightdir.color.y * RGB_CONVERT) as u8,
-                            b: (anmstrm_entry_lightdir.color.z * RGB_CONVERT) as u8,
-                        });     
-                    }
-
-                    if let Curve::Float(strength_values) = &mut anm_entry.curves[1] {
-                        strength_values.push(anmstrm_entry_lightdir.intensity);
-                    }
-
-                    if let Curve::QuaternionShort(rotation_keyframes) = &mut anm_entry.curves[2] {
-                        rotation_keyframes.push(QuaternionShort {
-                            x: (anmstrm_entry_lightdir.direction.x * QUAT_COMPRESS) as i16,
-                            y: (anmstrm_entry_lightdir.direction.y * QUAT_COMPRESS) as i16,
-                            z: (anmstrm_entry_lightdir.direction.z * QUAT_COMPRESS) as i16,
-                            w: (anmstrm_entry_lightdir.direction.w * QUAT_COMPRESS) as i16,
-                        });
-                    }
-                }
-
-                // ----------------- LIGHT POINT -----------------
-                Entry::LightPoint(anm_entry_lightpoint) => {
-                    anm_entry.entry_format = AnmEntryFormat::LIGHTPOINT as u16;
-
-                    if frame == 0 {
-                        // Create curves and curve headers for color, light strength, location
-                        anm_entry.curves.push(Curve::RGB(Vec::new()));
-                        anm_entry.curves.push(Curve::KeyframeVector3(Vec::new()));
-                        anm_entry.curves.push(Curve::Float(Vec::new()));
-                        anm_entry.curves.push(Curve::Float(Vec::new()));
-                        anm_entry.curves.push(Curve::Float(Vec::new()));
-
-                        
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index,
-                            curve_format: AnmCurveFormat::BYTE3 as u16, // Curve format for RGB
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 1,
-                            curve_format: AnmCurveFormat::INT1_FLOAT3 as u16, // Curve format for KeyframeVector3
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 2,
-                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 3,
-                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 4,
-                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-    
-                        curve_index += 5;
-                    }
-
-                    // Push keyframes for color, light strength, location
-                    if let Curve::RGB(color_values) = &mut anm_entry.curves[0] {
-                        color_values.push(RGB {
-                            r: (anm_entry_lightpoint.color.x * RGB_CONVERT) as u8,
-                            g: (anm_entry_lightpoint.color.y * RGB_CONVERT) as u8,
-                            b: (anm_entry_lightpoint.color.z * RGB_CONVERT) as u8,
-                        });     
-                    }
-
-                    if let Curve::KeyframeVector3(location_keyframes) = &mut anm_entry.curves[1] {
-                        location_keyframes.push(KeyframeVector3 {
-                            frame: frame as i32,
-                            value: anm_entry_lightpoint.position.clone(),
-                        });
-                    }
-
-                    if let Curve::Float(intensity_values) = &mut anm_entry.curves[2] {
-                        intensity_values.push(anm_entry_lightpoint.intensity);
-                    }
-
-                    if let Curve::Float(radius_values) = &mut anm_entry.curves[3] {
-                        radius_values.push(anm_entry_lightpoint.radius);
-                    }
-
-                    if let Curve::Float(falloff_values) = &mut anm_entry.curves[4] {
-                        falloff_values.push(anm_entry_lightpoint.falloff);
-                    }
-                }
-
-                Entry::Ambient(anm_entry_ambient) => {
-                    anm_entry.entry_format = AnmEntryFormat::AMBIENT as u16;
-
-                    if frame == 0 {
-                        // Create curves and curve headers for color, light strength
-                        anm_entry.curves.push(Curve::RGB(Vec::new()));
-                        anm_entry.curves.push(Curve::Float(Vec::new()));
-                        
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index,
-                            curve_format: AnmCurveFormat::BYTE3 as u16, // Curve format for RGB
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-
-                        anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index + 1,
-                            curve_format: AnmCurveFormat::FLOAT1ALT2 as u16, // Curve format for Float
-                            frame_count: 0,
-                            curve_size: 0,
-                        });
-
-
-                        curve_index += 2; 
-                    }
-
-                    // Push keyframes for color, light strength
-                    if let Curve::RGB(color_values) = &mut anm_entry.curves[0] {
-                        color_values.push(RGB {
-                            r: (anm_entry_ambient.color.x * RGB_CONVERT) as u8,
-                            g: (anm_entry_ambient.color.y * RGB_CONVERT) as u8,
-                            b: (anm_entry_ambient.color.z * RGB_CONVERT) as u8,
-                        });     
-                    }
-
-                    if let Curve::Float(strength_values) = &mut anm_entry.curves[1] {
-                        strength_values.push(anm_entry_ambient.intensity);
-                    }
-
-                }
-                _ => {
-                    // Handle other entry types if necessary
-                    // ...
-                }         
-            }
- 
-        }
-        // Update curves and headers for necessary changes
-        for (curve, curve_header) in anm_entry.curves.iter_mut().zip(&mut anm_entry.curve_headers) {
-            if curve.has_keyframes() {
-                curve.append_null_keyframe();
-                curve_header.frame_count += 1;
-            }
-
-            //If curve is RBG we need to pad the color values to be a multiple of 4
-            if curve.get_curve_format() == AnmCurveFormat::BYTE3 as u16 {
-                curve.pad_color_values();    
-            }
-            // Make sure we update the frame count and curve size for the curves
-            curve_header.curve_size += mem::size_of_val(&curve) as u16;
-            curve_header.frame_count = curve.get_frame_count() as u16;
-        }
-        
-        anm_entry.curve_count = anm_entry.curves.len() as u16;
-        anm_entries.push(anm_entry);
-        pb.set_message(format!("entry #{}", i + 1));
-        pb.inc(1);
-        
-    }
-
-    
-    pb.finish_with_message("done");
-
-    anm_entries
-    
-}
-
-/// Builds an ANM object from ANMSTRM and converted ANM entries.
-pub fn build_anm(anmstrm: &NuccAnmStrm, anm_entries: Vec<AnmEntry>) -> Result<NuccAnm, Error> {
-
-    // sort the entries by coord index
-    let mut anm_entries = anm_entries;
-    anm_entries.sort_by(|a, b| a.coord.coord_index.cmp(&b.coord.coord_index));
-
-
-    // We map the clumps to a new vector of clumps to avoid cloning the clumps
-    let anm_clumps: Vec<AnmClump> = anmstrm.clumps.clone().into_par_iter().map(|clump| AnmClump {
-        clump_index: clump.clump_index,
-        bone_material_count: clump.bone_material_count,
-        model_count: clump.model_count,
-        bone_material_indices: clump.bone_material_indices,
-        model_indices: clump.model_indices
-    }).collect();
-
-    let anm = NuccAnm {
-        anm_length: anmstrm.anm_length,
-        frame_size: anmstrm.frame_size,
-        entry_count: anm_entries.len() as u16,
-        looped: anmstrm.is_looped,
-        clump_count: anmstrm.clump_count,
-        other_entry_count: anmstrm.other_entry_count,
-        other_index_count: anmstrm.other_index_count,
-        coord_count: anmstrm.coord_count,
-        clumps: anm_clumps,
-        other_entries_indices: anmstrm.other_entries_indices.clone(),
-        coord_parents: anmstrm.coord_parents.clone(),
-        entries: anm_entries,
-    };
-
-    Ok(anm)
-}
-
-/// Builds a DMG ANM object from the ANM and ANMSTRM.
-fn build_dmg_anm(anm: &mut NuccAnm, anmstrm: &NuccAnmStrm) -> NuccAnm {
-    // ----------------- Clumps -----------------
-    let mut clumps = anm.clumps.clone();
-
-    let dmg_clump_index = clumps.par_iter().position_any(|clump| clump.bone_material_count == 97).unwrap_or(0);
-
-    let dmg_clump = clumps[dmg_clump_index].clone();
-
-    clumps.retain(|clump| clump.clump_index == dmg_clump.clump_index as u32); // Remove unnecessary clumps for the DMG ANM
-
-    let subtractor = dmg_clump.clump_index as u32;
-
-    for clump in &mut clumps {
-        clump.clump_index -= subtractor;
-
-        for index in &mut clump.bone_material_indices {
-            *index -= subtractor;
-        }
-        for index in &mut clump.model_indices {
-            *index -= subtractor;
-        }
-        
-    }
-
-    // ----------------- Coords ----------------- //
-    let mut coord_parents = anm.coord_parents.clone();
-    // Remove unnecessary coord parents for the DMG ANM
-    coord_parents.retain(|coord_parent| coord_parent.parent.clump_index == dmg_clump_index as i16); // Only keep the coord parents related to the DMG clump
-
-    // update indices for the DMG coord parents
-    for coord_parent in &mut coord_parents {
-        coord_parent.parent.clump_index -= dmg_clump_index as i16;
-        coord_parent.child.clump_index -= dmg_clump_index as i16;
-    }
-
-
-    // ----------------- Entries ----------------- // 
-    let mut dmg_entries: Vec<AnmEntry> = anm.entries.par_iter()
-        .filter(|entry| entry.coord.clump_index == dmg_clump_index as i16)
-        .cloned()
-        .collect();
-
-    for entry in &mut dmg_entries {
-        entry.coord.clump_index -= dmg_clump_index as i16;
-    }
-
-    dmg_entries.sort_by(|a, b| a.coord.coord_index.cmp(&b.coord.coord_index));
-
-
-    // -----------------DMG anm  ----------------- //
-    let dmg_anm = NuccAnm {
-        anm_length: anmstrm.anm_length,
-        frame_size: anmstrm.frame_size,
-        entry_count: dmg_entries.len() as u16,
-        looped: anmstrm.is_looped,
-        clump_count: clumps.len() as u16,
-        other_entry_count: 0,
-        other_index_count: anmstrm.other_index_count,
-        coord_count: coord_parents.len() as u16,
-        clumps: clumps,
-        other_entries_indices: vec![],
-        coord_parents: coord_parents,
-        entries: dmg_entries,
-    };
-
-
-    // Mutate the original ANM
-    anm.clumps.retain(|clump| clump.clump_index != dmg_clump.clump_index as u32); // Remove the DMG clump from the original ANM
-    anm.clump_count = anm.clumps.len() as u16;
-
-    // we need to edit the clump indices starting from the DMG clump index to the end
-    for clump in &mut anm.clumps {
-        // note we removed 98 indices + clump index so we need to adjust for that
-        if clump.clump_index > dmg_clump.clump_index as u32 {
-            clump.clump_index -= 99;
-
-            for index in &mut clump.bone_material_indices {
-                *index -= 99;
-            }
-
-            for index in &mut clump.model_indices {
-                *index -= 99;
-            }
-        }
-    }
-
-    anm.coord_parents.retain(|coord_parent| coord_parent.parent.clump_index != dmg_clump_index as i16); // Remove the DMG coord parents from the original ANM
-    anm.coord_count = anm.coord_parents.len() as u16;
-
-    for coord in &mut anm.coord_parents {
-            if coord.parent.clump_index > dmg_clump_index as i16 {
-                coord.parent.clump_index -= 1;
-                coord.child.clump_index -= 1;
-            }
-    }
-     
-    anm.entries.retain(|entry| entry.coord.clump_index != dmg_clump_index as i16); // Remove the DMG entries from the original ANM if the clump index matches the DMG clump index
-    anm.entry_count = anm.entries.len() as u16;
-
-
-    // we need to also edit the entry clump indices starting from the DMG clump index to the end
-    for entry in &mut anm.entries {
-        if entry.coord.clump_index > dmg_clump_index as i16 {
-            entry.coord.clump_index -= 1;
-        }
-
-    }
-
-    dmg_anm
-}
\ No newline at end of file
+use std::{
+    fs::File,
+    io::{BufReader, Cursor, Read, Seek},
+    path::Path,
+};
+
+use hashbrown::HashMap;
+use rayon::prelude::*; // Parallel iterator
+use binrw::BinReaderExt;
+use indicatif::{ProgressBar, ProgressStyle};
+
+
+use crate::clumps::{split_clumps, ClumpSelector};
+use crate::error::{Error, Result};
+use crate::optimize::{collapse_constant_curves, drop_rest_entries, reduce_keyframes, KeyframeTolerance};
+use crate::structure::anm::{NuccAnm, AnmEntry, AnmEntryFormat, AnmCurveFormat, Curve, CurveHeader, AnmClump, RAW_CURVE_FORMAT};
+use crate::structure::anmstrm::{NuccAnmStrm, NuccAnmStrmFrame, AnmStrmEntry, Entry};
+use crate::structure::anm_utils::*;
+
+/// Ticks per frame used when the ANMSTRM doesn't give any.
+const DEFAULT_FRAME_TICKS: i32 = 100;
+
+
+/// Reads an ANMSTRM chunk from any seekable reader.
+pub fn read_anmstrm<R: Read + Seek>(reader: &mut R) -> Result<NuccAnmStrm> {
+    reader.read_be::<NuccAnmStrm>().map_err(|err| Error::parse(reader, err))
+}
+
+/// Reads a single ANMSTRM frame chunk from any seekable reader.
+pub fn read_anmstrm_frame<R: Read + Seek>(reader: &mut R) -> Result<NuccAnmStrmFrame> {
+    reader.read_be::<NuccAnmStrmFrame>().map_err(|err| Error::parse(reader, err))
+}
+
+/// Reads an ANMSTRM chunk from a file.
+pub fn read_anmstrm_file<P: AsRef<Path>>(path: P) -> Result<NuccAnmStrm> {
+    let path = path.as_ref();
+    let mut reader = BufReader::new(File::open(path).map_err(|err| Error::from(err).with_path(path))?);
+
+    read_anmstrm(&mut reader).map_err(|err| err.with_path(path))
+}
+
+/// Options controlling how ANMSTRM data is converted.
+#[derive(Debug, Clone)]
+pub struct ConvertOptions {
+    /// Split the DMG clump out of the converted ANM into a second ANM.
+    pub split_dmg: bool,
+    /// The clumps split off when `split_dmg` is set.
+    pub dmg_clumps: ClumpSelector,
+    /// Draw progress bars and status messages while converting.
+    pub show_progress: bool,
+    /// Pass entries with an unknown format through to the ANM as raw curves instead of dropping them.
+    pub keep_unknown_entries: bool,
+    /// Write curves that hold the same value on every frame as a single value, see [`collapse_constant_curves`].
+    pub collapse_constant_curves: bool,
+    /// Leave out entries that hold their rest pose on every frame, see [`drop_rest_entries`].
+    pub drop_rest_entries: bool,
+    /// Ticks between frames for keyframe times, overriding the ANMSTRM's `frame_size`.
+    pub frame_ticks: Option<u32>,
+    /// Remove keyframes that can be rebuilt by interpolation within this tolerance, see [`reduce_keyframes`].
+    pub keyframe_tolerance: Option<KeyframeTolerance>,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self {
+            split_dmg: true,
+            dmg_clumps: ClumpSelector::dmg(),
+            show_progress: true,
+            keep_unknown_entries: false,
+            collapse_constant_curves: true,
+            drop_rest_entries: false,
+            frame_ticks: None,
+            keyframe_tolerance: None,
+        }
+    }
+}
+
+/// Converts in-memory ANMSTRM and ANMSTRM frame buffers into a vector of ANM data (ANM and DMG ANM).
+/// The frame buffers are expected in playback order.
+pub fn convert_anmstrm_bytes<B: AsRef<[u8]>>(anmstrm: &[u8], anmstrm_frames: &[B], options: &ConvertOptions) -> Result<Vec<NuccAnm>> {
+    let anmstrm = read_anmstrm(&mut Cursor::new(anmstrm))?;
+    let anmstrmframes = parse_anmstrm_frames(anmstrm_frames.iter().map(|frame| Cursor::new(frame.as_ref())), options)?;
+
+    convert_anmstrm(&anmstrm, anmstrmframes, options)
+}
+
+/// Converts ANMSTRM data into a vector of ANM data (ANM and, if `options.split_dmg` is set and a DMG clump
+/// is found, DMG ANM).
+/// The frames are expected in playback order.
+pub fn convert_anmstrm(anmstrm: &NuccAnmStrm, anmstrmframes: Vec<NuccAnmStrmFrame>, options: &ConvertOptions) -> Result<Vec<NuccAnm>> {
+    let anmstrm_entries = build_anmstrm_entries_map(&anmstrmframes, options)?;
+
+    let ticks_per_frame = ticks_per_frame(anmstrm, options);
+    let frame_numbers = frame_numbers(anmstrm, &anmstrmframes)?;
+    let anm_entries = convert_entries(anmstrm_entries, &frame_numbers, ticks_per_frame, options)?;
+
+
+    if options.show_progress {
+        println!("building anm files...");
+    }
+    let mut anm = build_anm(anmstrm, anm_entries, ticks_per_frame as u32)?;
+
+    if options.drop_rest_entries {
+        let dropped = drop_rest_entries(&mut anm);
+
+        if options.show_progress {
+            println!("dropped {} entries holding their rest pose", dropped);
+        }
+    }
+
+    if options.collapse_constant_curves {
+        let collapsed = collapse_constant_curves(&mut anm)?;
+
+        if options.show_progress {
+            println!("collapsed {} constant curves", collapsed);
+        }
+    }
+
+    if let Some(tolerance) = &options.keyframe_tolerance {
+        let report = reduce_keyframes(&mut anm, tolerance)?;
+
+        if options.show_progress {
+            println!(
+                "reduced keyframes: removed {} of {}, saved {} bytes",
+                report.keyframes_removed,
+                report.keyframes_before,
+                report.bytes_saved
+            );
+        }
+    }
+
+    if !options.split_dmg {
+        return Ok(vec![anm]);
+    }
+
+    let dmg_positions = options.dmg_clumps.select(&anm.clumps);
+    if dmg_positions.is_empty() {
+        if options.show_progress {
+            println!("no DMG clump found, keeping every clump in the anm");
+        }
+
+        return Ok(vec![anm]);
+    }
+
+    let dmg_anm = split_clumps(&mut anm, &dmg_positions);
+
+    Ok(vec![anm, dmg_anm])
+}
+
+/// Parses ANMSTRM frames from a sequence of readers and returns a vector of parsed frames.
+pub fn parse_anmstrm_frames<R, I>(readers: I, options: &ConvertOptions) -> Result<Vec<NuccAnmStrmFrame>>
+where
+    R: Read + Seek,
+    I: IntoIterator<Item = R>,
+    I::IntoIter: ExactSizeIterator,
+{
+    let readers = readers.into_iter();
+    let len = readers.len();
+
+    parse_frames(readers.map(|reader| (Ok(reader), None)), len, options)
+}
+
+/// Parses ANMSTRM frame files and returns a vector of parsed frames.
+/// The files are read in the given order, see [`sort_anmstrm_frame_filepaths`].
+pub fn parse_anmstrm_frame_files<P: AsRef<Path>>(anmstrm_frame_filepaths: &[P], options: &ConvertOptions) -> Result<Vec<NuccAnmStrmFrame>> {
+    let files = anmstrm_frame_filepaths.iter().map(|path| {
+        let path = path.as_ref();
+        (File::open(path).map(BufReader::new).map_err(Error::from), Some(path))
+    });
+
+    parse_frames(files, anmstrm_frame_filepaths.len(), options)
+}
+
+fn parse_frames<'a, R, I>(readers: I, len: usize, options: &ConvertOptions) -> Result<Vec<NuccAnmStrmFrame>>
+where
+    R: Read + Seek,
+    I: Iterator<Item = (Result<R>, Option<&'a Path>)>,
+{
+    let mut anmstrmframes: Vec<NuccAnmStrmFrame> = Vec::with_capacity(len);
+
+    let pb = progress_bar(len as u64, "parsing anmstrm...    {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}", options);
+
+    for (i, (reader, path)) in readers.enumerate() {
+        let frame = reader.and_then(|mut reader| read_anmstrm_frame(&mut reader));
+
+        match (frame, path) {
+            (Ok(frame), _) => anmstrmframes.push(frame),
+            (Err(err), Some(path)) => return Err(err.with_path(path)),
+            (Err(err), None) => return Err(err),
+        }
+
+        pb.set_message(format!("parsed #{}", i + 1));
+        pb.inc(1);
+    }
+    pb.finish_with_message("done");
+    
+    Ok(anmstrmframes)
+}
+
+fn progress_bar(len: u64, template: &str, options: &ConvertOptions) -> ProgressBar {
+    if !options.show_progress {
+        return ProgressBar::hidden();
+    }
+
+    let pb = ProgressBar::new(len);
+    pb.set_style(ProgressStyle::with_template(template)
+    .unwrap()
+    .progress_chars("||-"));
+
+    pb
+}
+
+/// Sorts ANMSTRM frame files by the frame number in their file name (e.g. `name_12.anmstrmframe`)
+pub fn sort_anmstrm_frame_filepaths<P: AsRef<Path>>(anmstrm_frame_filepaths: &mut [P]) -> Result<()> {
+    // Validate every file name up front so the sort itself can't fail
+    for path in anmstrm_frame_filepaths.iter() {
+        frame_file_number(path.as_ref())?;
+    }
+
+    anmstrm_frame_filepaths.sort_by_cached_key(|path| frame_file_number(path.as_ref()).unwrap_or_default());
+
+    Ok(())
+}
+
+fn frame_file_number(path: &Path) -> Result<u32> {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.rsplit('_').next())
+        .and_then(|number| number.parse::<u32>().ok())
+        .ok_or_else(|| Error::InvalidFrameFileName(path.to_path_buf()))
+}
+
+/// Builds entries from ANMSTRM frames and returns a vector of entries.
+fn build_entries_from_frames(anmstrmframes: &[NuccAnmStrmFrame], options: &ConvertOptions) -> Result<Vec<Vec<AnmStrmEntry>>> {
+    let entry_count = anmstrmframes.first().map_or(0, |frame| frame.entry_count);
+
+    let mut anmstrm_entries: Vec<Vec<AnmStrmEntry>> = vec![Vec::new(); entry_count as usize];
+
+    anmstrm_entries.par_iter_mut().for_each(|entry| *entry = Vec::new());
+
+    // Entry formats we don't model, reported once per (format, clump, coord)
+    let mut unknown_entries: Vec<(u16, i16, u16)> = Vec::new();
+
+    let pb = progress_bar(anmstrmframes.len() as u64, "gathering frames...   {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}", options);
+
+    for (i, anmstrmframe) in anmstrmframes.iter().enumerate() {
+        if anmstrmframe.entries.len() != entry_count as usize {
+            return Err(Error::CountMismatch {
+                what: format!("entry count in anmstrm frame #{}", i + 1),
+                expected: entry_count as usize,
+                found: anmstrmframe.entries.len(),
+            });
+        }
+
+        for (entry_index, entry) in anmstrmframe.entries.iter().enumerate() {
+            match &entry.entry_data {
+                Entry::Bone(_) | Entry::Material(_) | 
+                Entry::Camera(_) | Entry::LightDirc(_) | 
+                Entry::LightPoint(_) | Entry::Ambient(_) |
+                Entry::MorphModel(_)
+                 => {
+                    anmstrm_entries[entry_index].push(entry.clone());
+                }
+                Entry::Unknown(_) => {
+                    let unknown_entry = (entry.entry_format, entry.coord.clump_index, entry.coord.coord_index);
+                    if !unknown_entries.contains(&unknown_entry) {
+                        unknown_entries.push(unknown_entry);
+                    }
+
+                    if options.keep_unknown_entries {
+                        anmstrm_entries[entry_index].push(entry.clone());
+                    }
+                }
+            }    
+        }
+
+        pb.set_message(format!("frame #{}", i + 1));
+        pb.inc(1);
+    }
+
+    pb.finish_with_message("done");
+
+    for (entry_format, clump_index, coord_index) in unknown_entries {
+        eprintln!(
+            "warning: unknown anmstrm entry format {} at coord ({}, {}), {}",
+            entry_format,
+            clump_index,
+            coord_index,
+            if options.keep_unknown_entries { "passing it through as raw data" } else { "skipping it" }
+        );
+    }
+
+   
+    Ok(anmstrm_entries)
+}
+
+/// Builds a map of ANMSTRM entries, where the key is the entry index.
+fn build_anmstrm_entries_map(anmstrmframes: &[NuccAnmStrmFrame], options: &ConvertOptions) -> Result<HashMap<u16, Vec<AnmStrmEntry>>> {
+    let anmstrm_entries = build_entries_from_frames(anmstrmframes, options)?;
+
+    Ok(anmstrm_entries
+        .into_iter()
+        .enumerate()
+        .filter(|(_, entries)| !entries.is_empty())
+        .map(|(entry_index, entries)| (entry_index as u16, entries))
+        .collect()) 
+}
+
+/// Ticks between frames: `options.frame_ticks`, or else the ANMSTRM's `frame_size`.
+fn ticks_per_frame(anmstrm: &NuccAnmStrm, options: &ConvertOptions) -> i32 {
+    match options.frame_ticks.unwrap_or(anmstrm.frame_size) {
+        0 => DEFAULT_FRAME_TICKS,
+        ticks => ticks as i32,
+    }
+}
+
+/// Frame number of every frame. The numbers have to agree with the ANMSTRM frame infos, strictly
+/// increase, and stay within the ANMSTRM's length (or the frame infos' range without a frame size).
+fn frame_numbers(anmstrm: &NuccAnmStrm, anmstrmframes: &[NuccAnmStrmFrame]) -> Result<Vec<i32>> {
+    let last_frame_number = match anmstrm.frame_size {
+        0 => u16::MAX as u32,
+        frame_size => (anmstrm.anm_length / frame_size).min(u16::MAX as u32),
+    };
+
+    let mut frame_numbers: Vec<i32> = Vec::with_capacity(anmstrmframes.len());
+
+    for (i, anmstrmframe) in anmstrmframes.iter().enumerate() {
+        let frame_number = anmstrmframe.frame_number;
+
+        if let Some(frame_info) = anmstrm.frames.get(i) {
+            if frame_info.frame_number as u32 != frame_number {
+                return Err(Error::InvalidAnm(format!(
+                    "frame {} is numbered {} but its frame info says {}",
+                    i, frame_number, frame_info.frame_number
+                )));
+            }
+        }
+
+        if frame_number > last_frame_number {
+            return Err(Error::InvalidAnm(format!(
+                "frame {} is numbered {}, past the last frame {} of the anmstrm",
+                i, frame_number, last_frame_number
+            )));
+        }
+
+        if let Some(previous) = frame_numbers.last() {
+            if frame_number as i32 <= *previous {
+                return Err(Error::InvalidAnm(format!(
+                    "frame {} is numbered {}, not after the frame before it numbered {}",
+                    i, frame_number, previous
+                )));
+            }
+        }
+
+        frame_numbers.push(frame_number as i32);
+    }
+
+    Ok(frame_numbers)
+}
+
+/// Converts ANMSTRM entries map into a vector of ANM entries.
+/// `frame_numbers` holds the number of every frame, see [`frame_numbers`]; keyframes are timed at the
+/// frame number times `ticks_per_frame`, and per-frame curves get a value for every frame number.
+fn convert_entries(anmstrm_entries: HashMap<u16, Vec<AnmStrmEntry>>, frame_numbers: &[i32], ticks_per_frame: i32, options: &ConvertOptions) -> Result<Vec<AnmEntry>> {
+    let mut anm_entries: Vec<AnmEntry> = Vec::with_capacity(anmstrm_entries.len());
+
+    // Material keys are held for half a frame before the next frame's keys
+    let half_frame = ticks_per_frame / 2;
+
+    // Coords of bones whose scale SHORT3 can't hold
+    let mut float_scale_coords: Vec<(i16, u16)> = Vec::new();
+
+    let pb = progress_bar(anmstrm_entries.len() as u64, "converting entries... {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}", options);
+
+    for (i, entry) in &anmstrm_entries {
+        let mut anm_entry = AnmEntry {
+            coord: entry[0].coord.clone(),
+            entry_format: 0,
+            curve_count: 0,
+            curve_headers: Vec::new(),
+            curves: Vec::new(),
+        };
+
+        let mut curve_index = 0; // Index for the curve headers
+
+        // Scales out of SHORT3 range are written as floats instead of wrapping
+        let float_scale = entry.iter().any(|anmstrm_entry| match &anmstrm_entry.entry_data {
+            Entry::Bone(anmstrm_entry_bone) => !fits_short3(&anmstrm_entry_bone.scale),
+            _ => false,
+        });
+
+        if float_scale {
+            float_scale_coords.push((anm_entry.coord.clump_index, anm_entry.coord.coord_index));
+        }
+
+        for (index, anmstrm_entry) in entry.iter().enumerate() {
+            let frame = frame_numbers[index.min(frame_numbers.len() - 1)] * ticks_per_frame;
+
+            match &anmstrm_entry.entry_data {
+
+                // ----------------- BONE -----------------
+                Entry::Bone(anmstrm_entry_bone) => {
+                    anm_entry.entry_format = AnmEntryFormat::BONE as u16;
+
+                    if index == 0 {
+                        // Create curves and curve headers for location, rotation, scale, and toggled
+                        anm_entry.curves.push(Curve::KeyframeVector3(Vec::new()));
+                        anm_entry.curves.push(Curve::QuaternionShort(Vec::new()));
+                        anm_entry.curves.push(if float_scale { Curve::Vector3(Vec::new()) } else { Curve::Vector3Short(Vec::new()) });
+                        anm_entry.curves.push(Curve::Float(Vec::new()));
+    
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index,
+                            curve_format: AnmCurveFormat::INT1_FLOAT3 as u16, // Curve format for keyframe loc
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 1,
+                            curve_format: AnmCurveFormat::SHORT4 as u16, // Curve format for rot
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 2,
+                            curve_format: if float_scale { AnmCurveFormat::FLOAT3ALT2 as u16 } else { AnmCurveFormat::SHORT3 as u16 }, // Curve format for scale
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 3,
+                            curve_format: AnmCurveFormat::FLOAT1 as u16, // Curve format for toggled
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+    
+                       curve_index += 4;
+                    }
+                    // Push keyframes for location, rotation, scale, and toggled
+                    if let Curve::KeyframeVector3(location_keyframes) = &mut anm_entry.curves[0] {
+                        location_keyframes.push(KeyframeVector3 {
+                            frame,
+                            value: anmstrm_entry_bone.location.clone(),
+                        });
+                    }
+                    if let Curve::QuaternionShort(rotation_keyframes) = &mut anm_entry.curves[1] {
+                        let rotation = compress_quaternion(&anmstrm_entry_bone.rotation, rotation_keyframes.last());
+                        rotation_keyframes.push(rotation);
+                    }
+                    match &mut anm_entry.curves[2] {
+                        Curve::Vector3Short(scale_keyframes) => {
+                            scale_keyframes.push(Vector3Short {
+                                x: (anmstrm_entry_bone.scale.x * SCALE_COMPRESS) as i16,
+                                y: (anmstrm_entry_bone.scale.y * SCALE_COMPRESS) as i16,
+                                z: (anmstrm_entry_bone.scale.z * SCALE_COMPRESS) as i16,
+                            });
+                        }
+                        Curve::Vector3(scale_keyframes) => {
+                            scale_keyframes.push(anmstrm_entry_bone.scale.clone());
+                        }
+                        _ => {}
+                    }
+
+                    if let Curve::Float(toggled_value) = &mut anm_entry.curves[3] {
+                        toggled_value.push(anmstrm_entry_bone.toggled);
+                    }
+                }
+                
+                // ----------------- MATERIAL -----------------
+                Entry::Material(anmstrm_entry_material) => {
+                    anm_entry.entry_format = AnmEntryFormat::MATERIAL as u16;
+
+                    if index == 0 {
+                        anm_entry.curves.push(Curve::KeyframeFloat(Vec::new()));
+                        anm_entry.curves.push(Curve::KeyframeFloat(Vec::new()));
+
+                        anm_entry.curves.push(Curve::Float(Vec::new()));
+                        anm_entry.curves.push(Curve::Float(Vec::new()));
+                        anm_entry.curves.push(Curve::Float(Vec::new()));
+                        anm_entry.curves.push(Curve::Float(Vec::new()));
+                        anm_entry.curves.push(Curve::Float(Vec::new()));
+                        anm_entry.curves.push(Curve::Float(Vec::new()));
+
+                        anm_entry.curves.push(Curve::KeyframeFloat(Vec::new()));
+                        anm_entry.curves.push(Curve::KeyframeFloat(Vec::new()));
+
+                        anm_entry.curves.push(Curve::Float(Vec::new()));
+                        anm_entry.curves.push(Curve::Float(Vec::new()));
+                        anm_entry.curves.push(Curve::Float(Vec::new()));
+                        anm_entry.curves.push(Curve::Float(Vec::new()));
+                        anm_entry.curves.push(Curve::Float(Vec::new()));
+                        anm_entry.curves.push(Curve::Float(Vec::new()));
+                        anm_entry.curves.push(Curve::Float(Vec::new())); 
+                        anm_entry.curves.push(Curve::Float(Vec::new()));
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index,
+                            curve_format: AnmCurveFormat::INT1_FLOAT1 as u16, // Curve format for KeyframeFloat
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 1,
+                            curve_format: AnmCurveFormat::INT1_FLOAT1 as u16, // Curve format for KeyframeFloat
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 2,
+                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 3,
+                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 4,
+                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 5,
+                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 6,
+                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 7,
+                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 8,
+                            curve_format: AnmCurveFormat::INT1_FLOAT1 as u16, // Curve format for KeyframeFloat
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 9,
+                            curve_format: AnmCurveFormat::INT1_FLOAT1 as u16, // Curve format for KeyframeFloat
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index:  curve_index + 10,
+                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                             curve_index: curve_index + 11,
+                             curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
+                             frame_count: 0,
+                             curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                             curve_index: curve_index + 12,
+                             curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
+                             frame_count: 0,
+                             curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                             curve_index: curve_index + 13,
+                             curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
+                             frame_count: 0,
+                             curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                             curve_index: curve_index + 14,
+                             curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
+                             frame_count: 0,
+                             curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                             curve_index: curve_index + 15,
+                             curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
+                             frame_count: 0,
+                             curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                             curve_index: curve_index + 16,
+                             curve_format: AnmCurveFormat::FLOAT1 as u16, // Curve format for toggle
+                             frame_count: 0,
+                             curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                             curve_index: curve_index + 17,
+                             curve_format: AnmCurveFormat::FLOAT1 as u16, // Curve format for toggle
+                             frame_count: 0,
+                             curve_size: 0,
+                        });
+
+                        curve_index += 18;
+                    }
+
+                    // Push keyframes for material color and toggled
+                    if let Curve::KeyframeFloat(color_values) = &mut anm_entry.curves[0] {
+                        color_values.push(KeyframeFloat {
+                            frame,
+                            value: anmstrm_entry_material.ambient_color[0],
+                        });
+
+                        color_values.push(KeyframeFloat {
+                            frame: frame + half_frame,
+                            value: anmstrm_entry_material.ambient_color[0],
+                        });
+                    }
+
+                    if let Curve::KeyframeFloat(color_values) = &mut anm_entry.curves[1] {
+                        color_values.push(KeyframeFloat {
+                            frame,
+                            value: anmstrm_entry_material.ambient_color[1],
+                        });
+
+                        color_values.push(KeyframeFloat {
+                            frame: frame + half_frame,
+                            value: anmstrm_entry_material.ambient_color[1],
+                        });
+                    }
+
+                    if let Curve::Float(color_values) = &mut anm_entry.curves[2] {
+                        color_values.push(anmstrm_entry_material.ambient_color[2]);
+                    }
+
+                    if let Curve::Float(color_values) = &mut anm_entry.curves[3] {
+                        color_values.push(anmstrm_entry_material.ambient_color[3]);
+                    }
+
+
+                    if let Curve::Float(color_values) = &mut anm_entry.curves[4] {
+                        color_values.push(anmstrm_entry_material.ambient_color[4]);
+                    }
+
+                    if let Curve::Float(color_values) = &mut anm_entry.curves[5] {
+                        color_values.push(anmstrm_entry_material.ambient_color[5]);
+                    }
+
+                    if let Curve::Float(color_values) = &mut anm_entry.curves[6] {
+                        color_values.push(anmstrm_entry_material.ambient_color[6]);
+                    }
+
+                    if let Curve::Float(color_values) = &mut anm_entry.curves[7] {
+                        color_values.push(anmstrm_entry_material.ambient_color[7]);
+                    }
+
+                    if let Curve::KeyframeFloat(color_values) = &mut anm_entry.curves[8] {
+                        color_values.push(KeyframeFloat {
+                            frame,
+                            value: anmstrm_entry_material.ambient_color[8],
+                        });
+
+                        color_values.push(KeyframeFloat {
+                            frame: frame + half_frame,
+                            value: anmstrm_entry_material.ambient_color[8],
+                        });
+                    }
+
+                    if let Curve::KeyframeFloat(color_values) = &mut anm_entry.curves[9] {
+                        color_values.push(KeyframeFloat {
+                            frame,
+                            value: anmstrm_entry_material.ambient_color[9],
+                        });
+
+                        color_values.push(KeyframeFloat {
+                            frame: frame + half_frame,
+                            value: anmstrm_entry_material.ambient_color[9],
+                        });
+                    }
+
+                    if let Curve::Float(color_values) = &mut anm_entry.curves[10] {
+                        color_values.push(anmstrm_entry_material.ambient_color[10]);
+                    }
+
+                    if let Curve::Float(color_values) = &mut anm_entry.curves[11] {
+                        color_values.push(anmstrm_entry_material.ambient_color[11]);
+                    }
+
+                    if let Curve::Float(color_values) = &mut anm_entry.curves[12] {
+                        color_values.push(anmstrm_entry_material.ambient_color[12]);
+                    }
+
+
+                    if let Curve::Float(color_values) = &mut anm_entry.curves[13] {
+                        color_values.push(anmstrm_entry_material.ambient_color[13]);
+                    }
+
+                    if let Curve::Float(color_values) = &mut anm_entry.curves[14] {
+                        color_values.push(anmstrm_entry_material.ambient_color[14]);
+                    }
+
+
+                    if let Curve::Float(color_values) = &mut anm_entry.curves[15] {
+                        color_values.push(anmstrm_entry_material.ambient_color[15]);
+                    }
+
+
+                    if let Curve::Float(color_values) = &mut anm_entry.curves[16] {
+                        color_values.push(0.0);
+                    }
+
+                    if let Curve::Float(color_values) = &mut anm_entry.curves[17] {
+                        color_values.push(1.0);
+                    }                       
+                }
+
+                Entry::Camera(anmstrm_entry_camera) => {
+                    anm_entry.entry_format = AnmEntryFormat::CAMERA as u16;
+
+                    if index == 0 {
+                        // Create curves and curve headers for location, rotation, fov
+                        anm_entry.curves.push(Curve::KeyframeVector3(Vec::new()));
+                        anm_entry.curves.push(Curve::QuaternionShort(Vec::new()));
+                        anm_entry.curves.push(Curve::KeyframeFloat(Vec::new()));
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index,
+                            curve_format: AnmCurveFormat::INT1_FLOAT3 as u16, // Curve format for KeyframeVector3
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+    
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 1,
+                            curve_format: AnmCurveFormat::SHORT4 as u16, // Curve format for Short4
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+    
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 2,
+                            curve_format: AnmCurveFormat::INT1_FLOAT1 as u16, // Curve format for KeyframeFloat
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+    
+                        curve_index += 3;
+                    }
+
+                    // Push keyframes for location, rotation, fov
+                    if let Curve::KeyframeVector3(location_keyframes) = &mut anm_entry.curves[0] {
+                        location_keyframes.push(KeyframeVector3 {
+                            frame,
+                            value: anmstrm_entry_camera.location.clone(),
+                        });
+                    }
+                    if let Curve::QuaternionShort(rotation_keyframes) = &mut anm_entry.curves[1] {
+                        let rotation = compress_quaternion(&anmstrm_entry_camera.rotation, rotation_keyframes.last());
+                        rotation_keyframes.push(rotation);
+                    }
+                    if let Curve::KeyframeFloat(fov_keyframes) = &mut anm_entry.curves[2] {
+                        fov_keyframes.push(KeyframeFloat {
+                            frame,
+                            value: anmstrm_entry_camera.fov,
+                        });
+                    }
+                }
+
+
+                // ----------------- LIGHTDIRC -----------------
+                Entry::LightDirc(anmstrm_entry_lightdir) => {
+                    anm_entry.entry_format = AnmEntryFormat::LIGHTDIRC as u16;
+
+                    if index == 0 {
+                        anm_entry.curves.push(Curve::RGB(Vec::new()));
+                        anm_entry.curves.push(Curve::Float(Vec::new()));
+                        anm_entry.curves.push(Curve::QuaternionShort(Vec::new()));
+                        
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index,
+                            curve_format: AnmCurveFormat::BYTE3 as u16, // Curve format for RGB
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 1,
+                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 2,
+                            curve_format: AnmCurveFormat::SHORT4 as u16, // Curve format for KeyframeVector4
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+    
+                        curve_index += 3;
+                    }
+                    // Push keyframes for color, light strength, rotations
+                    if let Curve::RGB(color_values) = &mut anm_entry.curves[0] {
+                        color_values.push(RGB {
+                            r: (anmstrm_entry_lightdir.color.x * RGB_CONVERT) as u8,
+                            g: (anmstrm_entry_lightdir.color.y * RGB_CONVERT) as u8,
+                            b: (anmstrm_entry_lightdir.color.z * RGB_CONVERT) as u8,
+                        });     
+                    }
+
+                    if let Curve::Float(strength_values) = &mut anm_entry.curves[1] {
+                        strength_values.push(anmstrm_entry_lightdir.intensity);
+                    }
+
+                    if let Curve::QuaternionShort(rotation_keyframes) = &mut anm_entry.curves[2] {
+                        let rotation = compress_quaternion(&anmstrm_entry_lightdir.direction, rotation_keyframes.last());
+                        rotation_keyframes.push(rotation);
+                    }
+                }
+
+                // ----------------- LIGHT POINT -----------------
+                Entry::LightPoint(anm_entry_lightpoint) => {
+                    anm_entry.entry_format = AnmEntryFormat::LIGHTPOINT as u16;
+
+                    if index == 0 {
+                        // Create curves and curve headers for color, light strength, location
+                        anm_entry.curves.push(Curve::RGB(Vec::new()));
+                        anm_entry.curves.push(Curve::KeyframeVector3(Vec::new()));
+                        anm_entry.curves.push(Curve::Float(Vec::new()));
+                        anm_entry.curves.push(Curve::Float(Vec::new()));
+                        anm_entry.curves.push(Curve::Float(Vec::new()));
+
+                        
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index,
+                            curve_format: AnmCurveFormat::BYTE3 as u16, // Curve format for RGB
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 1,
+                            curve_format: AnmCurveFormat::INT1_FLOAT3 as u16, // Curve format for KeyframeVector3
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 2,
+                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 3,
+                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 4,
+                            curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+    
+                        curve_index += 5;
+                    }
+
+                    // Push keyframes for color, light strength, location
+                    if let Curve::RGB(color_values) = &mut anm_entry.curves[0] {
+                        color_values.push(RGB {
+                            r: (anm_entry_lightpoint.color.x * RGB_CONVERT) as u8,
+                            g: (anm_entry_lightpoint.color.y * RGB_CONVERT) as u8,
+                            b: (anm_entry_lightpoint.color.z * RGB_CONVERT) as u8,
+                        });     
+                    }
+
+                    if let Curve::KeyframeVector3(location_keyframes) = &mut anm_entry.curves[1] {
+                        location_keyframes.push(KeyframeVector3 {
+                            frame,
+                            value: anm_entry_lightpoint.position.clone(),
+                        });
+                    }
+
+                    if let Curve::Float(intensity_values) = &mut anm_entry.curves[2] {
+                        intensity_values.push(anm_entry_lightpoint.intensity);
+                    }
+
+                    if let Curve::Float(radius_values) = &mut anm_entry.curves[3] {
+                        radius_values.push(anm_entry_lightpoint.radius);
+                    }
+
+                    if let Curve::Float(falloff_values) = &mut anm_entry.curves[4] {
+                        falloff_values.push(anm_entry_lightpoint.falloff);
+                    }
+                }
+
+                Entry::Ambient(anm_entry_ambient) => {
+                    anm_entry.entry_format = AnmEntryFormat::AMBIENT as u16;
+
+                    if index == 0 {
+                        // Create curves and curve headers for color, light strength
+                        anm_entry.curves.push(Curve::RGB(Vec::new()));
+                        anm_entry.curves.push(Curve::Float(Vec::new()));
+                        
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index,
+                            curve_format: AnmCurveFormat::BYTE3 as u16, // Curve format for RGB
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index: curve_index + 1,
+                            curve_format: AnmCurveFormat::FLOAT1ALT2 as u16, // Curve format for Float
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+
+                        curve_index += 2; 
+                    }
+
+                    // Push keyframes for color, light strength
+                    if let Curve::RGB(color_values) = &mut anm_entry.curves[0] {
+                        color_values.push(RGB {
+                            r: (anm_entry_ambient.color.x * RGB_CONVERT) as u8,
+                            g: (anm_entry_ambient.color.y * RGB_CONVERT) as u8,
+                            b: (anm_entry_ambient.color.z * RGB_CONVERT) as u8,
+                        });     
+                    }
+
+                    if let Curve::Float(strength_values) = &mut anm_entry.curves[1] {
+                        strength_values.push(anm_entry_ambient.intensity);
+                    }
+
+                }
+
+                // ----------------- MORPHMODEL -----------------
+                Entry::MorphModel(anmstrm_entry_morph) => {
+                    anm_entry.entry_format = AnmEntryFormat::MORPHMODEL as u16;
+
+                    if index == 0 {
+                        // One weight curve per morph target
+                        for i in 0..anmstrm_entry_morph.morph_weight.len() as u16 {
+                            anm_entry.curves.push(Curve::Float(Vec::new()));
+
+                            anm_entry.curve_headers.push(CurveHeader {
+                                curve_index: curve_index + i,
+                                curve_format: AnmCurveFormat::FLOAT1ALT as u16, // Curve format for Float
+                                frame_count: 0,
+                                curve_size: 0,
+                            });
+                        }
+
+                        curve_index += anmstrm_entry_morph.morph_weight.len() as u16;
+                    }
+
+                    // Push the weight of every morph target
+                    for (curve, weight) in anm_entry.curves.iter_mut().zip(&anmstrm_entry_morph.morph_weight) {
+                        if let Curve::Float(weight_values) = curve {
+                            weight_values.push(*weight);
+                        }
+                    }
+                }
+
+                // ----------------- UNKNOWN -----------------
+                Entry::Unknown(data) => {
+                    anm_entry.entry_format = anmstrm_entry.entry_format;
+
+                    if index == 0 {
+                        // A single raw curve holding the entry data of every frame. The anmstrm entry has no
+                        // curve format to keep, so it gets one no reader parses as a known curve.
+                        anm_entry.curves.push(Curve::Unknown { curve_format: RAW_CURVE_FORMAT, frame_count: 0, data: Vec::new() });
+
+                        anm_entry.curve_headers.push(CurveHeader {
+                            curve_index,
+                            curve_format: RAW_CURVE_FORMAT,
+                            frame_count: 0,
+                            curve_size: 0,
+                        });
+
+                        curve_index += 1;
+                    }
+
+                    if let Curve::Unknown { frame_count, data: raw_data, .. } = &mut anm_entry.curves[0] {
+                        raw_data.extend_from_slice(data);
+                        *frame_count += 1;
+                    }
+                }
+            }
+ 
+        }
+        // Update curves and headers for necessary changes
+        for curve in anm_entry.curves.iter_mut() {
+            spread_frames(curve, frame_numbers);
+
+            if curve.has_keyframes() {
+                curve.append_null_keyframe();
+            }
+
+            //If curve is RBG we need to pad the color values to be a multiple of 4
+            if curve.get_curve_format() == AnmCurveFormat::BYTE3 as u16 {
+                curve.pad_color_values();    
+            }
+        }
+
+        // Make sure we update the frame count and curve size for the curves
+        anm_entry.update_curve_headers()?;
+        anm_entries.push(anm_entry);
+        pb.set_message(format!("entry #{}", i + 1));
+        pb.inc(1);
+        
+    }
+
+    
+    pb.finish_with_message("done");
+
+    if !float_scale_coords.is_empty() {
+        float_scale_coords.sort();
+
+        let coords: Vec<String> = float_scale_coords
+            .iter()
+            .map(|(clump_index, coord_index)| format!("({}, {})", clump_index, coord_index))
+            .collect();
+
+        eprintln!(
+            "warning: scale out of SHORT3 range at coords {}, writing float scale curves",
+            coords.join(", ")
+        );
+    }
+
+    Ok(anm_entries)
+}
+
+/// Spreads the values of a per-frame curve, one per ANMSTRM frame, over the frame numbers they belong to,
+/// which strictly increase, see [`frame_numbers`]. Frames the ANMSTRM skips hold the value of the frame
+/// before them, and frames before the first one its value. Keyframed and raw curves are left alone.
+fn spread_frames(curve: &mut Curve, frame_numbers: &[i32]) {
+    match curve {
+        Curve::Vector3(values) => spread(values, frame_numbers),
+        Curve::Float(values) => spread(values, frame_numbers),
+        Curve::Short(values) => spread(values, frame_numbers),
+        Curve::Vector3Short(values) => spread(values, frame_numbers),
+        Curve::QuaternionShort(values) => spread(values, frame_numbers),
+        Curve::RGB(values) => spread(values, frame_numbers),
+        _ => {}
+    }
+}
+
+fn spread<T: Clone>(values: &mut Vec<T>, frame_numbers: &[i32]) {
+    let mut spread: Vec<T> = Vec::with_capacity(values.len());
+
+    for (value, frame_number) in values.iter().zip(frame_numbers) {
+        let held = spread.last().unwrap_or(value).clone();
+
+        while (spread.len() as i32) < *frame_number {
+            spread.push(held.clone());
+        }
+        spread.push(value.clone());
+    }
+
+    *values = spread;
+}
+
+/// Compresses a rotation to SHORT4. The quaternion is normalized, flipped into the same hemisphere
+/// as the previous frame's rotation so interpolating between them doesn't take the long way around,
+/// and rounded rather than truncated.
+fn compress_quaternion(rotation: &Vector4, previous: Option<&QuaternionShort>) -> QuaternionShort {
+    let mut quaternion = [rotation.x, rotation.y, rotation.z, rotation.w];
+
+    let length = quaternion.iter().map(|value| value * value).sum::<f32>().sqrt();
+    if length.is_finite() && length > f32::EPSILON {
+        quaternion.iter_mut().for_each(|value| *value /= length);
+    } else {
+        quaternion = [0.0, 0.0, 0.0, 1.0];
+    }
+
+    if let Some(previous) = previous {
+        let previous = [previous.x, previous.y, previous.z, previous.w];
+        let dot: f32 = previous.iter().zip(&quaternion).map(|(a, b)| *a as f32 * b).sum();
+
+        if dot < 0.0 {
+            quaternion.iter_mut().for_each(|value| *value = -*value);
+        }
+    }
+
+    let compress = |value: f32| (value * QUAT_COMPRESS).round() as i16;
+
+    QuaternionShort {
+        x: compress(quaternion[0]),
+        y: compress(quaternion[1]),
+        z: compress(quaternion[2]),
+        w: compress(quaternion[3]),
+    }
+}
+
+/// Whether a scale survives SHORT3 quantization without wrapping.
+fn fits_short3(scale: &Vector3) -> bool {
+    [scale.x, scale.y, scale.z].iter().all(|value| {
+        let quantized = value * SCALE_COMPRESS;
+        quantized >= i16::MIN as f32 && quantized <= i16::MAX as f32
+    })
+}
+
+/// Builds an ANM object from ANMSTRM and converted ANM entries keyed `ticks_per_frame` ticks apart.
+/// The ANM's frame size is `ticks_per_frame` and its length the ANMSTRM's length in those ticks.
+pub fn build_anm(anmstrm: &NuccAnmStrm, anm_entries: Vec<AnmEntry>, ticks_per_frame: u32) -> Result<NuccAnm> {
+
+    // sort the entries by coord index
+    let mut anm_entries = anm_entries;
+    anm_entries.sort_by_key(|entry| entry.coord.coord_index);
+
+
+    // We map the clumps to a new vector of clumps to avoid cloning the clumps
+    let anm_clumps: Vec<AnmClump> = anmstrm.clumps.clone().into_par_iter().map(|clump| AnmClump {
+        clump_index: clump.clump_index,
+        bone_material_count: clump.bone_material_count,
+        model_count: clump.model_count,
+        bone_material_indices: clump.bone_material_indices,
+        model_indices: clump.model_indices
+    }).collect();
+
+    // Without a frame size the length can't be rescaled, it ends a frame past the last frame
+    let anm_length = match anmstrm.frame_size {
+        0 => anmstrm.frames.iter().map(|frame| frame.frame_number as u32 + 1).max().unwrap_or(0) * ticks_per_frame,
+        frame_size => (anmstrm.anm_length as u64 * ticks_per_frame as u64 / frame_size as u64) as u32,
+    };
+
+    let anm = NuccAnm {
+        anm_length,
+        frame_size: ticks_per_frame,
+        entry_count: anm_entries.len() as u16,
+        looped: anmstrm.is_looped,
+        clump_count: anmstrm.clump_count,
+        other_entry_count: anmstrm.other_entry_count,
+        other_index_count: anmstrm.other_index_count,
+        coord_count: anmstrm.coord_count,
+        clumps: anm_clumps,
+        other_entries_indices: anmstrm.other_entries_indices.clone(),
+        coord_parents: anmstrm.coord_parents.clone(),
+        entries: anm_entries,
+    };
+
+    Ok(anm)
+}
@@ -0,0 +1,95 @@
+use std::{
+    io::{self, Seek},
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors produced while reading, converting and writing animations.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error("{}: {source}", path.display())]
+    File { path: PathBuf, source: io::Error },
+
+    #[error("failed to parse {} at offset {offset:#x}: {source}", display_path(path))]
+    Parse { path: Option<PathBuf>, offset: u64, source: binrw::Error },
+
+    #[error("failed to write {}: {source}", display_path(path))]
+    Write { path: Option<PathBuf>, source: binrw::Error },
+
+    #[error("malformed page json {}: {source}", path.display())]
+    PageJson { path: PathBuf, source: serde_json::Error },
+
+    #[error("malformed page rules {}: {source}", path.display())]
+    PageRulesJson { path: PathBuf, source: serde_json::Error },
+
+    #[error("no {pattern} chunk folder found in {}", dir.display())]
+    MissingChunkFolder { dir: PathBuf, pattern: String },
+
+    #[error("no .{extension} file found in {}", dir.display())]
+    MissingFile { dir: PathBuf, extension: String },
+
+    #[error("could not read a frame number from file name {}", .0.display())]
+    InvalidFrameFileName(PathBuf),
+
+    #[error("no {chunk_type} chunk found in {}", path.display())]
+    MissingChunk { path: PathBuf, chunk_type: String },
+
+    #[error("invalid xfbin at offset {offset:#x}: {message}")]
+    InvalidXfbin { offset: u64, message: String },
+
+    #[error("curve with format {curve_format:#x} is {size} bytes, more than a curve header can hold")]
+    CurveTooLarge { curve_format: u16, size: usize },
+
+    #[error("invalid anm: {0}")]
+    InvalidAnm(String),
+
+    #[error("inconsistent {what}: expected {expected}, found {found}")]
+    CountMismatch { what: String, expected: usize, found: usize },
+}
+
+impl Error {
+    /// Wraps a binrw read error, keeping the byte offset where parsing failed.
+    pub(crate) fn parse<R: Seek>(reader: &mut R, source: binrw::Error) -> Self {
+        let offset = binrw_error_pos(&source)
+            .or_else(|| reader.stream_position().ok())
+            .unwrap_or(0);
+
+        Error::Parse { path: None, offset, source }
+    }
+
+    /// Attaches the file the error originated from, if it does not already have one.
+    pub fn with_path(self, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+
+        match self {
+            Error::Io(source) => Error::File { path, source },
+            Error::Parse { path: None, offset, source } => Error::Parse { path: Some(path), offset, source },
+            Error::Write { path: None, source } => Error::Write { path: Some(path), source },
+            err => err,
+        }
+    }
+}
+
+fn binrw_error_pos(err: &binrw::Error) -> Option<u64> {
+    match err.root_cause() {
+        binrw::Error::BadMagic { pos, .. }
+        | binrw::Error::AssertFail { pos, .. }
+        | binrw::Error::Custom { pos, .. }
+        | binrw::Error::NoVariantMatch { pos }
+        | binrw::Error::EnumErrors { pos, .. } => Some(*pos),
+        _ => None,
+    }
+}
+
+fn display_path(path: &Option<PathBuf>) -> String {
+    match path {
+        Some(path) => path.display().to_string(),
+        None => "<memory>".to_string(),
+    }
+}
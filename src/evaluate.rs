@@ -0,0 +1,477 @@
+//! Evaluation of ANM curves and entries, and of ANMSTRM frames, at an arbitrary time.
+//!
+//! Times are given in frames and may be fractional. Keyframed curves store their keys in ticks,
+//! so they are evaluated at `frame * frame_size`; curves with one value per frame are interpolated
+//! between their neighbouring values and hold their last value past the end.
+
+use crate::structure::anm::{AnmCoord, AnmEntry, AnmEntryFormat, Curve, NuccAnm};
+use crate::structure::anmstrm::{Entry, NuccAnmStrm, NuccAnmStrmFrame};
+use crate::structure::anm_utils::{Vector3, Vector4, QUAT_COMPRESS, RGB_CONVERT, SCALE_COMPRESS};
+
+
+/// The decoded value of an ANM entry at a point in time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntryValue {
+    Bone {
+        location: Vector3,
+        rotation: Vector4,
+        scale: Vector3,
+        toggled: f32,
+    },
+
+    Camera {
+        location: Vector3,
+        rotation: Vector4,
+        fov: f32,
+    },
+
+    Material {
+        values: [f32; 16],
+    },
+
+    LightDirc {
+        color: Vector3,
+        intensity: f32,
+        direction: Vector4,
+    },
+
+    LightPoint {
+        color: Vector3,
+        position: Vector3,
+        intensity: f32,
+        radius: f32,
+        falloff: f32,
+    },
+
+    Ambient {
+        color: Vector3,
+        intensity: f32,
+    },
+
+    MorphModel {
+        weights: Vec<f32>,
+    },
+}
+
+impl EntryValue {
+    /// The value held by an ANMSTRM entry, `None` for unknown entries.
+    pub fn from_entry(entry: &Entry) -> Option<EntryValue> {
+        let value = match entry {
+            Entry::Bone(bone) => EntryValue::Bone {
+                location: bone.location.clone(),
+                rotation: bone.rotation.clone(),
+                scale: bone.scale.clone(),
+                toggled: bone.toggled,
+            },
+
+            Entry::Camera(camera) => EntryValue::Camera {
+                location: camera.location.clone(),
+                rotation: camera.rotation.clone(),
+                fov: camera.fov,
+            },
+
+            Entry::Material(material) => EntryValue::Material {
+                values: material.ambient_color,
+            },
+
+            Entry::LightDirc(light) => EntryValue::LightDirc {
+                color: light.color.clone(),
+                intensity: light.intensity,
+                direction: light.direction.clone(),
+            },
+
+            Entry::LightPoint(light) => EntryValue::LightPoint {
+                color: light.color.clone(),
+                position: light.position.clone(),
+                intensity: light.intensity,
+                radius: light.radius,
+                falloff: light.falloff,
+            },
+
+            Entry::Ambient(ambient) => EntryValue::Ambient {
+                color: ambient.color.clone(),
+                intensity: ambient.intensity,
+            },
+
+            Entry::MorphModel(morph) => EntryValue::MorphModel {
+                weights: morph.morph_weight.clone(),
+            },
+
+            Entry::Unknown(_) => return None,
+        };
+
+        Some(value)
+    }
+
+    /// Interpolates towards `other` by `t`, rotations along the shortest path.
+    /// Values of different kinds don't mix, the value is kept as is.
+    pub fn interpolate(&self, other: &EntryValue, t: f32) -> EntryValue {
+        let mix3 = |a: &Vector3, b: &Vector3| vector3(&lerp(&[a.x, a.y, a.z], &[b.x, b.y, b.z], t));
+        let mix4 = |a: &Vector4, b: &Vector4| vector4(&slerp(&[a.x, a.y, a.z, a.w], &[b.x, b.y, b.z, b.w], t));
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+
+        match (self, other) {
+            (
+                EntryValue::Bone { location, rotation, scale, toggled },
+                EntryValue::Bone { location: location2, rotation: rotation2, scale: scale2, toggled: toggled2 },
+            ) => EntryValue::Bone {
+                location: mix3(location, location2),
+                rotation: mix4(rotation, rotation2),
+                scale: mix3(scale, scale2),
+                toggled: mix(*toggled, *toggled2),
+            },
+
+            (
+                EntryValue::Camera { location, rotation, fov },
+                EntryValue::Camera { location: location2, rotation: rotation2, fov: fov2 },
+            ) => EntryValue::Camera {
+                location: mix3(location, location2),
+                rotation: mix4(rotation, rotation2),
+                fov: mix(*fov, *fov2),
+            },
+
+            (EntryValue::Material { values }, EntryValue::Material { values: values2 }) => {
+                let mut mixed = [0.0; 16];
+
+                for (value, (a, b)) in mixed.iter_mut().zip(values.iter().zip(values2)) {
+                    *value = mix(*a, *b);
+                }
+
+                EntryValue::Material { values: mixed }
+            }
+
+            (
+                EntryValue::LightDirc { color, intensity, direction },
+                EntryValue::LightDirc { color: color2, intensity: intensity2, direction: direction2 },
+            ) => EntryValue::LightDirc {
+                color: mix3(color, color2),
+                intensity: mix(*intensity, *intensity2),
+                direction: mix4(direction, direction2),
+            },
+
+            (
+                EntryValue::LightPoint { color, position, intensity, radius, falloff },
+                EntryValue::LightPoint {
+                    color: color2,
+                    position: position2,
+                    intensity: intensity2,
+                    radius: radius2,
+                    falloff: falloff2,
+                },
+            ) => EntryValue::LightPoint {
+                color: mix3(color, color2),
+                position: mix3(position, position2),
+                intensity: mix(*intensity, *intensity2),
+                radius: mix(*radius, *radius2),
+                falloff: mix(*falloff, *falloff2),
+            },
+
+            (
+                EntryValue::Ambient { color, intensity },
+                EntryValue::Ambient { color: color2, intensity: intensity2 },
+            ) => EntryValue::Ambient {
+                color: mix3(color, color2),
+                intensity: mix(*intensity, *intensity2),
+            },
+
+            (EntryValue::MorphModel { weights }, EntryValue::MorphModel { weights: weights2 })
+                if weights.len() == weights2.len() =>
+            {
+                EntryValue::MorphModel {
+                    weights: lerp(weights, weights2, t),
+                }
+            }
+
+            _ => self.clone(),
+        }
+    }
+}
+
+/// Evaluates the entry animating `coord` at `frame`.
+/// Returns `None` if no entry animates the coord or its format isn't known.
+pub fn evaluate_coord(anm: &NuccAnm, coord: &AnmCoord, frame: f32) -> Option<EntryValue> {
+    anm.entries
+        .iter()
+        .find(|entry| entry.coord.clump_index == coord.clump_index && entry.coord.coord_index == coord.coord_index)
+        .and_then(|entry| evaluate_entry(entry, frame, anm.frame_size))
+}
+
+/// Evaluates every entry of an ANMSTRM at `frame` without converting it. `frames` are the
+/// ANMSTRM's frames in the order of its frame infos, whose `frame_number` locates the frames
+/// around `frame`; the values of those two frames are interpolated.
+/// Coords missing from the later frame hold their value, unknown entries are left out.
+pub fn evaluate_anmstrm(anmstrm: &NuccAnmStrm, frames: &[NuccAnmStrmFrame], frame: f32) -> Vec<(AnmCoord, EntryValue)> {
+    let frame_numbers: Vec<f32> = anmstrm
+        .frames
+        .iter()
+        .take(frames.len())
+        .map(|frame_info| frame_info.frame_number as f32)
+        .collect();
+
+    let Some(last) = frame_numbers.len().checked_sub(1) else {
+        return Vec::new();
+    };
+
+    let start = frame_numbers
+        .iter()
+        .rposition(|frame_number| *frame_number <= frame)
+        .unwrap_or(0);
+    let end = (start + 1).min(last);
+
+    let t = if start == end || frame <= frame_numbers[start] {
+        0.0
+    } else {
+        ((frame - frame_numbers[start]) / (frame_numbers[end] - frame_numbers[start])).min(1.0)
+    };
+
+    frames[start]
+        .entries
+        .iter()
+        .filter_map(|entry| {
+            let value = EntryValue::from_entry(&entry.entry_data)?;
+
+            let next = frames[end]
+                .entries
+                .iter()
+                .find(|next| next.coord.clump_index == entry.coord.clump_index && next.coord.coord_index == entry.coord.coord_index)
+                .and_then(|next| EntryValue::from_entry(&next.entry_data));
+
+            let value = match next {
+                Some(next) if t > 0.0 => value.interpolate(&next, t),
+                _ => value,
+            };
+
+            Some((entry.coord.clone(), value))
+        })
+        .collect()
+}
+
+/// Evaluates every channel of an entry at `frame`. Channels without a curve take their rest value.
+/// Returns `None` for entry formats that aren't known.
+pub fn evaluate_entry(entry: &AnmEntry, frame: f32, frame_size: u32) -> Option<EntryValue> {
+    let channel = |curve_index: u16, default: &[f32]| -> Vec<f32> {
+        entry
+            .curve_headers
+            .iter()
+            .zip(&entry.curves)
+            .find(|(header, _)| header.curve_index == curve_index)
+            .and_then(|(_, curve)| evaluate_curve(curve, frame, frame_size))
+            .filter(|values| values.len() >= default.len())
+            .unwrap_or_else(|| default.to_vec())
+    };
+
+    let value = match entry.entry_format {
+        format if format == AnmEntryFormat::BONE as u16 => EntryValue::Bone {
+            location: vector3(&channel(0, &[0.0, 0.0, 0.0])),
+            rotation: vector4(&channel(1, &[0.0, 0.0, 0.0, 1.0])),
+            scale: vector3(&channel(2, &[1.0, 1.0, 1.0])),
+            toggled: channel(3, &[0.0])[0],
+        },
+
+        format if format == AnmEntryFormat::CAMERA as u16 => EntryValue::Camera {
+            location: vector3(&channel(0, &[0.0, 0.0, 0.0])),
+            rotation: vector4(&channel(1, &[0.0, 0.0, 0.0, 1.0])),
+            fov: channel(2, &[45.0])[0],
+        },
+
+        format if format == AnmEntryFormat::MATERIAL as u16 => {
+            let mut values = [0.0; 16];
+
+            for (i, value) in values.iter_mut().enumerate() {
+                *value = channel(i as u16, &[0.0])[0];
+            }
+
+            EntryValue::Material { values }
+        }
+
+        format if format == AnmEntryFormat::LIGHTDIRC as u16 => EntryValue::LightDirc {
+            color: vector3(&channel(0, &[1.0, 1.0, 1.0])),
+            intensity: channel(1, &[1.0])[0],
+            direction: vector4(&channel(2, &[0.0, 0.0, 0.0, 1.0])),
+        },
+
+        format if format == AnmEntryFormat::LIGHTPOINT as u16 => EntryValue::LightPoint {
+            color: vector3(&channel(0, &[1.0, 1.0, 1.0])),
+            position: vector3(&channel(1, &[0.0, 0.0, 0.0])),
+            intensity: channel(2, &[1.0])[0],
+            radius: channel(3, &[0.0])[0],
+            falloff: channel(4, &[0.0])[0],
+        },
+
+        format if format == AnmEntryFormat::AMBIENT as u16 => EntryValue::Ambient {
+            color: vector3(&channel(0, &[1.0, 1.0, 1.0])),
+            intensity: channel(1, &[1.0])[0],
+        },
+
+        // One weight curve per morph target, in curve index order
+        format if format == AnmEntryFormat::MORPHMODEL as u16 => {
+            let mut curves: Vec<_> = entry.curve_headers.iter().zip(&entry.curves).collect();
+            curves.sort_by_key(|(header, _)| header.curve_index);
+
+            let weights = curves
+                .iter()
+                .map(|(_, curve)| evaluate_curve(curve, frame, frame_size).map_or(0.0, |values| values[0]))
+                .collect();
+
+            EntryValue::MorphModel { weights }
+        }
+
+        _ => return None,
+    };
+
+    Some(value)
+}
+
+/// Decodes the value of a curve at `frame`, dequantizing short and byte formats.
+/// Returns `None` for empty and unknown curves.
+pub fn evaluate_curve(curve: &Curve, frame: f32, frame_size: u32) -> Option<Vec<f32>> {
+    let tick = frame * frame_size as f32;
+
+    match curve {
+        Curve::Vector3(values) => per_frame(values, frame, lerp, |value| vec![value.x, value.y, value.z]),
+
+        Curve::Float(values) => per_frame(values, frame, lerp, |value| vec![*value]),
+
+        Curve::Short(values) => per_frame(values, frame, lerp, |value| vec![*value as f32]),
+
+        Curve::Vector3Short(values) => per_frame(values, frame, lerp, |value| {
+            vec![
+                value.x as f32 / SCALE_COMPRESS,
+                value.y as f32 / SCALE_COMPRESS,
+                value.z as f32 / SCALE_COMPRESS,
+            ]
+        }),
+
+        Curve::QuaternionShort(values) => per_frame(values, frame, slerp, |value| {
+            vec![
+                value.x as f32 / QUAT_COMPRESS,
+                value.y as f32 / QUAT_COMPRESS,
+                value.z as f32 / QUAT_COMPRESS,
+                value.w as f32 / QUAT_COMPRESS,
+            ]
+        }),
+
+        Curve::RGB(values) => per_frame(values, frame, lerp, |value| {
+            vec![
+                value.r as f32 / RGB_CONVERT,
+                value.g as f32 / RGB_CONVERT,
+                value.b as f32 / RGB_CONVERT,
+            ]
+        }),
+
+        Curve::KeyframeVector3(keyframes) => {
+            let keyframes: Vec<(i32, Vec<f32>)> = keyframes
+                .iter()
+                .map(|keyframe| (keyframe.frame, vec![keyframe.value.x, keyframe.value.y, keyframe.value.z]))
+                .collect();
+
+            interpolate(&keyframes, tick, lerp)
+        }
+
+        Curve::KeyframeVector4(keyframes) => {
+            let keyframes: Vec<(i32, Vec<f32>)> = keyframes
+                .iter()
+                .map(|keyframe| {
+                    let value = &keyframe.value;
+                    (keyframe.frame, vec![value.x, value.y, value.z, value.w])
+                })
+                .collect();
+
+            interpolate(&keyframes, tick, slerp)
+        }
+
+        Curve::KeyframeFloat(keyframes) => {
+            let keyframes: Vec<(i32, Vec<f32>)> = keyframes
+                .iter()
+                .map(|keyframe| (keyframe.frame, vec![keyframe.value]))
+                .collect();
+
+            interpolate(&keyframes, tick, lerp)
+        }
+
+        Curve::Unknown { .. } => None,
+    }
+}
+
+/// Linear interpolation, component by component.
+pub fn lerp(start: &[f32], end: &[f32], t: f32) -> Vec<f32> {
+    start.iter().zip(end).map(|(a, b)| a + (b - a) * t).collect()
+}
+
+/// Spherical interpolation between two quaternions along the shortest path.
+pub fn slerp(start: &[f32], end: &[f32], t: f32) -> Vec<f32> {
+    let mut dot: f32 = start.iter().zip(end).map(|(a, b)| a * b).sum();
+    let mut end = end.to_vec();
+
+    if dot < 0.0 {
+        dot = -dot;
+        end.iter_mut().for_each(|value| *value = -*value);
+    }
+
+    // Nearly identical rotations, fall back to a normalized lerp
+    if dot > 0.9995 {
+        let value = lerp(start, &end, t);
+        let length = value.iter().map(|v| v * v).sum::<f32>().sqrt();
+
+        return value.iter().map(|v| v / length).collect();
+    }
+
+    let theta = dot.acos();
+    let sin_theta = theta.sin();
+    let start_weight = ((1.0 - t) * theta).sin() / sin_theta;
+    let end_weight = (t * theta).sin() / sin_theta;
+
+    start.iter().zip(&end).map(|(a, b)| a * start_weight + b * end_weight).collect()
+}
+
+/// Decodes the values around `frame` of a curve holding one value per frame and mixes them.
+fn per_frame<T>(values: &[T], frame: f32, mix: fn(&[f32], &[f32], f32) -> Vec<f32>, decode: impl Fn(&T) -> Vec<f32>) -> Option<Vec<f32>> {
+    let last = values.len().checked_sub(1)?;
+    let frame = frame.max(0.0);
+
+    let start = (frame.floor() as usize).min(last);
+    let end = (frame.ceil() as usize).min(last);
+
+    let value = decode(&values[start]);
+    if start == end {
+        return Some(value);
+    }
+
+    Some(mix(&value, &decode(&values[end]), frame.fract()))
+}
+
+/// Interpolates between the keyframes around `tick`, ignoring the `-1` null keyframe that ends a curve.
+fn interpolate(keyframes: &[(i32, Vec<f32>)], tick: f32, mix: fn(&[f32], &[f32], f32) -> Vec<f32>) -> Option<Vec<f32>> {
+    let keyframes: Vec<&(i32, Vec<f32>)> = keyframes.iter().filter(|(frame, _)| *frame != -1).collect();
+
+    let next = keyframes.iter().position(|(frame, _)| *frame as f32 > tick);
+
+    match next {
+        Some(0) => keyframes.first().map(|(_, value)| value.clone()),
+        Some(next) => {
+            let (start_frame, start) = keyframes[next - 1];
+            let (end_frame, end) = keyframes[next];
+            let t = (tick - *start_frame as f32) / (end_frame - start_frame) as f32;
+
+            Some(mix(start, end, t))
+        }
+        None => keyframes.last().map(|(_, value)| value.clone()),
+    }
+}
+
+fn vector3(values: &[f32]) -> Vector3 {
+    Vector3 {
+        x: values[0],
+        y: values[1],
+        z: values[2],
+    }
+}
+
+fn vector4(values: &[f32]) -> Vector4 {
+    Vector4 {
+        x: values[0],
+        y: values[1],
+        z: values[2],
+        w: values[3],
+    }
+}
@@ -0,0 +1,49 @@
+//! Library interface for converting streamed animations (`nuccChunkAnmStrm`) into
+//! regular animations (`nuccChunkAnm`) and back.
+
+pub mod error;
+pub mod structure;
+pub mod utils;
+pub mod convert;
+pub mod anm2anmstrm;
+pub mod clumps;
+pub mod evaluate;
+pub mod optimize;
+pub mod build_page;
+pub mod page_rules;
+pub mod output;
+pub mod xfbin;
+
+pub use crate::error::{Error, Result};
+
+pub use crate::structure::{
+    anm::NuccAnm,
+    anmstrm::{NuccAnmStrm, NuccAnmStrmFrame},
+    page::Page,
+};
+
+pub use crate::convert::{
+    convert_anmstrm,
+    convert_anmstrm_bytes,
+    ConvertOptions,
+    parse_anmstrm_frame_files,
+    parse_anmstrm_frames,
+    read_anmstrm,
+    read_anmstrm_file,
+    read_anmstrm_frame,
+    sort_anmstrm_frame_filepaths,
+};
+
+pub use crate::anm2anmstrm::{convert_anm, read_anm, read_anm_file};
+
+pub use crate::clumps::{clump_name, removed_indices, split_clumps, split_every_clump, ClumpAnm, ClumpSelector, SceneAnm};
+
+pub use crate::evaluate::{evaluate_anmstrm, evaluate_coord, evaluate_curve, evaluate_entry, EntryValue};
+
+pub use crate::optimize::{collapse_constant_curves, drop_rest_entries, reduce_keyframes, KeyframeTolerance, ReductionReport};
+
+pub use crate::page_rules::PageRules;
+
+pub use crate::xfbin::Xfbin;
+
+pub use crate::build_page::{build_anm_page, build_anm_page_with_dmg, build_anmstrm_page, build_clump_page, build_dmg_page};
@@ -1,107 +1,482 @@
-use std::{
-    fs::{self, File},
-    path::Path,
-};
-use binrw::{BinReaderExt, BinWriterExt};
-use console::Emoji;
-
-mod structure;
-mod utils;
-mod convert;
-mod build_page;
-
-use crate::{
-    structure::anmstrm::NuccAnmStrm,
-    utils::macros::find_subfolder,
-};
-
-use convert::*;
-use build_page::*;
-
-static SPARKLE: Emoji<'_, '_> = Emoji("✨ ", ":-)");
-
-fn main() {
-    let args = std::env::args().collect::<Vec<String>>();
-    let xfbin_dir = &args[1];
-    let working_dir = std::env::current_dir().unwrap();
-  
-
-    println!("anmstr2anm v0.1.0");
-    println!("by dei");
-
-    let new_xfbin_dir = format!("{}\\{}_converted", working_dir.display(), Path::new(xfbin_dir).file_name().unwrap().to_str().unwrap());
-     
-    let chunk_name = Path::new(&xfbin_dir)
-        .file_name()
-        .unwrap()
-        .to_str()
-        .unwrap();
-
-    let anmstrm_dir = find_subfolder(xfbin_dir, "(nuccChunkAnmStrmFrame)")
-        .expect("No nuccChunk found in the directory.");
-
-    let anmstrm: NuccAnmStrm = File::open(&collect_files!(anmstrm_dir.as_str(), "anmstrm")[0])
-        .unwrap()
-        .read_be::<NuccAnmStrm>()
-        .unwrap();
-
-    let now = std::time::Instant::now();
-
-    let anmstrm_frame_filepaths = collect_files!(&anmstrm_dir, "anmstrmframe");
-    let anms = convert_anmstrm(&anmstrm, anmstrm_frame_filepaths);
-
-    let elapsed = now.elapsed().as_secs_f32();
-
-    for (i, anm) in anms.unwrap().iter().enumerate() {
-        let suffix = if i == 1 { "_dmg" } else { "" };
-
-        let anm_path = format!(
-            "{}\\[00{}] {}{} (nuccChunkAnm)",
-            new_xfbin_dir, i, chunk_name, suffix
-        );
-
-        fs::create_dir_all(&anm_path).expect("Failed to create anm directory");
-
-        let anm_file_path = format!("{}\\{}{}.anm", anm_path, chunk_name, suffix);
-        let anm_file_path = Path::new(&anm_file_path);
-
-        let mut buf_writer = std::io::BufWriter::new(File::create(anm_file_path).unwrap());
-
-        buf_writer.write_be(anm).expect("Failed to write anm file");
-    }
-
-    // Copy other files
-    let other_entries = collect_files!(
-        anmstrm_dir.as_str(),
-        "camera",
-        "lightdirc",
-        "lightpoint",
-        "ambient",
-        "layerset",
-        "json"
-    );
-
-    let main_anm_path = format!("{}\\[000] {} (nuccChunkAnm)", new_xfbin_dir, chunk_name);
-
-    for other_entry_path in &other_entries {
-        let dest = Path::new(&main_anm_path).
-        join(Path::new(other_entry_path)
-        .file_name()
-        .unwrap());
-
-        if let Err(err) = fs::copy(&other_entry_path, &dest) {
-            eprintln!("Error copying file: {}", err);
-        }
-    }
-
-    // Build pages
-    let anm_page = build_anm_page(&collect_files!(anmstrm_dir.as_str(), "json")[0]);
-    anm_page.to_json_file(format!("{}\\_page.json", main_anm_path).as_str());
-
-    let dmg_page = build_dmg_page(&collect_files!(anmstrm_dir.as_str(), "json")[0]);
-    let dmg_path = format!("{}\\[001] {}_dmg (nuccChunkAnm)", new_xfbin_dir, chunk_name);
-    dmg_page.to_json_file(format!("{}\\_page.json", dmg_path).as_str());
-
-    println!("{} Done converting anmstrm {} to anm in {}s", SPARKLE, chunk_name, elapsed);
-    std::thread::sleep(std::time::Duration::from_secs(4));
-}
+use std::{
+    fs,
+    io::{Cursor, IsTerminal},
+    path::Path,
+    process::ExitCode,
+    time::Instant,
+};
+use binrw::BinWriterExt;
+use console::Emoji;
+use hashbrown::HashMap;
+
+use anmstrm2anm::{
+    collect_files,
+    utils::macros::find_subfolders,
+    convert_anmstrm, parse_anmstrm_frame_files, parse_anmstrm_frames,
+    read_anmstrm, read_anmstrm_file, sort_anmstrm_frame_filepaths,
+    output::OutputLayout,
+    xfbin::{chunk_extension, XfbinPage},
+    clump_name, removed_indices, split_clumps, split_every_clump, ClumpSelector, ConvertOptions, Error, KeyframeTolerance, NuccAnm, NuccAnmStrm, NuccAnmStrmFrame, Page, PageRules, Result, SceneAnm, Xfbin,
+};
+
+mod batch;
+mod cli;
+mod reverse;
+
+use cli::{AuxFile, Cli, Command, ConvertArgs, ConvertSettings, Verbosity};
+
+static SPARKLE: Emoji<'_, '_> = Emoji("✨ ", ":-)");
+
+const ANMSTRM_FOLDER_PATTERN: &str = "(nuccChunkAnmStrmFrame)";
+
+fn main() -> ExitCode {
+    let cli = Cli::parse_checked();
+    let verbosity = cli.output.verbosity();
+
+    let command = match (cli.command, cli.input) {
+        (Some(command), _) => command,
+        (None, Some(input)) => Command::Convert(ConvertArgs { input, settings: cli.settings }),
+        (None, None) => {
+            eprintln!("error: no input folder given, see --help");
+            return ExitCode::from(2);
+        }
+    };
+
+    if verbosity > Verbosity::Quiet {
+        println!("anmstr2anm v0.1.0");
+        println!("by dei");
+    }
+
+    // Keep the console window open when the exe was started by dropping a folder onto it
+    let pause = !cli.output.non_interactive && !matches!(command, Command::Batch(_)) && std::io::stdout().is_terminal();
+
+    let code = match command {
+        Command::Convert(args) => match run(&args, verbosity) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                ExitCode::from(exit_code(&err))
+            }
+        },
+        Command::Batch(args) => batch::run(&args, verbosity),
+        Command::Anm2AnmStrm(args) => match reverse::run(&args, verbosity) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                ExitCode::from(exit_code(&err))
+            }
+        },
+    };
+
+    if pause {
+        std::thread::sleep(std::time::Duration::from_secs(4));
+    }
+
+    code
+}
+
+/// Maps an error to the exit codes documented in the CLI help.
+fn exit_code(err: &Error) -> u8 {
+    match err {
+        Error::MissingChunkFolder { .. } | Error::MissingFile { .. } | Error::MissingChunk { .. } => 3,
+        Error::File { source, .. } if source.kind() == std::io::ErrorKind::NotFound => 3,
+        _ => 1,
+    }
+}
+
+fn run(args: &ConvertArgs, verbosity: Verbosity) -> Result<()> {
+    let show_progress = verbosity > Verbosity::Quiet;
+    let output_dir = args.settings.output_dir()?;
+
+    let report = if args.input.is_file() {
+        convert_xfbin(&args.input, &output_dir, &args.settings, verbosity, show_progress)?
+    } else {
+        if !args.input.is_dir() {
+            return Err(Error::MissingChunkFolder {
+                dir: args.input.clone(),
+                pattern: ANMSTRM_FOLDER_PATTERN.to_string(),
+            });
+        }
+
+        let anmstrm_dir = find_subfolders(&args.input, ANMSTRM_FOLDER_PATTERN)
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::MissingChunkFolder {
+                dir: args.input.clone(),
+                pattern: ANMSTRM_FOLDER_PATTERN.to_string(),
+            })?;
+
+        convert_folder(&args.input, &anmstrm_dir, &output_dir, &args.settings, verbosity, show_progress)?
+    };
+
+    if verbosity > Verbosity::Quiet {
+        println!("{} Done converting anmstrm {} to anm in {}s", SPARKLE, report.chunk_name, report.elapsed);
+    }
+
+    Ok(())
+}
+
+/// What was produced for a single converted XFBIN folder.
+pub struct FolderReport {
+    pub chunk_name: String,
+    pub frame_count: usize,
+    pub anm_count: usize,
+    pub elapsed: f32,
+}
+
+/// An anmstrm loaded either from an extracted XFBIN folder or from an .xfbin file.
+struct ConvertInput {
+    chunk_name: String,
+    anmstrm: NuccAnmStrm,
+    anmstrmframes: Vec<NuccAnmStrmFrame>,
+    page: Page,
+    /// Auxiliary chunk files (file name, contents) of the anmstrm's page.
+    aux_files: Vec<(String, Vec<u8>)>,
+}
+
+/// Converts the anmstrm in `anmstrm_dir` and writes the converted XFBIN folder for `xfbin_dir` to `output_dir`.
+pub fn convert_folder(
+    xfbin_dir: &Path,
+    anmstrm_dir: &Path,
+    output_dir: &Path,
+    settings: &ConvertSettings,
+    verbosity: Verbosity,
+    show_progress: bool,
+) -> Result<FolderReport> {
+    let chunk_name = xfbin_dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| Error::MissingChunkFolder {
+            dir: xfbin_dir.to_path_buf(),
+            pattern: "xfbin".to_string(),
+        })?;
+
+    let options = convert_options(settings, show_progress);
+
+    let anmstrm_path = first_file(anmstrm_dir, "anmstrm")?;
+    if verbosity == Verbosity::Verbose {
+        println!("reading {}", anmstrm_path);
+    }
+
+    let anmstrm = read_anmstrm_file(&anmstrm_path)?;
+
+    let now = Instant::now();
+
+    let mut anmstrm_frame_filepaths = collect_files!(anmstrm_dir, "anmstrmframe")?;
+    sort_anmstrm_frame_filepaths(&mut anmstrm_frame_filepaths)?;
+
+    if verbosity == Verbosity::Verbose {
+        println!("found {} anmstrm frames in {}", anmstrm_frame_filepaths.len(), anmstrm_dir.display());
+    }
+
+    let anmstrmframes = parse_anmstrm_frame_files(&anmstrm_frame_filepaths, &options)?;
+
+    let page = Page::from_json_file(first_file(anmstrm_dir, "json")?)?;
+
+    let mut aux_files = Vec::new();
+
+    for aux_file in AuxFile::all() {
+        for other_entry_path in &collect_files!(anmstrm_dir, aux_file.extension())? {
+            let file_name = Path::new(other_entry_path)
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string();
+
+            match fs::read(other_entry_path) {
+                Ok(data) => aux_files.push((file_name, data)),
+                Err(err) => eprintln!("Error reading file: {}", err),
+            }
+        }
+    }
+
+    let input = ConvertInput {
+        chunk_name: chunk_name.to_string(),
+        anmstrm,
+        anmstrmframes,
+        page,
+        aux_files,
+    };
+
+    write_converted(input, now, output_dir, settings, verbosity, &options)
+}
+
+/// Converts the first anmstrm in an .xfbin file and writes the converted XFBIN folder for it.
+pub fn convert_xfbin(
+    xfbin_path: &Path,
+    output_dir: &Path,
+    settings: &ConvertSettings,
+    verbosity: Verbosity,
+    show_progress: bool,
+) -> Result<FolderReport> {
+    let chunk_name = xfbin_path
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| Error::MissingChunk {
+            path: xfbin_path.to_path_buf(),
+            chunk_type: "nuccChunkAnmStrm".to_string(),
+        })?;
+
+    let options = convert_options(settings, show_progress);
+
+    if verbosity == Verbosity::Verbose {
+        println!("reading {}", xfbin_path.display());
+    }
+
+    let now = Instant::now();
+
+    let xfbin = Xfbin::from_file(xfbin_path)?;
+    let anmstrms = xfbin.anmstrms();
+
+    let xfbin_anmstrm = anmstrms.first().ok_or_else(|| Error::MissingChunk {
+        path: xfbin_path.to_path_buf(),
+        chunk_type: "nuccChunkAnmStrm".to_string(),
+    })?;
+
+    if verbosity == Verbosity::Verbose {
+        println!("found {} anmstrm frames in {}", xfbin_anmstrm.frames.len(), xfbin_path.display());
+    }
+
+    let anmstrm = read_anmstrm(&mut Cursor::new(&xfbin_anmstrm.anmstrm.data))
+        .map_err(|err| err.with_path(xfbin_path))?;
+
+    let anmstrmframes = parse_anmstrm_frames(
+        xfbin_anmstrm.frames.iter().map(|frame| Cursor::new(&frame.data)),
+        &options,
+    ).map_err(|err| err.with_path(xfbin_path))?;
+
+    let mut aux_files = Vec::new();
+    let page = xfbin_anmstrm.page;
+
+    for (chunk, file_name) in page.chunks.iter().zip(page.chunk_file_names()) {
+        let extension = chunk_extension(&chunk.chunk.types);
+
+        if AuxFile::all().iter().any(|aux_file| aux_file.extension() == extension) {
+            aux_files.push((file_name, chunk.data.clone()));
+        }
+    }
+
+    let input = ConvertInput {
+        chunk_name: chunk_name.to_string(),
+        anmstrm,
+        anmstrmframes,
+        page: xfbin_anmstrm.page.to_page(),
+        aux_files,
+    };
+
+    write_converted(input, now, output_dir, settings, verbosity, &options)
+}
+
+fn convert_options(settings: &ConvertSettings, show_progress: bool) -> ConvertOptions {
+    ConvertOptions {
+        split_dmg: !settings.no_dmg && !settings.split_clumps,
+        dmg_clumps: ClumpSelector::dmg(),
+        show_progress,
+        keep_unknown_entries: settings.keep_unknown,
+        collapse_constant_curves: !settings.keep_constant_curves,
+        drop_rest_entries: settings.drop_rest_entries,
+        frame_ticks: settings.frame_ticks,
+        keyframe_tolerance: settings.reduce_keyframes.map(|tolerance| KeyframeTolerance {
+            vector4: settings.rotation_tolerance.unwrap_or(tolerance),
+            ..KeyframeTolerance::uniform(tolerance)
+        }),
+    }
+}
+
+/// Converts a loaded anmstrm and writes the anms, auxiliary files and pages to the output layout.
+fn write_converted(
+    input: ConvertInput,
+    started: Instant,
+    output_dir: &Path,
+    settings: &ConvertSettings,
+    verbosity: Verbosity,
+    options: &ConvertOptions,
+) -> Result<FolderReport> {
+    let layout = OutputLayout::new(output_dir, &input.chunk_name);
+
+    let frame_count = input.anmstrmframes.len();
+    let page_rules = page_rules(settings.page_rules.as_deref())?;
+
+    // The DMG clumps are split off here, where the page indices each anm leaves out are known
+    let convert = ConvertOptions {
+        split_dmg: false,
+        ..options.clone()
+    };
+    let anm = convert_anmstrm(&input.anmstrm, input.anmstrmframes, &convert)?.remove(0);
+
+    let elapsed = started.elapsed().as_secs_f32();
+
+    // Each anm with its name suffix and page
+    let outputs = if settings.split_clumps {
+        clump_outputs(&anm, &input.page, settings.scene_clump, &page_rules)
+    } else {
+        let dmg_positions = if options.split_dmg {
+            settings.dmg_clumps.selector(&input.page).select(&anm.clumps)
+        } else {
+            Vec::new()
+        };
+
+        if options.split_dmg && dmg_positions.is_empty() && options.show_progress {
+            println!("no DMG clump found, keeping every clump in the anm");
+        }
+
+        dmg_outputs(anm, input.page, &dmg_positions, &page_rules)
+    };
+
+    let mut anm_datas = Vec::new();
+
+    for (i, (suffix, anm, _)) in outputs.iter().enumerate() {
+        let anm_path = layout.anm_dir(i, suffix);
+        fs::create_dir_all(&anm_path).map_err(|err| Error::from(err).with_path(&anm_path))?;
+
+        let anm_file_path = layout.anm_file(i, suffix);
+        let anm_file_path = anm_file_path.as_path();
+
+        if verbosity == Verbosity::Verbose {
+            println!("writing {}", anm_file_path.display());
+        }
+
+        let mut anm_data = Cursor::new(Vec::new());
+        anm_data.write_be(anm).map_err(|source| Error::Write {
+            path: Some(anm_file_path.to_path_buf()),
+            source,
+        })?;
+        let anm_data = anm_data.into_inner();
+
+        fs::write(anm_file_path, &anm_data).map_err(|err| Error::from(err).with_path(anm_file_path))?;
+
+        anm_datas.push(anm_data);
+    }
+
+    // Copy other files, next to the main anm or, when every clump has its own anm, next to the anms whose pages list them
+    for (file_name, data) in &input.aux_files {
+        let extension = Path::new(file_name).extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+        if !settings.copies(extension) {
+            continue;
+        }
+
+        for (i, (suffix, _, page)) in outputs.iter().enumerate() {
+            let copied = if settings.split_clumps {
+                page.files.iter().any(|file| &file.file_name == file_name)
+            } else {
+                i == 0
+            };
+
+            if !copied {
+                continue;
+            }
+
+            let dest = layout.anm_dir(i, suffix).join(file_name);
+
+            if verbosity == Verbosity::Verbose {
+                println!("copying {} to {}", file_name, dest.display());
+            }
+
+            if let Err(err) = fs::write(&dest, data) {
+                eprintln!("Error copying file: {}", err);
+            }
+        }
+    }
+
+    // Write the pages last so they replace any copied page json
+    for (i, (suffix, _, page)) in outputs.iter().enumerate() {
+        page.to_json_file(layout.page_file(i, suffix))?;
+    }
+
+    if settings.xfbin {
+        // Chunk file contents by the file names the pages list
+        let mut chunk_files: HashMap<String, Vec<u8>> = input.aux_files.into_iter().collect();
+
+        for ((_, _, page), anm_data) in outputs.iter().zip(anm_datas) {
+            if let Some(file) = page.files.iter().find(|file| file.chunk.types == "nuccChunkAnm") {
+                chunk_files.insert(file.file_name.clone(), anm_data);
+            }
+        }
+
+        let xfbin = Xfbin {
+            pages: outputs
+                .iter()
+                .map(|(_, _, page)| XfbinPage::from_page(page, &chunk_files))
+                .collect::<Result<_>>()?,
+        };
+
+        let xfbin_path = layout.xfbin_file();
+        if verbosity == Verbosity::Verbose {
+            println!("writing {}", xfbin_path.display());
+        }
+
+        xfbin.to_file(&xfbin_path)?;
+    }
+
+    Ok(FolderReport {
+        chunk_name: input.chunk_name,
+        frame_count,
+        anm_count: outputs.len(),
+        elapsed,
+    })
+}
+
+/// The converted anm and, if there are DMG clumps at `dmg_positions`, the `_dmg` anm split off it, each
+/// with its name suffix and page. The pages leave out the chunk maps of the clumps in the other anm.
+fn dmg_outputs(mut anm: NuccAnm, page: Page, dmg_positions: &[usize], page_rules: &PageRules) -> Vec<(String, NuccAnm, Page)> {
+    if dmg_positions.is_empty() {
+        let page = page_rules.anm_page(page, None);
+        return vec![(String::new(), anm, page)];
+    }
+
+    let rest: Vec<usize> = (0..anm.clumps.len()).filter(|position| !dmg_positions.contains(position)).collect();
+    let anm_page = page_rules.anm_page(page.clone(), Some(&removed_indices(&anm, &rest, true)));
+    let dmg_page = page_rules.dmg_page(page, &removed_indices(&anm, dmg_positions, false));
+
+    let dmg_anm = split_clumps(&mut anm, dmg_positions);
+
+    vec![(String::new(), anm, anm_page), ("_dmg".to_string(), dmg_anm, dmg_page)]
+}
+
+/// Splits the converted anm into one anm per clump, each with its name suffix (`_<clump chunk name>`, or
+/// `_scene` for the camera and light entries) and page.
+fn clump_outputs(anm: &NuccAnm, page: &Page, scene_clump: Option<usize>, page_rules: &PageRules) -> Vec<(String, NuccAnm, Page)> {
+    let scene = scene_clump.map_or(SceneAnm::Separate, SceneAnm::Clump);
+    let mut suffixes: Vec<String> = Vec::new();
+
+    split_every_clump(anm, scene)
+        .into_iter()
+        .map(|clump_anm| {
+            let mut suffix = match clump_anm.position {
+                Some(position) => match clump_name(page, &anm.clumps[position]) {
+                    Some(name) if !name.is_empty() => format!("_{}", name.replace(|c: char| !c.is_ascii_alphanumeric() && c != '-', "_")),
+                    _ => format!("_clump{}", position),
+                },
+                None => "_scene".to_string(),
+            };
+
+            // Clumps of the same model share a chunk name
+            if suffixes.contains(&suffix) {
+                suffix = format!("{}_{}", suffix, suffixes.len());
+            }
+            suffixes.push(suffix.clone());
+
+            let page = page_rules.clump_page(page.clone(), &clump_anm.removed_indices, &suffix);
+            (suffix, clump_anm.anm, page)
+        })
+        .collect()
+}
+
+/// The page rules in `path`, or the built-in rules.
+fn page_rules(path: Option<&Path>) -> Result<PageRules> {
+    match path {
+        Some(path) => PageRules::from_json_file(path),
+        None => Ok(PageRules::default()),
+    }
+}
+
+/// Returns the first file with the given extension in a chunk folder.
+fn first_file(dir: &Path, extension: &str) -> Result<String> {
+    collect_files!(dir, extension)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::MissingFile {
+            dir: dir.to_path_buf(),
+            extension: extension.to_string(),
+        })
+}
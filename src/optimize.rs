@@ -0,0 +1,270 @@
+//! Size optimizations applied to converted ANMs.
+
+use crate::error::Result;
+use crate::evaluate::{evaluate_entry, lerp, slerp, EntryValue};
+use crate::structure::anm::{AnmEntry, Curve, NuccAnm};
+use crate::structure::anm_utils::{KeyframeVector4, QuaternionShort, Vector4, QUAT_COMPRESS};
+
+/// Largest error allowed when a keyframe is reconstructed from its neighbours, per kind of curve.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyframeTolerance {
+    /// `KeyframeVector3` curves (locations and positions), in units.
+    pub vector3: f32,
+    /// Rotations, per quaternion component: `KeyframeVector4` curves, and `QuaternionShort` curves
+    /// when they become keyframes.
+    pub vector4: f32,
+    /// `KeyframeFloat` curves.
+    pub float: f32,
+}
+
+impl KeyframeTolerance {
+    /// The same tolerance for every kind of curve.
+    pub fn uniform(tolerance: f32) -> Self {
+        Self {
+            vector3: tolerance,
+            vector4: tolerance,
+            float: tolerance,
+        }
+    }
+}
+
+/// What [`reduce_keyframes`] removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReductionReport {
+    pub keyframes_before: usize,
+    pub keyframes_removed: usize,
+    pub bytes_saved: usize,
+}
+
+/// Removes keyframes that can be rebuilt within `tolerance` by interpolating between the keyframes
+/// kept around them, linearly or by slerp for rotations. The first and last keyframes and the `-1`
+/// null keyframe ending a curve are always kept. Per-frame `QuaternionShort` rotations are turned into
+/// `KeyframeVector4` keyframes, one per frame, and reduced the same way; they are only replaced if that
+/// makes them smaller. Other curves without keyframes are left alone.
+pub fn reduce_keyframes(anm: &mut NuccAnm, tolerance: &KeyframeTolerance) -> Result<ReductionReport> {
+    let mut report = ReductionReport::default();
+    let frame_size = anm.frame_size;
+
+    for entry in &mut anm.entries {
+        for (curve, header) in entry.curves.iter_mut().zip(&mut entry.curve_headers) {
+            let size = curve.byte_size();
+            let count = curve.get_frame_count() as usize;
+
+            match curve {
+                Curve::KeyframeVector3(keyframes) => reduce(
+                    keyframes,
+                    |keyframe| (keyframe.frame, vec![keyframe.value.x, keyframe.value.y, keyframe.value.z]),
+                    lerp,
+                    tolerance.vector3,
+                ),
+
+                Curve::KeyframeVector4(keyframes) => reduce(keyframes, decode_vector4, slerp, tolerance.vector4),
+
+                Curve::QuaternionShort(values) if frame_size > 0 => {
+                    let rotations = keyframe_rotations(values, frame_size, tolerance.vector4);
+                    if rotations.byte_size() >= size {
+                        continue;
+                    }
+
+                    *curve = rotations;
+                    header.curve_format = curve.get_curve_format();
+                }
+
+                Curve::KeyframeFloat(keyframes) => reduce(
+                    keyframes,
+                    |keyframe| (keyframe.frame, vec![keyframe.value]),
+                    lerp,
+                    tolerance.float,
+                ),
+
+                _ => continue,
+            }
+
+            report.keyframes_before += count;
+            report.keyframes_removed += count - curve.get_frame_count() as usize;
+            report.bytes_saved += size - curve.byte_size();
+        }
+
+        entry.update_curve_headers()?;
+    }
+
+    Ok(report)
+}
+
+/// Replaces curves holding the same value on every frame with a single value (frame count 1, plus
+/// the null keyframe ending keyframed curves). Returns how many curves were collapsed.
+pub fn collapse_constant_curves(anm: &mut NuccAnm) -> Result<usize> {
+    let mut collapsed = 0;
+
+    for entry in &mut anm.entries {
+        for curve in &mut entry.curves {
+            let frame_count = curve.get_frame_count();
+
+            if collapse_curve(curve) && curve.get_frame_count() < frame_count {
+                collapsed += 1;
+            }
+        }
+
+        entry.update_curve_headers()?;
+    }
+
+    Ok(collapsed)
+}
+
+/// Removes entries that hold their rest pose on every frame, i.e. evaluate to the same values as an
+/// entry without any curves (no offset, no rotation, a scale of 1, default light values, all morph
+/// weights 0). Entries held at any other pose and raw entries are kept. Returns how many entries were removed.
+pub fn drop_rest_entries(anm: &mut NuccAnm) -> usize {
+    let frame_size = anm.frame_size;
+    let entry_count = anm.entries.len();
+
+    anm.entries.retain(|entry| !is_at_rest(entry, frame_size));
+    anm.entry_count = anm.entries.len() as u16;
+
+    entry_count - anm.entries.len()
+}
+
+fn is_at_rest(entry: &AnmEntry, frame_size: u32) -> bool {
+    if !entry.curves.iter().all(|curve| collapse_curve(&mut curve.clone())) {
+        return false;
+    }
+
+    let rest = AnmEntry {
+        coord: entry.coord.clone(),
+        entry_format: entry.entry_format,
+        curve_count: 0,
+        curve_headers: Vec::new(),
+        curves: Vec::new(),
+    };
+
+    match (evaluate_entry(entry, 0.0, frame_size), evaluate_entry(&rest, 0.0, frame_size)) {
+        (Some(EntryValue::MorphModel { weights }), _) => weights.iter().all(|weight| *weight == 0.0),
+        (Some(value), Some(rest)) => value == rest,
+        _ => false,
+    }
+}
+
+/// Cuts a curve holding the same value on every frame down to that value, keeping the `-1` null
+/// keyframe ending a keyframed curve. Returns whether the curve holds a single value; raw curves never do.
+fn collapse_curve(curve: &mut Curve) -> bool {
+    match curve {
+        Curve::Vector3(values) => collapse(values, |value| Some(value.clone())),
+        Curve::Float(values) => collapse(values, |value| Some(*value)),
+        Curve::Short(values) => collapse(values, |value| Some(*value)),
+        Curve::Vector3Short(values) => collapse(values, |value| Some(value.clone())),
+        Curve::QuaternionShort(values) => collapse(values, |value| Some(value.clone())),
+        Curve::RGB(values) => collapse(values, |value| Some(value.clone())),
+
+        // Keyframes only differ in their frame
+        Curve::KeyframeVector3(keyframes) => collapse(keyframes, |keyframe| keyed(keyframe.frame, keyframe.value.clone())),
+        Curve::KeyframeVector4(keyframes) => collapse(keyframes, |keyframe| keyed(keyframe.frame, keyframe.value.clone())),
+        Curve::KeyframeFloat(keyframes) => collapse(keyframes, |keyframe| keyed(keyframe.frame, keyframe.value)),
+
+        Curve::Unknown { .. } => false,
+    }
+}
+
+/// The value of a keyframe, or `None` for the null keyframe.
+fn keyed<K>(frame: i32, value: K) -> Option<K> {
+    (frame != -1).then_some(value)
+}
+
+/// Keeps only the first value if every value up to the first one keyed `None` has the same key.
+/// Returns whether they all had the same key.
+fn collapse<T, K: PartialEq>(values: &mut Vec<T>, key: impl Fn(&T) -> Option<K>) -> bool {
+    let len = values.iter().position(|value| key(value).is_none()).unwrap_or(values.len());
+
+    let mut keys = values[..len].iter().filter_map(&key);
+    let constant = match keys.next() {
+        Some(first) => keys.all(|key| key == first),
+        None => true,
+    };
+
+    if constant && len > 1 {
+        values.drain(1..len);
+    }
+
+    constant
+}
+
+/// Keeps the keyframes that can't be rebuilt from the last kept keyframe and a later one.
+fn reduce<T>(keyframes: &mut Vec<T>, decode: impl Fn(&T) -> (i32, Vec<f32>), mix: fn(&[f32], &[f32], f32) -> Vec<f32>, tolerance: f32) {
+    let decoded: Vec<(i32, Vec<f32>)> = keyframes.iter().map(&decode).collect();
+
+    // Keyframes before the null keyframe, which stays where it is
+    let len = decoded.iter().position(|(frame, _)| *frame == -1).unwrap_or(decoded.len());
+    if len < 3 {
+        return;
+    }
+
+    let fits = |start: usize, end: usize| {
+        let (start_frame, start_value) = &decoded[start];
+        let (end_frame, end_value) = &decoded[end];
+
+        decoded[start + 1..end].iter().all(|(frame, value)| {
+            let t = (frame - start_frame) as f32 / (end_frame - start_frame) as f32;
+            error(&mix(start_value, end_value, t), value) <= tolerance
+        })
+    };
+
+    let mut keep = vec![false; decoded.len()];
+    keep[0] = true;
+    keep[len - 1] = true;
+    keep[len..].iter_mut().for_each(|keep| *keep = true);
+
+    let mut anchor = 0;
+    for end in 2..len {
+        if !fits(anchor, end) {
+            anchor = end - 1;
+            keep[anchor] = true;
+        }
+    }
+
+    let mut keep = keep.into_iter();
+    keyframes.retain(|_| keep.next().unwrap_or(true));
+}
+
+fn decode_vector4(keyframe: &KeyframeVector4) -> (i32, Vec<f32>) {
+    let value = &keyframe.value;
+    (keyframe.frame, vec![value.x, value.y, value.z, value.w])
+}
+
+/// A per-frame rotation curve as reduced `KeyframeVector4` keyframes, frame `n` keyed at `n * frame_size` ticks.
+fn keyframe_rotations(values: &[QuaternionShort], frame_size: u32, tolerance: f32) -> Curve {
+    let mut keyframes: Vec<KeyframeVector4> = values
+        .iter()
+        .enumerate()
+        .map(|(frame, value)| KeyframeVector4 {
+            frame: frame as i32 * frame_size as i32,
+            value: Vector4 {
+                x: value.x as f32 / QUAT_COMPRESS,
+                y: value.y as f32 / QUAT_COMPRESS,
+                z: value.z as f32 / QUAT_COMPRESS,
+                w: value.w as f32 / QUAT_COMPRESS,
+            },
+        })
+        .collect();
+
+    reduce(&mut keyframes, decode_vector4, slerp, tolerance);
+
+    let mut curve = Curve::KeyframeVector4(keyframes);
+    curve.append_null_keyframe();
+    curve
+}
+
+/// Largest component difference, treating opposite quaternions as the same rotation.
+fn error(actual: &[f32], expected: &[f32]) -> f32 {
+    let max_difference = |sign: f32| {
+        actual
+            .iter()
+            .zip(expected)
+            .map(|(a, b)| (a - sign * b).abs())
+            .fold(0.0, f32::max)
+    };
+
+    if actual.len() == 4 {
+        max_difference(1.0).min(max_difference(-1.0))
+    } else {
+        max_difference(1.0)
+    }
+}
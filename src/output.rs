@@ -0,0 +1,74 @@
+use std::path::{Path, PathBuf};
+
+/// Builds the paths of a converted XFBIN folder, laid out like the tree an XFBIN extractor produces:
+///
+/// ```text
+/// <output dir>/<name>_converted/
+///     [000] <name> (nuccChunkAnm)/<name>.anm
+///     [000] <name> (nuccChunkAnm)/_page.json
+///     [001] <name>_dmg (nuccChunkAnm)/<name>_dmg.anm
+///     <name>.xfbin
+/// ```
+#[derive(Debug, Clone)]
+pub struct OutputLayout {
+    root: PathBuf,
+    chunk_name: String,
+}
+
+impl OutputLayout {
+    pub fn new<P: AsRef<Path>>(output_dir: P, chunk_name: &str) -> Self {
+        Self {
+            root: output_dir.as_ref().join(format!("{}_converted", chunk_name)),
+            chunk_name: chunk_name.to_string(),
+        }
+    }
+
+    /// The `<name>_converted` folder everything is written to.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn chunk_name(&self) -> &str {
+        &self.chunk_name
+    }
+
+    /// Folder of the `index`-th converted anm chunk, e.g. `[001] <name>_dmg (nuccChunkAnm)`.
+    pub fn anm_dir(&self, index: usize, suffix: &str) -> PathBuf {
+        self.root.join(format!("[{:03}] {}{} (nuccChunkAnm)", index, self.chunk_name, suffix))
+    }
+
+    /// The `.anm` file inside [`OutputLayout::anm_dir`].
+    pub fn anm_file(&self, index: usize, suffix: &str) -> PathBuf {
+        self.anm_dir(index, suffix).join(format!("{}{}.anm", self.chunk_name, suffix))
+    }
+
+    /// The `_page.json` file inside [`OutputLayout::anm_dir`].
+    pub fn page_file(&self, index: usize, suffix: &str) -> PathBuf {
+        self.anm_dir(index, suffix).join("_page.json")
+    }
+
+    /// Folder of an anmstrm converted back from an anm, `[000] <name> (nuccChunkAnmStrmFrame)`.
+    pub fn anmstrm_dir(&self) -> PathBuf {
+        self.root.join(format!("[000] {} (nuccChunkAnmStrmFrame)", self.chunk_name))
+    }
+
+    /// The `.anmstrm` file inside [`OutputLayout::anmstrm_dir`].
+    pub fn anmstrm_file(&self) -> PathBuf {
+        self.anmstrm_dir().join(format!("{}.anmstrm", self.chunk_name))
+    }
+
+    /// The `frame`-th `.anmstrmframe` file inside [`OutputLayout::anmstrm_dir`].
+    pub fn anmstrm_frame_file(&self, frame: usize) -> PathBuf {
+        self.anmstrm_dir().join(format!("{}_{}.anmstrmframe", self.chunk_name, frame))
+    }
+
+    /// The `_page.json` file inside [`OutputLayout::anmstrm_dir`].
+    pub fn anmstrm_page_file(&self) -> PathBuf {
+        self.anmstrm_dir().join("_page.json")
+    }
+
+    /// The repacked `<name>.xfbin` containing every converted chunk.
+    pub fn xfbin_file(&self) -> PathBuf {
+        self.root.join(format!("{}.xfbin", self.chunk_name))
+    }
+}
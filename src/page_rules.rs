@@ -0,0 +1,452 @@
+//! Declarative rules turning the page of an anmstrm into the pages of the converted anms and back.
+//!
+//! A rule set is a JSON file listing the rules applied, in order, to each kind of page:
+//!
+//! ```json
+//! {
+//!   "Anm": [
+//!     { "Remove": { "Target": "Chunk Maps", "Any": [{ "Type": "nuccChunkAnmStrmFrame" }] } },
+//!     { "Rewrite": { "Target": "Chunk Maps", "Matching": { "Type": "nuccChunkAnmStrm" }, "Edits": [
+//!       { "Set": { "Field": "Type", "Value": "nuccChunkAnm" } }
+//!     ] } }
+//!   ],
+//!   "Anm Without DMG": [],
+//!   "DMG": [],
+//!   "Clump": [],
+//!   "AnmStrm": ["Add Frame Chunks"]
+//! }
+//! ```
+//!
+//! Patterns are regular expressions matched anywhere in a field. `{Suffix}` in the value of an edit is
+//! replaced by the name suffix of the anm the page is for. The rules the tool ships with are in
+//! `default_page_rules.json`; their DMG pages drop the chunks of the clumps split into the other anm by
+//! index rather than by the `1cmn` chunk name earlier versions matched.
+
+use std::{fs, path::Path, sync::OnceLock};
+
+use regex::Regex;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::{Error, Result};
+use crate::structure::page::{Chunk, ChunkReference, Files, Page};
+
+const DEFAULT_PAGE_RULES: &str = include_str!("default_page_rules.json");
+
+/// Replaced by the name suffix of the anm in the value of an edit.
+const SUFFIX: &str = "{Suffix}";
+
+/// The rules for every kind of page the tool writes.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PageRules {
+    /// Turn an anmstrm page into the page of the converted anm.
+    #[serde(rename = "Anm")]
+    pub anm: Vec<PageRule>,
+    /// Applied after `anm` when the DMG clumps are split off the converted anm.
+    #[serde(rename = "Anm Without DMG")]
+    pub anm_without_dmg: Vec<PageRule>,
+    /// Turn an anmstrm page into the page of the DMG anm.
+    #[serde(rename = "DMG")]
+    pub dmg: Vec<PageRule>,
+    /// Turn an anmstrm page into the page of one of the anms every clump is split into.
+    #[serde(rename = "Clump")]
+    pub clump: Vec<PageRule>,
+    /// Turn an anm page back into the page of an anmstrm.
+    #[serde(rename = "AnmStrm")]
+    pub anmstrm: Vec<PageRule>,
+}
+
+impl Default for PageRules {
+    fn default() -> Self {
+        PageRules::built_in().clone()
+    }
+}
+
+impl PageRules {
+    /// The rules the tool ships with, parsed on first use.
+    pub fn built_in() -> &'static PageRules {
+        static BUILT_IN: OnceLock<PageRules> = OnceLock::new();
+
+        BUILT_IN.get_or_init(|| serde_json::from_str(DEFAULT_PAGE_RULES).expect("Failed to parse the default page rules"))
+    }
+
+    pub fn from_json_file<P: AsRef<Path>>(filepath: P) -> Result<PageRules> {
+        let filepath = filepath.as_ref();
+        let data = fs::read(filepath).map_err(|err| Error::from(err).with_path(filepath))?;
+
+        serde_json::from_slice(&data).map_err(|source| Error::PageRulesJson {
+            path: filepath.to_path_buf(),
+            source,
+        })
+    }
+
+    pub fn to_json_file<P: AsRef<Path>>(&self, filepath: P) -> Result<()> {
+        let filepath = filepath.as_ref();
+
+        let json = serde_json::to_string_pretty(&self).map_err(|source| Error::PageRulesJson {
+            path: filepath.to_path_buf(),
+            source,
+        })?;
+
+        fs::write(filepath, json).map_err(|err| Error::from(err).with_path(filepath))
+    }
+
+    /// The page of the converted anm. With the DMG clumps split off, `removed_indices` are the chunk map
+    /// indices the anm leaves out, and the `Anm Without DMG` rules follow the `Anm` rules.
+    pub fn anm_page(&self, page: Page, removed_indices: Option<&[u32]>) -> Page {
+        let context = RuleContext::split(&page, removed_indices.unwrap_or_default(), "");
+        let page = apply_rules(&self.anm, page, &context);
+
+        match removed_indices {
+            Some(_) => apply_rules(&self.anm_without_dmg, page, &context),
+            None => page,
+        }
+    }
+
+    /// The page of the DMG anm split off the converted anm, leaving out the chunk maps at `removed_indices`.
+    pub fn dmg_page(&self, page: Page, removed_indices: &[u32]) -> Page {
+        let context = RuleContext::split(&page, removed_indices, "_dmg");
+        apply_rules(&self.dmg, page, &context)
+    }
+
+    /// The page of one of the anms every clump is split into, named with `suffix` and leaving out the chunk
+    /// maps at `removed_indices`.
+    pub fn clump_page(&self, page: Page, removed_indices: &[u32], suffix: &str) -> Page {
+        let context = RuleContext::split(&page, removed_indices, suffix);
+        apply_rules(&self.clump, page, &context)
+    }
+
+    /// The page of an anmstrm with `frame_count` frame chunks.
+    pub fn anmstrm_page(&self, page: Page, frame_count: usize) -> Page {
+        let context = RuleContext {
+            frame_count,
+            ..RuleContext::default()
+        };
+        apply_rules(&self.anmstrm, page, &context)
+    }
+}
+
+/// What rules are applied with besides the page.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleContext {
+    /// Number of frame files `Add Frame Chunks` adds.
+    pub frame_count: usize,
+    /// Chunks of the page split off into other anms, which `Remove Split Chunks` drops.
+    pub split_chunks: Vec<Chunk>,
+    /// Name suffix of the anm the page is for, replacing `{Suffix}` in edits.
+    pub suffix: String,
+}
+
+impl RuleContext {
+    /// The chunk maps of `page` at `removed_indices` split off, for an anm named with `suffix`.
+    pub fn split(page: &Page, removed_indices: &[u32], suffix: &str) -> Self {
+        RuleContext {
+            frame_count: 0,
+            split_chunks: removed_indices
+                .iter()
+                .filter_map(|index| page.chunk_maps.get(*index as usize))
+                .cloned()
+                .collect(),
+            suffix: suffix.to_string(),
+        }
+    }
+}
+
+/// One step of a page transformation.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum PageRule {
+    /// Keeps only the items matching one of the patterns.
+    Retain {
+        #[serde(rename = "Target")]
+        target: PageTarget,
+        #[serde(rename = "Any")]
+        any: Vec<ChunkPattern>,
+    },
+
+    /// Removes the items matching one of the patterns.
+    Remove {
+        #[serde(rename = "Target")]
+        target: PageTarget,
+        #[serde(rename = "Any")]
+        any: Vec<ChunkPattern>,
+    },
+
+    /// Applies the edits, in order, to the items matching the pattern.
+    Rewrite {
+        #[serde(rename = "Target")]
+        target: PageTarget,
+        #[serde(rename = "Matching")]
+        matching: ChunkPattern,
+        #[serde(rename = "Edits")]
+        edits: Vec<FieldEdit>,
+        /// Only edit the first matching item.
+        #[serde(rename = "First Only", default, skip_serializing_if = "std::ops::Not::not")]
+        first_only: bool,
+    },
+
+    /// Adds a frame chunk map after the first anmstrm chunk map, and one frame file per frame after the
+    /// first anmstrm file, named `<anmstrm file stem>_<frame>.anmstrmframe`.
+    #[serde(rename = "Add Frame Chunks")]
+    AddFrameChunks,
+
+    /// Removes the chunk maps split off into other anms, with the references and files of their chunks,
+    /// except the ones matching one of the `Keep` patterns.
+    #[serde(rename = "Remove Split Chunks")]
+    RemoveSplitChunks {
+        #[serde(rename = "Keep")]
+        keep: Vec<ChunkPattern>,
+    },
+}
+
+/// The list of a page a rule applies to.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageTarget {
+    #[serde(rename = "Chunk Maps")]
+    ChunkMaps,
+    #[serde(rename = "Chunk References")]
+    ChunkReferences,
+    #[serde(rename = "Chunks")]
+    Files,
+}
+
+/// A field of a chunk map, chunk reference or file. `Name`, `Type` and `Path` are the fields of the chunk
+/// itself, the chunk a reference points at, or the chunk of a file.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkField {
+    Name,
+    Type,
+    Path,
+    #[serde(rename = "File Name")]
+    FileName,
+    #[serde(rename = "Reference Name")]
+    ReferenceName,
+}
+
+/// Matches items whose fields all match the given patterns. Fields the item doesn't have never match.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ChunkPattern {
+    #[serde(rename = "Name", default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<Pattern>,
+    #[serde(rename = "Type", default, skip_serializing_if = "Option::is_none")]
+    pub types: Option<Pattern>,
+    #[serde(rename = "Path", default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<Pattern>,
+    #[serde(rename = "File Name", default, skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<Pattern>,
+    #[serde(rename = "Reference Name", default, skip_serializing_if = "Option::is_none")]
+    pub reference_name: Option<Pattern>,
+}
+
+impl ChunkPattern {
+    fn matches(&self, item: &impl PageItem) -> bool {
+        [
+            (ChunkField::Name, &self.name),
+            (ChunkField::Type, &self.types),
+            (ChunkField::Path, &self.path),
+            (ChunkField::FileName, &self.file_name),
+            (ChunkField::ReferenceName, &self.reference_name),
+        ]
+        .into_iter()
+        .all(|(field, pattern)| match pattern {
+            Some(pattern) => item.field(field).is_some_and(|value| pattern.0.is_match(value)),
+            None => true,
+        })
+    }
+}
+
+/// A change to one field of an item.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum FieldEdit {
+    /// Replaces the field.
+    Set {
+        #[serde(rename = "Field")]
+        field: ChunkField,
+        #[serde(rename = "Value")]
+        value: String,
+    },
+
+    /// Replaces every match of the pattern in the field, `$1` and `${name}` refer to capture groups.
+    Replace {
+        #[serde(rename = "Field")]
+        field: ChunkField,
+        #[serde(rename = "Pattern")]
+        pattern: Pattern,
+        #[serde(rename = "With")]
+        with: String,
+    },
+}
+
+/// A regular expression, written as a string in the rules.
+#[derive(Debug, Clone)]
+pub struct Pattern(pub Regex);
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+
+        Regex::new(&pattern).map(Pattern).map_err(de::Error::custom)
+    }
+}
+
+/// Applies `rules` to `page` in order.
+pub fn apply_rules(rules: &[PageRule], mut page: Page, context: &RuleContext) -> Page {
+    for rule in rules {
+        let target = match rule {
+            PageRule::Retain { target, .. } | PageRule::Remove { target, .. } | PageRule::Rewrite { target, .. } => *target,
+            PageRule::AddFrameChunks => {
+                add_frame_chunks(&mut page, context.frame_count);
+                continue;
+            }
+            PageRule::RemoveSplitChunks { keep } => {
+                remove_split_chunks(&mut page, &context.split_chunks, keep);
+                continue;
+            }
+        };
+
+        match target {
+            PageTarget::ChunkMaps => apply_rule(rule, &mut page.chunk_maps, &context.suffix),
+            PageTarget::ChunkReferences => apply_rule(rule, &mut page.chunk_references, &context.suffix),
+            PageTarget::Files => apply_rule(rule, &mut page.files, &context.suffix),
+        }
+    }
+
+    page
+}
+
+fn apply_rule<T: PageItem>(rule: &PageRule, items: &mut Vec<T>, suffix: &str) {
+    match rule {
+        PageRule::Retain { any, .. } => items.retain(|item| any.iter().any(|pattern| pattern.matches(item))),
+        PageRule::Remove { any, .. } => items.retain(|item| !any.iter().any(|pattern| pattern.matches(item))),
+
+        PageRule::Rewrite { matching, edits, first_only, .. } => {
+            let limit = if *first_only { 1 } else { usize::MAX };
+
+            for item in items.iter_mut().filter(|item| matching.matches(*item)).take(limit) {
+                for edit in edits {
+                    match edit {
+                        FieldEdit::Set { field, value } => {
+                            if let Some(field) = item.field_mut(*field) {
+                                *field = value.replace(SUFFIX, suffix);
+                            }
+                        }
+                        FieldEdit::Replace { field, pattern, with } => {
+                            if let Some(field) = item.field_mut(*field) {
+                                *field = pattern.0.replace_all(field, with.replace(SUFFIX, suffix).as_str()).into_owned();
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        PageRule::AddFrameChunks | PageRule::RemoveSplitChunks { .. } => {}
+    }
+}
+
+fn remove_split_chunks(page: &mut Page, split_chunks: &[Chunk], keep: &[ChunkPattern]) {
+    let removed: Vec<&Chunk> = split_chunks
+        .iter()
+        .filter(|chunk| !keep.iter().any(|pattern| pattern.matches(*chunk)))
+        .collect();
+
+    page.chunk_maps.retain(|chunk| !removed.contains(&chunk));
+    page.chunk_references.retain(|reference| !removed.contains(&&reference.chunk));
+    page.files.retain(|file| !removed.contains(&&file.chunk));
+}
+
+fn add_frame_chunks(page: &mut Page, frame_count: usize) {
+    // Every frame chunk shares a single chunk map named after the anmstrm
+    if let Some(index) = page.chunk_maps.iter().position(|chunk| chunk.types == "nuccChunkAnmStrm") {
+        let frame_map = Chunk {
+            types: "nuccChunkAnmStrmFrame".to_string(),
+            ..page.chunk_maps[index].clone()
+        };
+        page.chunk_maps.insert(index + 1, frame_map);
+    }
+
+    if let Some(index) = page.files.iter().position(|file| file.chunk.types == "nuccChunkAnmStrm") {
+        let file = &page.files[index];
+
+        let stem = file.file_name.trim_end_matches(".anmstrm").to_string();
+        let frame_chunk = Chunk {
+            types: "nuccChunkAnmStrmFrame".to_string(),
+            ..file.chunk.clone()
+        };
+
+        let frame_files: Vec<Files> = (0..frame_count).map(|frame| Files {
+            file_name: format!("{}_{}.anmstrmframe", stem, frame),
+            chunk: frame_chunk.clone(),
+        }).collect();
+
+        page.files.splice(index + 1..index + 1, frame_files);
+    }
+}
+
+/// The fields rules match and edit on an item of a page.
+trait PageItem {
+    fn field(&self, field: ChunkField) -> Option<&str>;
+    fn field_mut(&mut self, field: ChunkField) -> Option<&mut String>;
+}
+
+impl PageItem for Chunk {
+    fn field(&self, field: ChunkField) -> Option<&str> {
+        match field {
+            ChunkField::Name => Some(&self.name),
+            ChunkField::Type => Some(&self.types),
+            ChunkField::Path => Some(&self.path),
+            ChunkField::FileName | ChunkField::ReferenceName => None,
+        }
+    }
+
+    fn field_mut(&mut self, field: ChunkField) -> Option<&mut String> {
+        match field {
+            ChunkField::Name => Some(&mut self.name),
+            ChunkField::Type => Some(&mut self.types),
+            ChunkField::Path => Some(&mut self.path),
+            ChunkField::FileName | ChunkField::ReferenceName => None,
+        }
+    }
+}
+
+impl PageItem for ChunkReference {
+    fn field(&self, field: ChunkField) -> Option<&str> {
+        match field {
+            ChunkField::ReferenceName => Some(&self.name),
+            field => self.chunk.field(field),
+        }
+    }
+
+    fn field_mut(&mut self, field: ChunkField) -> Option<&mut String> {
+        match field {
+            ChunkField::ReferenceName => Some(&mut self.name),
+            field => self.chunk.field_mut(field),
+        }
+    }
+}
+
+impl PageItem for Files {
+    fn field(&self, field: ChunkField) -> Option<&str> {
+        match field {
+            ChunkField::FileName => Some(&self.file_name),
+            field => self.chunk.field(field),
+        }
+    }
+
+    fn field_mut(&mut self, field: ChunkField) -> Option<&mut String> {
+        match field {
+            ChunkField::FileName => Some(&mut self.file_name),
+            field => self.chunk.field_mut(field),
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use std::{
+    fs,
+    io::Cursor,
+    path::Path,
+    time::Instant,
+};
+
+use binrw::BinWriterExt;
+use hashbrown::HashMap;
+
+use anmstrm2anm::{
+    anm2anmstrm::{convert_anm, read_anm, read_anm_file},
+    collect_files,
+    output::OutputLayout,
+    utils::macros::find_subfolders,
+    xfbin::{chunk_extension, XfbinPage},
+    Error, NuccAnm, Page, Result, Xfbin,
+};
+
+use crate::cli::{AuxFile, ReverseArgs, Verbosity};
+use crate::{first_file, page_rules, SPARKLE};
+
+const ANM_FOLDER_PATTERN: &str = "(nuccChunkAnm)";
+
+/// An anm loaded either from an extracted XFBIN folder or from an .xfbin file.
+struct ReverseInput {
+    chunk_name: String,
+    anm: NuccAnm,
+    page: Page,
+    /// Auxiliary chunk files (file name, contents) of the anm's page.
+    aux_files: Vec<(String, Vec<u8>)>,
+}
+
+/// Converts the anm of an .xfbin file or extracted XFBIN folder back into an anmstrm and its frames.
+pub fn run(args: &ReverseArgs, verbosity: Verbosity) -> Result<()> {
+    let now = Instant::now();
+
+    let input = if args.input.is_file() {
+        load_xfbin(&args.input, verbosity)?
+    } else {
+        load_folder(&args.input, verbosity)?
+    };
+
+    let output_dir = match &args.output {
+        Some(output_dir) => output_dir.clone(),
+        None => std::env::current_dir()?,
+    };
+
+    let layout = OutputLayout::new(&output_dir, &input.chunk_name);
+
+    let (anmstrm, anmstrmframes) = convert_anm(&input.anm)?;
+
+    let anmstrm_dir = layout.anmstrm_dir();
+    fs::create_dir_all(&anmstrm_dir).map_err(|err| Error::from(err).with_path(&anmstrm_dir))?;
+
+    let anmstrm_data = write_chunk_file(&layout.anmstrm_file(), &anmstrm, verbosity)?;
+
+    let mut frame_datas = Vec::with_capacity(anmstrmframes.len());
+    for (frame, anmstrmframe) in anmstrmframes.iter().enumerate() {
+        frame_datas.push(write_chunk_file(&layout.anmstrm_frame_file(frame), anmstrmframe, verbosity)?);
+    }
+
+    if !args.no_copy {
+        for (file_name, data) in &input.aux_files {
+            let dest = anmstrm_dir.join(file_name);
+
+            if verbosity == Verbosity::Verbose {
+                println!("copying {} to {}", file_name, dest.display());
+            }
+
+            if let Err(err) = fs::write(&dest, data) {
+                eprintln!("Error copying file: {}", err);
+            }
+        }
+    }
+
+    let page = page_rules(args.page_rules.as_deref())?.anmstrm_page(input.page, anmstrmframes.len());
+    page.to_json_file(layout.anmstrm_page_file())?;
+
+    if args.xfbin {
+        // Chunk file contents by the file names the page lists
+        let mut chunk_files: HashMap<String, Vec<u8>> = input.aux_files.into_iter().collect();
+        let mut frame_datas = frame_datas.into_iter();
+
+        for file in &page.files {
+            match file.chunk.types.as_str() {
+                "nuccChunkAnmStrm" => {
+                    chunk_files.insert(file.file_name.clone(), anmstrm_data.clone());
+                }
+                "nuccChunkAnmStrmFrame" => {
+                    if let Some(frame_data) = frame_datas.next() {
+                        chunk_files.insert(file.file_name.clone(), frame_data);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let xfbin = Xfbin {
+            pages: vec![XfbinPage::from_page(&page, &chunk_files)?],
+        };
+
+        let xfbin_path = layout.xfbin_file();
+        if verbosity == Verbosity::Verbose {
+            println!("writing {}", xfbin_path.display());
+        }
+
+        xfbin.to_file(&xfbin_path)?;
+    }
+
+    if verbosity > Verbosity::Quiet {
+        println!(
+            "{} Done converting anm {} to anmstrm ({} frames) in {}s",
+            SPARKLE,
+            input.chunk_name,
+            anmstrmframes.len(),
+            now.elapsed().as_secs_f32()
+        );
+    }
+
+    Ok(())
+}
+
+fn load_folder(xfbin_dir: &Path, verbosity: Verbosity) -> Result<ReverseInput> {
+    let missing_folder = || Error::MissingChunkFolder {
+        dir: xfbin_dir.to_path_buf(),
+        pattern: ANM_FOLDER_PATTERN.to_string(),
+    };
+
+    if !xfbin_dir.is_dir() {
+        return Err(missing_folder());
+    }
+
+    let anm_dir = find_subfolders(xfbin_dir, ANM_FOLDER_PATTERN)
+        .into_iter()
+        .next()
+        .ok_or_else(missing_folder)?;
+
+    let anm_path = first_file(&anm_dir, "anm")?;
+    if verbosity == Verbosity::Verbose {
+        println!("reading {}", anm_path);
+    }
+
+    let anm = read_anm_file(&anm_path)?;
+    let page = Page::from_json_file(first_file(&anm_dir, "json")?)?;
+
+    // The chunk folder itself was given, name the output after the anm instead of the folder
+    let name_path = if anm_dir == xfbin_dir { Path::new(&anm_path).with_extension("") } else { xfbin_dir.to_path_buf() };
+    let chunk_name = name_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(missing_folder)?;
+
+    let mut aux_files = Vec::new();
+
+    for aux_file in AuxFile::all() {
+        if aux_file == AuxFile::Json {
+            continue;
+        }
+
+        for other_entry_path in &collect_files!(&anm_dir, aux_file.extension())? {
+            let file_name = Path::new(other_entry_path)
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string();
+
+            match fs::read(other_entry_path) {
+                Ok(data) => aux_files.push((file_name, data)),
+                Err(err) => eprintln!("Error reading file: {}", err),
+            }
+        }
+    }
+
+    Ok(ReverseInput {
+        chunk_name,
+        anm,
+        page,
+        aux_files,
+    })
+}
+
+fn load_xfbin(xfbin_path: &Path, verbosity: Verbosity) -> Result<ReverseInput> {
+    let missing_chunk = || Error::MissingChunk {
+        path: xfbin_path.to_path_buf(),
+        chunk_type: "nuccChunkAnm".to_string(),
+    };
+
+    let chunk_name = xfbin_path
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .ok_or_else(missing_chunk)?;
+
+    if verbosity == Verbosity::Verbose {
+        println!("reading {}", xfbin_path.display());
+    }
+
+    let xfbin = Xfbin::from_file(xfbin_path)?;
+
+    let (page, anm_chunk) = xfbin
+        .pages
+        .iter()
+        .find_map(|page| {
+            page.chunks
+                .iter()
+                .find(|chunk| chunk.chunk.types == "nuccChunkAnm")
+                .map(|chunk| (page, chunk))
+        })
+        .ok_or_else(missing_chunk)?;
+
+    let anm = read_anm(&mut Cursor::new(&anm_chunk.data)).map_err(|err| err.with_path(xfbin_path))?;
+
+    let aux_files = page
+        .chunks
+        .iter()
+        .zip(page.chunk_file_names())
+        .filter(|(chunk, _)| {
+            let extension = chunk_extension(&chunk.chunk.types);
+            AuxFile::all().iter().any(|aux_file| aux_file.extension() == extension)
+        })
+        .map(|(chunk, file_name)| (file_name, chunk.data.clone()))
+        .collect();
+
+    Ok(ReverseInput {
+        chunk_name: chunk_name.to_string(),
+        anm,
+        page: page.to_page(),
+        aux_files,
+    })
+}
+
+/// Serializes a chunk, writes it to `path` and returns the written bytes.
+fn write_chunk_file<T>(path: &Path, chunk: &T, verbosity: Verbosity) -> Result<Vec<u8>>
+where
+    T: binrw::BinWrite<Args = ()>,
+{
+    if verbosity == Verbosity::Verbose {
+        println!("writing {}", path.display());
+    }
+
+    let mut data = Cursor::new(Vec::new());
+    data.write_be(chunk).map_err(|source| Error::Write {
+        path: Some(path.to_path_buf()),
+        source,
+    })?;
+    let data = data.into_inner();
+
+    fs::write(path, &data).map_err(|err| Error::from(err).with_path(path))?;
+
+    Ok(data)
+}
@@ -1,65 +1,72 @@
-use binrw::binrw;
-
-#[binrw]
-#[derive(Debug, Clone)]
-pub struct Vector3 {
-    pub x: f32,
-    pub y: f32,
-    pub z: f32,
-}
-
-#[binrw]
-#[derive(Debug, Clone)]
-pub struct Vector4 {
-    pub x: f32,
-    pub y: f32,
-    pub z: f32,
-    pub w: f32,
-}
-
-#[binrw]
-#[derive(Debug, Clone)]
-pub struct Vector3Short {
-    pub x: i16,
-    pub y: i16,
-    pub z: i16,
-}
-
-#[binrw]
-#[derive(Debug, Clone)]
-pub struct KeyframeVector3 {
-    pub frame: i32,
-    pub value: Vector3,
-}
-
-
-#[binrw]
-#[derive(Debug, Clone)]
-pub struct KeyframeVector4 {
-    pub frame: i32,
-    pub value: Vector4,
-}
-
-#[binrw]
-#[derive(Debug, Clone)]
-pub struct KeyframeFloat {
-    pub frame: i32,
-    pub value: f32,
-}
-
-#[binrw]
-#[derive(Debug, Clone)]
-pub struct QuaternionShort {
-    pub x: i16,
-    pub y: i16,
-    pub z: i16,
-    pub w: i16,
-}
-
-#[binrw]
-#[derive(Debug, Clone)]
-pub struct RGB {
-    pub r: u8,
-    pub g: u8,
-    pub b: u8,
+use binrw::binrw;
+
+/// Scale of a SHORT3 scale value, 4096 being a scale of 1.
+pub const SCALE_COMPRESS: f32 = 4096.0;
+/// Scale of a SHORT4 quaternion component, 32767 being 1.
+pub const QUAT_COMPRESS: f32 = 32767.0;
+/// Scale of a BYTE3 color channel, 255 being 1.
+pub const RGB_CONVERT: f32 = 255.0;
+
+#[binrw]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vector3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+#[binrw]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vector4 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+#[binrw]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vector3Short {
+    pub x: i16,
+    pub y: i16,
+    pub z: i16,
+}
+
+#[binrw]
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyframeVector3 {
+    pub frame: i32,
+    pub value: Vector3,
+}
+
+
+#[binrw]
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyframeVector4 {
+    pub frame: i32,
+    pub value: Vector4,
+}
+
+#[binrw]
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyframeFloat {
+    pub frame: i32,
+    pub value: f32,
+}
+
+#[binrw]
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuaternionShort {
+    pub x: i16,
+    pub y: i16,
+    pub z: i16,
+    pub w: i16,
+}
+
+#[binrw]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RGB {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
 }
\ No newline at end of file
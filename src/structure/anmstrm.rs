@@ -1,171 +1,230 @@
-use binrw::binrw;
-
-use crate::structure::anm::{CoordParent, AnmCoord};
-use crate::structure::anm_utils::*;
-
-#[binrw]
-#[derive(Debug)]
-pub struct NuccAnmStrm {
-    pub anm_length: u32,
-    pub frame_size: u32,
-    pub frame_count: u16,
-    pub is_looped: u16,
-    pub clump_count: u16,
-    pub other_entry_count: u16,
-    pub other_index_count: u16,
-    pub coord_count: u16,
-
-    #[br(count = clump_count)]
-    pub clumps: Vec<AnmStrmClump>,
-
-    #[br(count = other_entry_count + other_index_count)]
-    pub other_entries_indices: Vec<u32>, 
-
-    #[br(count = coord_count)]
-    pub coord_parents: Vec<CoordParent>,
-
-    #[br(count = frame_count)]
-    pub frames: Vec<AnmStrmFrameInfo>
-}
-
-#[binrw]
-#[derive(Debug, Clone)]
-pub struct AnmStrmClump {
-    pub clump_index: u32,
-    pub bone_material_count: u16,
-    pub model_count: u16,
-
-    #[br(count = bone_material_count)]
-    pub bone_material_indices: Vec<u32>,
-    
-    #[br(count = model_count)]
-    pub model_indices: Vec<u32>,
-
-    #[br(count = model_count)]
-    pub unknown: Vec<u32>
-}
-
-#[binrw]
-#[derive(Debug)]
-pub struct AnmStrmFrameInfo {
-    pub frame_offset: u32,
-    pub frame_number: u16
-}
-
-
-#[binrw]
-#[derive(Debug, Clone)]
-pub struct NuccAnmStrmFrame {
-    pub frame_number: u32,
-    pub entry_count: u16,
-    pub unknown: u16,
-
-    #[br(count = entry_count)]
-    pub entries: Vec<AnmStrmEntry>
-}
-
-#[binrw]
-#[derive(Debug, Clone)]
-pub struct AnmStrmEntry {
-    pub coord: AnmCoord,
-    pub entry_format: u16,
-    pub entry_size: u16,
-
-    #[br(args(entry_format))]
-    pub entry_data: Entry
-}
-
-#[binrw]
-#[derive(Debug, Clone)]
-#[br(import(entry_format: u16))]
-pub enum Entry {
-    #[br(pre_assert(entry_format == 1))]
-    Bone(AnmEntryBone),
-
-    #[br(pre_assert(entry_format == 2))]
-    Camera(AnmEntryCamera),
-
-    #[br(pre_assert(entry_format == 4))]
-    Material(AnmEntryMaterial),
-
-    #[br(pre_assert(entry_format == 5))]
-    LightDirc(AnmEntryLightDirc),
-
-    #[br(pre_assert(entry_format == 6))]
-    LightPoint(AnmEntryLightPoint),
-
-    #[br(pre_assert(entry_format == 8))]
-    Ambient(AnmEntryAmbient),
-
-    #[br(pre_assert(entry_format == 12))]
-    MorphModel(AnmEntryMorphModel),
-
-
-    #[br(pre_assert(false))]
-    Unknown
-}
-
-
-
-#[binrw]
-#[derive(Debug, Clone)]
-pub struct AnmEntryBone {
-    pub frame_count: i32,
-    pub location: Vector3,
-    pub rotation: Vector4,
-    pub scale: Vector3,
-    pub toggled: f32
-}
-
-#[binrw]
-#[derive(Debug, Clone)]
-pub struct AnmEntryCamera {
-    pub frame_count: i32,
-    pub location: Vector3,
-    pub rotation: Vector4,
-    pub fov: f32,
-    pub scale: Vector3
-}
-
-#[binrw]
-#[derive(Debug, Clone)]
-pub struct AnmEntryMaterial {
-    pub frame_count: i32,
-    pub ambient_color: [f32; 16]
-}
-
-#[binrw]
-#[derive(Debug, Clone)]
-pub struct AnmEntryLightDirc {
-    pub frame_count: i32,
-    pub color: Vector3,
-    pub intensity: f32,
-    pub direction: Vector4,
-}
-
-#[binrw]
-#[derive(Debug, Clone)]
-pub struct AnmEntryLightPoint {
-    pub frame_count: i32,
-    pub color: Vector3,
-    pub position: Vector3,
-    pub intensity: f32,
-    pub radius: f32,
-    pub falloff: f32
-}
-
-#[binrw]
-#[derive(Debug, Clone)]
-pub struct AnmEntryAmbient {
-    pub frame_count: i32,
-    pub color: Vector3,
-    pub intensity: f32
-}
-
-#[binrw]
-#[derive(Debug, Clone)]
-pub struct AnmEntryMorphModel {
-    pub frame_count: i32,
-    #[br(count = frame_count)]
-    pub morph_weight: Vec<f32>,
+use binrw::binrw;
+
+use crate::structure::anm::{CoordParent, AnmCoord};
+use crate::structure::anm_utils::*;
+
+#[binrw]
+#[derive(Debug, Clone)]
+pub struct NuccAnmStrm {
+    pub anm_length: u32,
+    pub frame_size: u32,
+
+    #[bw(map = |_: &u16| frames.len() as u16)]
+    pub frame_count: u16,
+    pub is_looped: u16,
+
+    #[bw(map = |_: &u16| clumps.len() as u16)]
+    pub clump_count: u16,
+    pub other_entry_count: u16,
+    pub other_index_count: u16,
+
+    #[bw(map = |_: &u16| coord_parents.len() as u16)]
+    pub coord_count: u16,
+
+    #[br(count = clump_count)]
+    pub clumps: Vec<AnmStrmClump>,
+
+    #[br(count = other_entry_count + other_index_count)]
+    pub other_entries_indices: Vec<u32>, 
+
+    #[br(count = coord_count)]
+    pub coord_parents: Vec<CoordParent>,
+
+    #[br(count = frame_count)]
+    pub frames: Vec<AnmStrmFrameInfo>
+}
+
+impl NuccAnmStrm {
+    /// Rebuilds the frame infos from the frames, e.g. after frames were edited, added or removed.
+    /// Frame offsets are the positions of the frames laid out one after another.
+    pub fn update_frame_infos(&mut self, frames: &[NuccAnmStrmFrame]) {
+        let mut frame_offset = 0;
+
+        self.frames = frames.iter().map(|frame| {
+            let frame_info = AnmStrmFrameInfo {
+                frame_offset,
+                frame_number: frame.frame_number as u16,
+            };
+
+            frame_offset += frame.frame_size();
+            frame_info
+        }).collect();
+
+        self.frame_count = self.frames.len() as u16;
+    }
+}
+
+#[binrw]
+#[derive(Debug, Clone)]
+pub struct AnmStrmClump {
+    pub clump_index: u32,
+
+    #[bw(map = |_: &u16| bone_material_indices.len() as u16)]
+    pub bone_material_count: u16,
+
+    #[bw(map = |_: &u16| model_indices.len() as u16)]
+    pub model_count: u16,
+
+    #[br(count = bone_material_count)]
+    pub bone_material_indices: Vec<u32>,
+    
+    #[br(count = model_count)]
+    pub model_indices: Vec<u32>,
+
+    #[br(count = model_count)]
+    pub unknown: Vec<u32>
+}
+
+#[binrw]
+#[derive(Debug, Clone)]
+pub struct AnmStrmFrameInfo {
+    pub frame_offset: u32,
+    pub frame_number: u16
+}
+
+
+#[binrw]
+#[derive(Debug, Clone)]
+pub struct NuccAnmStrmFrame {
+    pub frame_number: u32,
+
+    #[bw(map = |_: &u16| entries.len() as u16)]
+    pub entry_count: u16,
+    pub unknown: u16,
+
+    #[br(count = entry_count)]
+    pub entries: Vec<AnmStrmEntry>
+}
+
+impl NuccAnmStrmFrame {
+    /// Size of the frame in bytes as it is written.
+    pub fn frame_size(&self) -> u32 {
+        8 + self.entries.iter().map(|entry| 8 + entry.entry_data.entry_size() as u32).sum::<u32>()
+    }
+}
+
+#[binrw]
+#[derive(Debug, Clone)]
+pub struct AnmStrmEntry {
+    pub coord: AnmCoord,
+    pub entry_format: u16,
+
+    #[bw(map = |_: &u16| entry_data.entry_size())]
+    pub entry_size: u16,
+
+    #[br(args(entry_format, entry_size))]
+    pub entry_data: Entry
+}
+
+#[binrw]
+#[derive(Debug, Clone)]
+#[br(import(entry_format: u16, entry_size: u16))]
+pub enum Entry {
+    #[br(pre_assert(entry_format == 1))]
+    Bone(AnmEntryBone),
+
+    #[br(pre_assert(entry_format == 2))]
+    Camera(AnmEntryCamera),
+
+    #[br(pre_assert(entry_format == 4))]
+    Material(AnmEntryMaterial),
+
+    #[br(pre_assert(entry_format == 5))]
+    LightDirc(AnmEntryLightDirc),
+
+    #[br(pre_assert(entry_format == 6))]
+    LightPoint(AnmEntryLightPoint),
+
+    #[br(pre_assert(entry_format == 8))]
+    Ambient(AnmEntryAmbient),
+
+    #[br(pre_assert(entry_format == 12))]
+    MorphModel(AnmEntryMorphModel),
+
+
+    // Entry formats we don't model are kept as raw bytes
+    #[br(pre_assert(!matches!(entry_format, 1 | 2 | 4 | 5 | 6 | 8 | 12)))]
+    Unknown(#[br(count = entry_size)] Vec<u8>)
+}
+
+impl Entry {
+    /// Size of the entry data in bytes as it is written, stored in [`AnmStrmEntry::entry_size`].
+    pub fn entry_size(&self) -> u16 {
+        match self {
+            Entry::Bone(_) => 48,
+            Entry::Camera(_) => 48,
+            Entry::Material(_) => 68,
+            Entry::LightDirc(_) => 36,
+            Entry::LightPoint(_) => 40,
+            Entry::Ambient(_) => 20,
+            Entry::MorphModel(entry) => 4 + 4 * entry.morph_weight.len() as u16,
+            Entry::Unknown(data) => data.len() as u16,
+        }
+    }
+}
+
+
+
+#[binrw]
+#[derive(Debug, Clone)]
+pub struct AnmEntryBone {
+    pub frame_count: i32,
+    pub location: Vector3,
+    pub rotation: Vector4,
+    pub scale: Vector3,
+    pub toggled: f32
+}
+
+#[binrw]
+#[derive(Debug, Clone)]
+pub struct AnmEntryCamera {
+    pub frame_count: i32,
+    pub location: Vector3,
+    pub rotation: Vector4,
+    pub fov: f32,
+    pub scale: Vector3
+}
+
+#[binrw]
+#[derive(Debug, Clone)]
+pub struct AnmEntryMaterial {
+    pub frame_count: i32,
+    pub ambient_color: [f32; 16]
+}
+
+#[binrw]
+#[derive(Debug, Clone)]
+pub struct AnmEntryLightDirc {
+    pub frame_count: i32,
+    pub color: Vector3,
+    pub intensity: f32,
+    pub direction: Vector4,
+}
+
+#[binrw]
+#[derive(Debug, Clone)]
+pub struct AnmEntryLightPoint {
+    pub frame_count: i32,
+    pub color: Vector3,
+    pub position: Vector3,
+    pub intensity: f32,
+    pub radius: f32,
+    pub falloff: f32
+}
+
+#[binrw]
+#[derive(Debug, Clone)]
+pub struct AnmEntryAmbient {
+    pub frame_count: i32,
+    pub color: Vector3,
+    pub intensity: f32
+}
+
+#[binrw]
+#[derive(Debug, Clone)]
+pub struct AnmEntryMorphModel {
+    #[bw(map = |_: &i32| morph_weight.len() as i32)]
+    pub frame_count: i32,
+    #[br(count = frame_count)]
+    pub morph_weight: Vec<f32>,
 }
\ No newline at end of file
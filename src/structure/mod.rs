@@ -1,4 +1,5 @@
-pub mod anmstrm;
-pub mod anm;
-pub mod anm_utils;
-pub mod page;
\ No newline at end of file
+pub mod anmstrm;
+pub mod anm;
+pub mod anm_utils;
+pub mod page;
+pub mod xfbin;
\ No newline at end of file
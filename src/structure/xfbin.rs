@@ -0,0 +1,80 @@
+use binrw::{binrw, NullString};
+
+
+#[binrw]
+#[brw(magic = b"NUCC")]
+#[derive(Debug, Clone)]
+pub struct NuccHeader {
+    pub version: u32,
+
+    #[brw(pad_before = 8)]
+    pub chunk_table_size: u32,
+    pub min_page_size: u32,
+    pub version2: u16,
+    pub unknown: u16,
+}
+
+#[binrw]
+#[derive(Debug, Clone)]
+pub struct NuccChunkTable {
+    pub chunk_type_count: u32,
+    pub chunk_type_size: u32,
+    pub file_path_count: u32,
+    pub file_path_size: u32,
+    pub chunk_name_count: u32,
+    pub chunk_name_size: u32,
+    pub chunk_map_count: u32,
+    pub chunk_map_size: u32,
+    pub chunk_map_indices_count: u32,
+    pub chunk_map_references_count: u32,
+
+    #[br(count = chunk_type_count)]
+    pub chunk_types: Vec<NullString>,
+
+    #[br(count = file_path_count)]
+    pub file_paths: Vec<NullString>,
+
+    #[br(count = chunk_name_count)]
+    pub chunk_names: Vec<NullString>,
+
+    #[brw(align_before = 4)]
+    #[br(count = chunk_map_count)]
+    pub chunk_maps: Vec<NuccChunkMap>,
+
+    #[br(count = chunk_map_references_count)]
+    pub chunk_map_references: Vec<NuccChunkReference>,
+
+    #[br(count = chunk_map_indices_count)]
+    pub chunk_map_indices: Vec<u32>,
+}
+
+#[binrw]
+#[derive(Debug, Clone)]
+pub struct NuccChunkMap {
+    pub chunk_type_index: u32,
+    pub file_path_index: u32,
+    pub chunk_name_index: u32,
+}
+
+#[binrw]
+#[derive(Debug, Clone)]
+pub struct NuccChunkReference {
+    pub chunk_name_index: u32,
+    pub chunk_map_index: u32,
+}
+
+#[binrw]
+#[derive(Debug, Clone)]
+pub struct NuccChunkHeader {
+    pub size: u32,
+    pub chunk_map_index: u32,
+    pub version: u16,
+    pub unknown: u16,
+}
+
+#[binrw]
+#[derive(Debug, Clone)]
+pub struct NuccChunkPage {
+    pub page_size: u32,
+    pub reference_size: u32,
+}
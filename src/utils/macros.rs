@@ -1,44 +1,79 @@
-use std::fs;
-
-#[macro_export]
-macro_rules! collect_files {
-    ($dir:expr, $($ext:expr),+) => {{
-        use std::fs;
-        use std::path::Path;
-
-        let dir_path = Path::new($dir);
-
-        let files: Vec<String> = fs::read_dir(dir_path)
-            .expect("Failed to read directory!")
-            .filter_map(Result::ok)
-            .filter(|entry| entry.file_type().ok().map(|ft| ft.is_file()).unwrap_or(false))
-            .filter_map(|entry| {
-                let ext = entry.path().extension().map(|ext| ext.to_string_lossy().to_lowercase());
-                match ext {
-                    $(Some(ext) if ext == $ext.to_string().to_lowercase() => Some(entry.path().to_string_lossy().to_string()),)*
-                    _ => None,
-                }
-            })
-            .collect();
-
-        if files.is_empty() {
-            Vec::new()
-        } else {
-            files
-        }
-    }};
-}
-
-pub fn find_subfolder(path: &str, pattern: &str) -> Option<String> {
-    // find the subfolder that contains the pattern
-    fs::read_dir(path)
-        .ok()?
-        .find_map(|entry| entry.ok().filter(|e| e.file_type().ok().map_or(false, |ft| ft.is_dir())).map(|e| e.path().to_string_lossy().to_string()))
-        .and_then(|subfolder| {
-            if subfolder.contains(pattern) {
-                Some(subfolder)
-            } else {
-                find_subfolder(&subfolder, pattern)
-            }
-        })
-}
\ No newline at end of file
+use std::{fs, path::{Path, PathBuf}};
+
+/// Collects the files in a directory with one of the given extensions, as a [`crate::Result`].
+#[macro_export]
+macro_rules! collect_files {
+    ($dir:expr, $($ext:expr),+) => {{
+        use std::fs;
+        use std::path::Path;
+
+        let dir_path = Path::new($dir);
+
+        fs::read_dir(dir_path)
+            .map(|entries| {
+                entries
+                    .filter_map(std::result::Result::ok)
+                    .filter(|entry| entry.file_type().ok().map(|ft| ft.is_file()).unwrap_or(false))
+                    .filter_map(|entry| {
+                        let ext = entry.path().extension().map(|ext| ext.to_string_lossy().to_lowercase());
+                        match ext {
+                            $(Some(ext) if ext == $ext.to_string().to_lowercase() => Some(entry.path().to_string_lossy().to_string()),)*
+                            _ => None,
+                        }
+                    })
+                    .collect::<Vec<String>>()
+            })
+            .map_err(|err| $crate::Error::from(err).with_path(dir_path))
+    }};
+}
+
+pub fn find_subfolder(path: &str, pattern: &str) -> Option<String> {
+    // find the subfolder that contains the pattern
+    fs::read_dir(path)
+        .ok()?
+        .find_map(|entry| entry.ok().filter(|e| e.file_type().ok().is_some_and(|ft| ft.is_dir())).map(|e| e.path().to_string_lossy().to_string()))
+        .and_then(|subfolder| {
+            if subfolder.contains(pattern) {
+                Some(subfolder)
+            } else {
+                find_subfolder(&subfolder, pattern)
+            }
+        })
+}
+
+/// Recursively collects every folder below `root` whose name contains the pattern.
+/// Unlike [`find_subfolder`] every branch of the tree is searched, symlinks are not followed
+/// and the result is sorted so runs are reproducible.
+pub fn find_subfolders<P: AsRef<Path>>(root: P, pattern: &str) -> Vec<PathBuf> {
+    let root = root.as_ref();
+
+    if root.file_name().is_some_and(|name| name.to_string_lossy().contains(pattern)) {
+        return vec![root.to_path_buf()];
+    }
+
+    let mut found = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in entries.filter_map(std::result::Result::ok) {
+            if !entry.file_type().is_ok_and(|ft| ft.is_dir()) {
+                continue;
+            }
+
+            let path = entry.path();
+
+            if entry.file_name().to_string_lossy().contains(pattern) {
+                found.push(path);
+            } else {
+                pending.push(path);
+            }
+        }
+    }
+
+    found.sort();
+    found
+}
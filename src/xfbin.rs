@@ -0,0 +1,445 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use binrw::{BinReaderExt, BinWriterExt, NullString};
+use hashbrown::HashMap;
+
+use crate::error::{Error, Result};
+use crate::structure::page::{Chunk, ChunkReference, Files, Page};
+use crate::structure::xfbin::*;
+
+/// Size of the [`NuccHeader`] in bytes, the chunk table starts right after it.
+pub const NUCC_HEADER_SIZE: u64 = 0x1C;
+
+/// Version written to the XFBIN header and to every chunk header.
+pub const NUCC_VERSION: u16 = 0x79;
+
+/// A data chunk read from an XFBIN page.
+#[derive(Debug, Clone)]
+pub struct XfbinChunk {
+    pub chunk: Chunk,
+    pub data: Vec<u8>,
+}
+
+/// A page of an XFBIN: the chunk maps and references it declares and the data chunks it contains.
+#[derive(Debug, Clone, Default)]
+pub struct XfbinPage {
+    pub chunk_maps: Vec<Chunk>,
+    pub chunk_references: Vec<ChunkReference>,
+    pub chunks: Vec<XfbinChunk>,
+}
+
+/// An XFBIN container split into its pages.
+#[derive(Debug, Clone, Default)]
+pub struct Xfbin {
+    pub pages: Vec<XfbinPage>,
+}
+
+/// An ANMSTRM chunk of an XFBIN together with its frame chunks and the page it was found in.
+#[derive(Debug)]
+pub struct XfbinAnmStrm<'a> {
+    pub page: &'a XfbinPage,
+    pub anmstrm: &'a XfbinChunk,
+    pub frames: Vec<&'a XfbinChunk>,
+}
+
+impl Xfbin {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Xfbin> {
+        let path = path.as_ref();
+        let mut reader = BufReader::new(File::open(path).map_err(|err| Error::from(err).with_path(path))?);
+
+        Xfbin::read(&mut reader).map_err(|err| err.with_path(path))
+    }
+
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Xfbin> {
+        let header: NuccHeader = reader.read_be().map_err(|err| Error::parse(reader, err))?;
+        let table: NuccChunkTable = reader.read_be().map_err(|err| Error::parse(reader, err))?;
+
+        let end = reader.seek(SeekFrom::End(0))?;
+        let mut offset = reader.seek(SeekFrom::Start(NUCC_HEADER_SIZE + header.chunk_table_size as u64))?;
+
+        let mut xfbin = Xfbin::default();
+        let mut page = XfbinPage::default();
+
+        // Chunk map indices inside a chunk are relative to the current page
+        let mut map_offset = 0;
+        let mut reference_offset = 0;
+
+        while offset < end {
+            let chunk_header: NuccChunkHeader = reader.read_be().map_err(|err| Error::parse(reader, err))?;
+
+            // Checked before allocating, the size of a truncated or corrupt chunk can be anything
+            let remaining = end - reader.stream_position()?;
+            if chunk_header.size as u64 > remaining {
+                return Err(Error::InvalidXfbin {
+                    offset,
+                    message: format!("chunk of {} bytes with only {} bytes left", chunk_header.size, remaining),
+                });
+            }
+
+            let mut data = vec![0; chunk_header.size as usize];
+            reader.read_exact(&mut data)?;
+
+            let map_index = table_index(&table.chunk_map_indices, map_offset + chunk_header.chunk_map_index as usize, offset)?;
+            let chunk = table_chunk(&table, map_index as usize, offset)?;
+
+            match chunk.types.as_str() {
+                "nuccChunkNull" => {}
+                "nuccChunkPage" => {
+                    let page_info: NuccChunkPage = std::io::Cursor::new(&data)
+                        .read_be()
+                        .map_err(|source| Error::Parse { path: None, offset, source })?;
+
+                    let page_size = page_info.page_size as usize;
+                    let reference_size = page_info.reference_size as usize;
+
+                    for i in map_offset..map_offset + page_size {
+                        let map_index = table_index(&table.chunk_map_indices, i, offset)?;
+                        page.chunk_maps.push(table_chunk(&table, map_index as usize, offset)?);
+                    }
+
+                    for i in reference_offset..reference_offset + reference_size {
+                        let reference = table.chunk_map_references.get(i).ok_or_else(|| Error::InvalidXfbin {
+                            offset,
+                            message: format!("chunk reference {} out of range", i),
+                        })?;
+
+                        page.chunk_references.push(ChunkReference {
+                            name: table_string(&table.chunk_names, reference.chunk_name_index as usize, offset)?,
+                            chunk: table_chunk(&table, reference.chunk_map_index as usize, offset)?,
+                        });
+                    }
+
+                    map_offset += page_size;
+                    reference_offset += reference_size;
+
+                    xfbin.pages.push(std::mem::take(&mut page));
+                }
+                _ => page.chunks.push(XfbinChunk { chunk, data }),
+            }
+
+            offset = reader.stream_position()?;
+        }
+
+        // Chunks after the last page chunk still belong to a page
+        if !page.chunks.is_empty() {
+            xfbin.pages.push(page);
+        }
+
+        Ok(xfbin)
+    }
+
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let file = File::create(path).map_err(|err| Error::from(err).with_path(path))?;
+        let mut writer = BufWriter::new(file);
+
+        self.write(&mut writer)
+            .and_then(|_| writer.flush().map_err(Error::from))
+            .map_err(|err| err.with_path(path))
+    }
+
+    /// Writes the XFBIN: header, chunk table, then every page's data chunks framed by a null chunk
+    /// and a page chunk.
+    pub fn write<W: Write + Seek>(&self, writer: &mut W) -> Result<()> {
+        let mut table = ChunkTableBuilder::default();
+
+        // Local chunk map indices of each page's null chunk, page chunk and data chunks
+        let mut page_chunk_indices = Vec::new();
+
+        for page in &self.pages {
+            let mut chunk_maps = page.chunk_maps.clone();
+
+            let null_index = local_map_index(&mut chunk_maps, &Chunk::empty("nuccChunkNull"), |map| map.types == "nuccChunkNull");
+            let data_indices: Vec<u32> = page
+                .chunks
+                .iter()
+                .map(|chunk| local_map_index(&mut chunk_maps, &chunk.chunk, |map| map == &chunk.chunk))
+                .collect();
+            let page_index = local_map_index(&mut chunk_maps, &Chunk::empty("nuccChunkPage"), |map| map.types == "nuccChunkPage");
+
+            if !chunk_maps.iter().any(|chunk| chunk.types == "nuccChunkIndex") {
+                chunk_maps.push(Chunk {
+                    name: "index".to_string(),
+                    types: "nuccChunkIndex".to_string(),
+                    path: String::new(),
+                });
+            }
+
+            for chunk in &chunk_maps {
+                let map_index = table.map_index(chunk);
+                table.map_indices.push(map_index);
+            }
+
+            for reference in &page.chunk_references {
+                let reference = NuccChunkReference {
+                    chunk_name_index: table.names.index(&reference.name),
+                    chunk_map_index: table.map_index(&reference.chunk),
+                };
+                table.references.push(reference);
+            }
+
+            let page_info = NuccChunkPage {
+                page_size: chunk_maps.len() as u32,
+                reference_size: page.chunk_references.len() as u32,
+            };
+
+            page_chunk_indices.push((null_index, data_indices, page_index, page_info));
+        }
+
+        let mut table_data = Cursor::new(Vec::new());
+        table_data
+            .write_be(&table.build())
+            .map_err(|source| Error::Write { path: None, source })?;
+        let table_data = table_data.into_inner();
+
+        let header = NuccHeader {
+            version: NUCC_VERSION as u32,
+            chunk_table_size: table_data.len() as u32,
+            min_page_size: 3,
+            version2: NUCC_VERSION,
+            unknown: 0,
+        };
+
+        writer.write_be(&header).map_err(|source| Error::Write { path: None, source })?;
+        writer.write_all(&table_data)?;
+
+        for (page, (null_index, data_indices, page_index, page_info)) in self.pages.iter().zip(page_chunk_indices) {
+            write_chunk(writer, null_index, &[])?;
+
+            for (chunk, map_index) in page.chunks.iter().zip(data_indices) {
+                write_chunk(writer, map_index, &chunk.data)?;
+            }
+
+            let mut page_data = Cursor::new(Vec::new());
+            page_data.write_be(&page_info).map_err(|source| Error::Write { path: None, source })?;
+
+            write_chunk(writer, page_index, &page_data.into_inner())?;
+        }
+
+        Ok(())
+    }
+
+    /// Finds every ANMSTRM chunk along with the ANMSTRM frame chunks that follow it.
+    pub fn anmstrms(&self) -> Vec<XfbinAnmStrm<'_>> {
+        let mut anmstrms: Vec<XfbinAnmStrm> = Vec::new();
+
+        for page in &self.pages {
+            for chunk in &page.chunks {
+                match chunk.chunk.types.as_str() {
+                    "nuccChunkAnmStrm" => anmstrms.push(XfbinAnmStrm {
+                        page,
+                        anmstrm: chunk,
+                        frames: Vec::new(),
+                    }),
+                    "nuccChunkAnmStrmFrame" => {
+                        if let Some(anmstrm) = anmstrms.last_mut() {
+                            anmstrm.frames.push(chunk);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        anmstrms
+    }
+}
+
+impl XfbinPage {
+    /// Builds a page from a `_page.json` and the contents of the chunk files it lists, keyed by file name.
+    pub fn from_page(page: &Page, files: &HashMap<String, Vec<u8>>) -> Result<XfbinPage> {
+        let chunks = page
+            .files
+            .iter()
+            .map(|file| match files.get(&file.file_name) {
+                Some(data) => Ok(XfbinChunk {
+                    chunk: file.chunk.clone(),
+                    data: data.clone(),
+                }),
+                None => Err(Error::MissingChunk {
+                    path: file.file_name.clone().into(),
+                    chunk_type: file.chunk.types.clone(),
+                }),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(XfbinPage {
+            chunk_maps: page.chunk_maps.clone(),
+            chunk_references: page.chunk_references.clone(),
+            chunks,
+        })
+    }
+
+    /// Builds the `_page.json` an XFBIN extractor writes for this page.
+    pub fn to_page(&self) -> Page {
+        Page {
+            chunk_maps: self.chunk_maps.clone(),
+            chunk_references: self.chunk_references.clone(),
+            files: self.chunks.iter().zip(self.chunk_file_names()).map(|(chunk, file_name)| Files {
+                file_name,
+                chunk: chunk.chunk.clone(),
+            }).collect(),
+        }
+    }
+
+    /// File names of the data chunks as an XFBIN extractor would write them, e.g. `name.anmstrm`
+    /// or `name_12.anmstrmframe`.
+    pub fn chunk_file_names(&self) -> Vec<String> {
+        let mut frame_number = 0;
+
+        self.chunks.iter().map(|chunk| {
+            let extension = chunk_extension(&chunk.chunk.types);
+
+            if chunk.chunk.types == "nuccChunkAnmStrmFrame" {
+                frame_number += 1;
+                format!("{}_{}.{}", chunk.chunk.name, frame_number - 1, extension)
+            } else {
+                format!("{}.{}", chunk.chunk.name, extension)
+            }
+        }).collect()
+    }
+}
+
+/// File extension used for a chunk type, e.g. `nuccChunkLightDirc` -> `lightdirc`.
+pub fn chunk_extension(chunk_type: &str) -> String {
+    chunk_type.trim_start_matches("nuccChunk").to_lowercase()
+}
+
+/// Index of the first chunk map of a page matching `matches`, adding `chunk` if the page doesn't declare one.
+fn local_map_index(chunk_maps: &mut Vec<Chunk>, chunk: &Chunk, matches: impl Fn(&Chunk) -> bool) -> u32 {
+    let index = chunk_maps.iter().position(matches).unwrap_or_else(|| {
+        chunk_maps.push(chunk.clone());
+        chunk_maps.len() - 1
+    });
+
+    index as u32
+}
+
+fn write_chunk<W: Write + Seek>(writer: &mut W, chunk_map_index: u32, data: &[u8]) -> Result<()> {
+    let header = NuccChunkHeader {
+        size: data.len() as u32,
+        chunk_map_index,
+        version: NUCC_VERSION,
+        unknown: 0,
+    };
+
+    writer.write_be(&header).map_err(|source| Error::Write { path: None, source })?;
+    writer.write_all(data)?;
+
+    Ok(())
+}
+
+/// Deduplicated strings of one of the chunk table's string tables.
+#[derive(Default)]
+struct StringTable {
+    strings: Vec<String>,
+    lookup: HashMap<String, u32>,
+}
+
+impl StringTable {
+    fn index(&mut self, string: &str) -> u32 {
+        if let Some(&index) = self.lookup.get(string) {
+            return index;
+        }
+
+        let index = self.strings.len() as u32;
+        self.strings.push(string.to_string());
+        self.lookup.insert(string.to_string(), index);
+        index
+    }
+
+    fn size(&self) -> u32 {
+        self.strings.iter().map(|string| string.len() as u32 + 1).sum()
+    }
+
+    fn to_null_strings(&self) -> Vec<NullString> {
+        self.strings.iter().map(|string| NullString::from(string.as_str())).collect()
+    }
+}
+
+/// Collects the chunk maps, references and page map indices of every page into one chunk table.
+#[derive(Default)]
+struct ChunkTableBuilder {
+    types: StringTable,
+    paths: StringTable,
+    names: StringTable,
+    maps: Vec<NuccChunkMap>,
+    map_lookup: HashMap<(u32, u32, u32), u32>,
+    map_indices: Vec<u32>,
+    references: Vec<NuccChunkReference>,
+}
+
+impl ChunkTableBuilder {
+    fn map_index(&mut self, chunk: &Chunk) -> u32 {
+        let key = (
+            self.types.index(&chunk.types),
+            self.paths.index(&chunk.path),
+            self.names.index(&chunk.name),
+        );
+
+        if let Some(&index) = self.map_lookup.get(&key) {
+            return index;
+        }
+
+        let index = self.maps.len() as u32;
+        self.maps.push(NuccChunkMap {
+            chunk_type_index: key.0,
+            file_path_index: key.1,
+            chunk_name_index: key.2,
+        });
+        self.map_lookup.insert(key, index);
+        index
+    }
+
+    fn build(&self) -> NuccChunkTable {
+        NuccChunkTable {
+            chunk_type_count: self.types.strings.len() as u32,
+            chunk_type_size: self.types.size(),
+            file_path_count: self.paths.strings.len() as u32,
+            file_path_size: self.paths.size(),
+            chunk_name_count: self.names.strings.len() as u32,
+            chunk_name_size: self.names.size(),
+            chunk_map_count: self.maps.len() as u32,
+            chunk_map_size: self.maps.len() as u32 * 12,
+            chunk_map_indices_count: self.map_indices.len() as u32,
+            chunk_map_references_count: self.references.len() as u32,
+            chunk_types: self.types.to_null_strings(),
+            file_paths: self.paths.to_null_strings(),
+            chunk_names: self.names.to_null_strings(),
+            chunk_maps: self.maps.clone(),
+            chunk_map_references: self.references.clone(),
+            chunk_map_indices: self.map_indices.clone(),
+        }
+    }
+}
+
+fn table_index(indices: &[u32], index: usize, offset: u64) -> Result<u32> {
+    indices.get(index).copied().ok_or_else(|| Error::InvalidXfbin {
+        offset,
+        message: format!("chunk map index {} out of range", index),
+    })
+}
+
+fn table_string(strings: &[NullString], index: usize, offset: u64) -> Result<String> {
+    strings.get(index).map(|string| string.to_string()).ok_or_else(|| Error::InvalidXfbin {
+        offset,
+        message: format!("string index {} out of range", index),
+    })
+}
+
+fn table_chunk(table: &NuccChunkTable, map_index: usize, offset: u64) -> Result<Chunk> {
+    let map = table.chunk_maps.get(map_index).ok_or_else(|| Error::InvalidXfbin {
+        offset,
+        message: format!("chunk map {} out of range", map_index),
+    })?;
+
+    Ok(Chunk {
+        name: table_string(&table.chunk_names, map.chunk_name_index as usize, offset)?,
+        types: table_string(&table.chunk_types, map.chunk_type_index as usize, offset)?,
+        path: table_string(&table.file_paths, map.file_path_index as usize, offset)?,
+    })
+}
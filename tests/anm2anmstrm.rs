@@ -0,0 +1,176 @@
+use std::io::Cursor;
+
+use binrw::BinWriterExt;
+
+use anmstrm2anm::{
+    build_page::{anm_page, anmstrm_page},
+    convert_anm, convert_anmstrm, read_anmstrm,
+    structure::{
+        anm::AnmEntry,
+        anmstrm::*,
+        page::{Chunk, Files, Page},
+    },
+    ConvertOptions, NuccAnm, NuccAnmStrm,
+};
+
+mod common;
+use common::{bone, camera, entry, write_read, StreamBuilder, IDENTITY};
+
+const FRAME_COUNT: u32 = 3;
+
+fn moving_bone(coord_index: u16, frame: u32) -> AnmStrmEntry {
+    let angle = 0.1 * frame as f32;
+    let location = [frame as f32 + coord_index as f32, 0.5, 0.0];
+
+    entry(0, coord_index, bone(location, [0.0, 0.0, angle.sin(), angle.cos()], [1.0; 3]))
+}
+
+/// A clump with two moving bones, one parented to the other, and a camera.
+fn stream() -> (NuccAnmStrm, Vec<NuccAnmStrmFrame>) {
+    StreamBuilder::new()
+        .clump(1, &[2, 3])
+        .other_entries(&[4])
+        .coord_parent((0, 0), (0, 1))
+        .frames(FRAME_COUNT, |frame| {
+            let camera = camera([0.0, 2.0 * frame as f32, 0.0], IDENTITY, 40.0 + frame as f32);
+            vec![moving_bone(0, frame), moving_bone(1, frame), entry(-1, 0, camera)]
+        })
+        .build()
+}
+
+fn chunk(name: &str, types: &str) -> Chunk {
+    Chunk {
+        name: name.to_string(),
+        types: types.to_string(),
+        path: "c/x/x.max".to_string(),
+    }
+}
+
+fn page() -> Page {
+    let file = |file_name: &str, chunk: Chunk| Files {
+        file_name: file_name.to_string(),
+        chunk,
+    };
+
+    Page {
+        chunk_maps: vec![
+            Chunk::empty("nuccChunkNull"),
+            chunk("x_anm", "nuccChunkAnmStrm"),
+            chunk("x_anm", "nuccChunkAnmStrmFrame"),
+            chunk("xbod1", "nuccChunkClump"),
+            chunk("xbod1", "nuccChunkCoord"),
+            chunk("xbod2", "nuccChunkCoord"),
+            chunk("x_cam", "nuccChunkCamera"),
+            Chunk::empty("nuccChunkPage"),
+        ],
+        chunk_references: Vec::new(),
+        files: [file("x_anm.anmstrm", chunk("x_anm", "nuccChunkAnmStrm"))]
+            .into_iter()
+            .chain((0..FRAME_COUNT).map(|frame| file(&format!("x_anm_{}.anmstrmframe", frame), chunk("x_anm", "nuccChunkAnmStrmFrame"))))
+            .collect(),
+    }
+}
+
+fn options() -> ConvertOptions {
+    ConvertOptions {
+        collapse_constant_curves: false,
+        ..common::options()
+    }
+}
+
+/// Converts the stream to an anm and reads it back from its bytes.
+fn anm() -> NuccAnm {
+    let (anmstrm, frames) = stream();
+    write_read(&convert_anmstrm(&anmstrm, frames, &options()).unwrap().remove(0))
+}
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!((actual - expected).abs() < 1e-3, "{} != {}", actual, expected);
+}
+
+#[test]
+fn anm_converts_back_to_the_same_structure() {
+    let (original, _) = stream();
+    let (anmstrm, frames) = convert_anm(&anm()).unwrap();
+
+    assert_eq!(anmstrm.frame_count as u32, FRAME_COUNT);
+    assert_eq!(anmstrm.frames.len() as u32, FRAME_COUNT);
+    assert_eq!((anmstrm.anm_length, anmstrm.frame_size), (original.anm_length, original.frame_size));
+
+    assert_eq!(anmstrm.clump_count, 1);
+    assert_eq!(anmstrm.clumps[0].clump_index, 1);
+    assert_eq!(anmstrm.clumps[0].bone_material_indices, [2, 3]);
+    assert_eq!(anmstrm.other_entries_indices, [4]);
+    assert_eq!(anmstrm.coord_parents, original.coord_parents);
+
+    // The rebuilt anmstrm survives being written and read again
+    let mut data = Cursor::new(Vec::new());
+    data.write_be(&anmstrm).unwrap();
+    let read_back = read_anmstrm(&mut Cursor::new(data.into_inner())).unwrap();
+    assert_eq!(read_back.frames.len() as u32, FRAME_COUNT);
+    assert_eq!(read_back.clumps[0].bone_material_indices, [2, 3]);
+
+    assert_eq!(frames.len() as u32, FRAME_COUNT);
+    for (frame, anmstrmframe) in frames.iter().enumerate() {
+        assert_eq!(anmstrmframe.frame_number, frame as u32);
+        assert_eq!(anmstrmframe.entry_count, 3);
+        assert_eq!(anmstrmframe.entries.len(), 3);
+    }
+}
+
+#[test]
+fn frames_hold_the_original_poses() {
+    let (_, original) = stream();
+    let (_, frames) = convert_anm(&anm()).unwrap();
+
+    for (frame, original) in frames.iter().zip(&original) {
+        for expected in &original.entries {
+            let entry = frame.entries.iter().find(|entry| entry.coord == expected.coord).unwrap();
+            assert_eq!(entry.entry_format, expected.entry_format);
+
+            match (&entry.entry_data, &expected.entry_data) {
+                (Entry::Bone(bone), Entry::Bone(expected)) => {
+                    assert_close(bone.location.x, expected.location.x);
+                    assert_close(bone.location.y, expected.location.y);
+                    assert_close(bone.rotation.z, expected.rotation.z);
+                    assert_close(bone.rotation.w, expected.rotation.w);
+                    assert_close(bone.scale.x, 1.0);
+                }
+                (Entry::Camera(camera), Entry::Camera(expected)) => {
+                    assert_close(camera.location.y, expected.location.y);
+                    assert_close(camera.fov, expected.fov);
+                }
+                other => panic!("unexpected entries {:?}", other),
+            }
+        }
+    }
+}
+
+#[test]
+fn converting_twice_gives_the_same_anm() {
+    let anm = anm();
+    let (anmstrm, frames) = convert_anm(&anm).unwrap();
+    let again = convert_anmstrm(&anmstrm, frames, &options()).unwrap().remove(0);
+
+    assert_eq!(again.entry_count, anm.entry_count);
+    assert_eq!(again.clumps, anm.clumps);
+
+    for (entry, expected) in again.entries.iter().zip(&anm.entries) {
+        assert_eq!(entry.coord, expected.coord);
+        let formats = |entry: &AnmEntry| entry.curve_headers.iter().map(|header| (header.curve_format, header.frame_count)).collect::<Vec<_>>();
+        assert_eq!(formats(entry), formats(expected));
+    }
+}
+
+#[test]
+fn anm_page_is_rebuilt_into_the_anmstrm_page() {
+    let (_, frames) = convert_anm(&anm()).unwrap();
+    let rebuilt = anmstrm_page(anm_page(page(), None), frames.len());
+
+    let types = |page: &Page| page.chunk_maps.iter().map(|chunk| chunk.types.clone()).collect::<Vec<_>>();
+    let file_names = |page: &Page| page.files.iter().map(|file| file.file_name.clone()).collect::<Vec<_>>();
+
+    assert_eq!(types(&rebuilt), types(&page()));
+    assert_eq!(file_names(&rebuilt), file_names(&page()));
+    assert_eq!(rebuilt.files[1].chunk, chunk("x_anm", "nuccChunkAnmStrmFrame"));
+}
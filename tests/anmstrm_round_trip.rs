@@ -0,0 +1,211 @@
+use std::io::Cursor;
+
+use binrw::{BinWrite, BinWriterExt};
+
+use anmstrm2anm::{
+    read_anmstrm, read_anmstrm_frame,
+    structure::anmstrm::{Entry, NuccAnmStrmFrame},
+};
+
+/// Entry formats with their data: a frame count of 1 followed by the entry's floats.
+fn float_entries() -> Vec<(u16, Vec<u8>)> {
+    let floats = |count: usize| -> Vec<u8> {
+        let mut data = 1i32.to_be_bytes().to_vec();
+        data.extend((0..count).flat_map(|value| (value as f32 * 0.5 - 1.0).to_be_bytes()));
+        data
+    };
+
+    vec![
+        (1, floats(11)),  // bone
+        (2, floats(11)),  // camera
+        (4, floats(16)),  // material
+        (5, floats(8)),   // light dirc
+        (6, floats(9)),   // light point
+        (8, floats(4)),   // ambient
+    ]
+}
+
+fn morph_model_data(weights: &[f32]) -> Vec<u8> {
+    let mut data = (weights.len() as i32).to_be_bytes().to_vec();
+    data.extend(weights.iter().flat_map(|weight| weight.to_be_bytes()));
+    data
+}
+
+fn frame_bytes(frame_number: u32, entries: &[(u16, Vec<u8>)]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend(frame_number.to_be_bytes());
+    bytes.extend((entries.len() as u16).to_be_bytes());
+    bytes.extend(0u16.to_be_bytes());
+
+    for (coord_index, (entry_format, data)) in entries.iter().enumerate() {
+        bytes.extend(0i16.to_be_bytes());
+        bytes.extend((coord_index as u16).to_be_bytes());
+        bytes.extend(entry_format.to_be_bytes());
+        bytes.extend((data.len() as u16).to_be_bytes());
+        bytes.extend(data);
+    }
+
+    bytes
+}
+
+fn write<T: BinWrite<Args = ()>>(value: &T) -> Vec<u8> {
+    let mut writer = Cursor::new(Vec::new());
+    writer.write_be(value).unwrap();
+    writer.into_inner()
+}
+
+fn read_frame(bytes: &[u8]) -> NuccAnmStrmFrame {
+    let mut reader = Cursor::new(bytes);
+    let frame = read_anmstrm_frame(&mut reader).unwrap();
+
+    assert_eq!(reader.position() as usize, bytes.len(), "frame not fully read");
+    frame
+}
+
+/// Reads, writes and reads the frame again, checking the bytes and values survive.
+fn assert_round_trip(bytes: &[u8]) -> NuccAnmStrmFrame {
+    let frame = read_frame(bytes);
+    let written = write(&frame);
+    assert_eq!(written, bytes);
+
+    let reread = read_frame(&written);
+    assert_eq!(format!("{:?}", reread), format!("{:?}", frame));
+    assert_eq!(frame.frame_size() as usize, bytes.len());
+
+    frame
+}
+
+#[test]
+fn every_float_entry_format() {
+    for (entry_format, data) in float_entries() {
+        let frame = assert_round_trip(&frame_bytes(3, &[(entry_format, data.clone())]));
+
+        let entry = &frame.entries[0];
+        assert_eq!(entry.entry_format, entry_format);
+        assert_eq!(entry.entry_data.entry_size() as usize, data.len());
+        assert!(!matches!(entry.entry_data, Entry::Unknown(_)), "format {} read as unknown", entry_format);
+    }
+}
+
+#[test]
+fn morph_model_entry() {
+    let frame = assert_round_trip(&frame_bytes(0, &[(12, morph_model_data(&[0.0, 0.25, 1.0]))]));
+
+    match &frame.entries[0].entry_data {
+        Entry::MorphModel(entry) => assert_eq!(entry.morph_weight, [0.0, 0.25, 1.0]),
+        other => panic!("unexpected entry {:?}", other),
+    }
+}
+
+#[test]
+fn unknown_entry() {
+    let frame = assert_round_trip(&frame_bytes(0, &[(99, vec![1, 2, 3, 4, 5, 6])]));
+
+    match &frame.entries[0].entry_data {
+        Entry::Unknown(data) => assert_eq!(data, &[1, 2, 3, 4, 5, 6]),
+        other => panic!("unexpected entry {:?}", other),
+    }
+}
+
+#[test]
+fn mixed_entries() {
+    let mut entries = float_entries();
+    entries.push((12, morph_model_data(&[0.5; 5])));
+    entries.push((99, vec![9; 10]));
+
+    let frame = assert_round_trip(&frame_bytes(7, &entries));
+    assert_eq!(frame.entries.len(), entries.len());
+}
+
+#[test]
+fn edited_frame_updates_sizes_and_counts() {
+    let mut frame = read_frame(&frame_bytes(0, &[(12, morph_model_data(&[0.5]))]));
+
+    match &mut frame.entries[0].entry_data {
+        Entry::MorphModel(entry) => entry.morph_weight.extend([0.75, 1.0]),
+        other => panic!("unexpected entry {:?}", other),
+    }
+    let mut copy = frame.entries[0].clone();
+    copy.coord.coord_index = 1;
+    frame.entries.push(copy);
+
+    // Stale counts and sizes are recomputed on write
+    let morph_model = (12, morph_model_data(&[0.5, 0.75, 1.0]));
+    assert_eq!(write(&frame), frame_bytes(0, &[morph_model.clone(), morph_model]));
+}
+
+#[test]
+fn anmstrm_header() {
+    let mut bytes = Vec::new();
+    for value in [300u32, 100] {
+        bytes.extend(value.to_be_bytes());
+    }
+    // frame count, looped, clump count, other entry count, other index count, coord count
+    for value in [3u16, 1, 1, 1, 0, 2] {
+        bytes.extend(value.to_be_bytes());
+    }
+    // clump with 2 bones and a model
+    bytes.extend(0u32.to_be_bytes());
+    bytes.extend(2u16.to_be_bytes());
+    bytes.extend(1u16.to_be_bytes());
+    for value in [4u32, 5, 6, 0] {
+        bytes.extend(value.to_be_bytes());
+    }
+    // other entries
+    bytes.extend(9u32.to_be_bytes());
+    // coord parents
+    for value in [0i16, 0, 0, 1, 0, 1, 0, 2] {
+        bytes.extend(value.to_be_bytes());
+    }
+    // frame infos
+    for (frame_offset, frame_number) in [(0u32, 0u16), (64, 1), (128, 2)] {
+        bytes.extend(frame_offset.to_be_bytes());
+        bytes.extend(frame_number.to_be_bytes());
+    }
+
+    let mut reader = Cursor::new(&bytes);
+    let mut anmstrm = read_anmstrm(&mut reader).unwrap();
+    assert_eq!(reader.position() as usize, bytes.len(), "anmstrm not fully read");
+    assert_eq!(write(&anmstrm), bytes);
+
+    // Dropping a frame and a coord is reflected in the written counts
+    anmstrm.frames.pop();
+    anmstrm.coord_parents.pop();
+
+    let written = write(&anmstrm);
+    let reread = read_anmstrm(&mut Cursor::new(&written)).unwrap();
+    assert_eq!(reread.frame_count, 2);
+    assert_eq!(reread.coord_count, 1);
+    assert_eq!(reread.frames.len(), 2);
+}
+
+#[test]
+fn frame_infos_follow_frame_sizes() {
+    let entries = float_entries();
+    let frames: Vec<NuccAnmStrmFrame> = (0..3)
+        .map(|frame| read_frame(&frame_bytes(frame, &entries[..frame as usize + 1])))
+        .collect();
+
+    let mut anmstrm = read_anmstrm(&mut Cursor::new(frames_anmstrm_bytes())).unwrap();
+    anmstrm.update_frame_infos(&frames);
+
+    let offsets: Vec<u32> = anmstrm.frames.iter().map(|info| info.frame_offset).collect();
+    let sizes: Vec<u32> = frames.iter().map(|frame| write(frame).len() as u32).collect();
+    assert_eq!(offsets, [0, sizes[0], sizes[0] + sizes[1]]);
+    assert_eq!(anmstrm.frame_count, 3);
+
+    let numbers: Vec<u16> = anmstrm.frames.iter().map(|info| info.frame_number).collect();
+    assert_eq!(numbers, [0, 1, 2]);
+}
+
+/// An anmstrm without clumps, coords or frames.
+fn frames_anmstrm_bytes() -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for value in [300u32, 100] {
+        bytes.extend(value.to_be_bytes());
+    }
+    for _ in 0..6 {
+        bytes.extend(0u16.to_be_bytes());
+    }
+    bytes
+}
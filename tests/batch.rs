@@ -0,0 +1,81 @@
+use std::{fs, path::Path};
+
+use anmstrm2anm::read_anm_file;
+
+mod common;
+use common::{bone_at, entry, run_cli, temp_dir, write_xfbin_folder, StreamBuilder};
+
+fn write_stream(xfbin_dir: &Path, name: &str) {
+    let (anmstrm, frames) = StreamBuilder::new()
+        .clump(1, &[2])
+        .frames(2, |frame| vec![entry(0, 0, bone_at([frame as f32, 0.0, 0.0]))])
+        .build();
+
+    write_xfbin_folder(xfbin_dir, name, &anmstrm, &frames);
+}
+
+fn anm_file(output_dir: &Path, name: &str) -> std::path::PathBuf {
+    output_dir.join(format!("{}_converted", name)).join(format!("[000] {} (nuccChunkAnm)", name)).join(format!("{}.anm", name))
+}
+
+#[test]
+fn same_named_folders_are_written_below_their_subfolders() {
+    let dir = temp_dir("batch_mirrored");
+    let (root, out) = (dir.join("in"), dir.join("out"));
+    write_stream(&root.join("a").join("x"), "x");
+    write_stream(&root.join("b").join("c").join("x"), "x");
+
+    let output = run_cli(&["batch".as_ref(), root.as_os_str(), "-o".as_ref(), out.as_os_str(), "-q".as_ref()]);
+    let anms = [out.join("a"), out.join("b").join("c")].map(|output_dir| read_anm_file(anm_file(&output_dir, "x")).map(|anm| anm.entries.len()));
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(anms.map(Result::ok), [Some(1), Some(1)]);
+}
+
+#[test]
+fn list_files_are_converted_with_a_summary() {
+    let dir = temp_dir("batch_list");
+    let out = dir.join("out");
+    write_stream(&dir.join("good").join("x"), "x");
+    write_stream(&dir.join("bad").join("y"), "y");
+    let bad_anmstrm = dir.join("bad").join("y").join("[000] y (nuccChunkAnmStrmFrame)").join("y.anmstrm");
+    fs::write(&bad_anmstrm, [0u8; 6]).unwrap();
+    fs::write(dir.join("folders.txt"), "# cutscenes\ngood\n\nbad\n").unwrap();
+
+    let output = run_cli(&["batch".as_ref(), "-l".as_ref(), dir.join("folders.txt").as_os_str(), "-o".as_ref(), out.as_os_str()]);
+    let converted = anm_file(&out, "x").is_file();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(converted);
+    assert!(stdout.contains("FAILED"), "{}", stdout);
+    assert!(stdout.contains("1 converted, 1 failed"), "{}", stdout);
+}
+
+#[test]
+fn folders_written_to_the_same_output_are_rejected() {
+    let dir = temp_dir("batch_collision");
+    write_stream(&dir.join("a").join("x"), "x");
+    write_stream(&dir.join("b").join("x"), "x");
+    fs::write(dir.join("folders.txt"), "a\nb\n").unwrap();
+
+    let output = run_cli(&["batch".as_ref(), "-l".as_ref(), dir.join("folders.txt").as_os_str(), "-o".as_ref(), dir.join("out").as_os_str()]);
+    let written = dir.join("out").exists();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("would all be written to"));
+    assert!(!written);
+}
+
+#[test]
+fn roots_without_chunk_folders_exit_with_3() {
+    let dir = temp_dir("batch_empty");
+
+    let output = run_cli(&["batch".as_ref(), dir.as_os_str(), "-q".as_ref()]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(output.status.code(), Some(3));
+}
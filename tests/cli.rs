@@ -0,0 +1,63 @@
+use anmstrm2anm::read_anm_file;
+
+mod common;
+use common::{bone_at, entry, run_cli, temp_dir, write_xfbin_folder, StreamBuilder};
+
+fn write_stream(xfbin_dir: &std::path::Path, name: &str) {
+    let (anmstrm, frames) = StreamBuilder::new()
+        .clump(1, &[2])
+        .frames(3, |frame| vec![entry(0, 0, bone_at([frame as f32, 0.0, 0.0]))])
+        .build();
+
+    write_xfbin_folder(xfbin_dir, name, &anmstrm, &frames);
+}
+
+#[test]
+fn top_level_options_before_the_subcommand_are_rejected() {
+    let output = run_cli(&["-o", "out", "convert", "in"]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("must go after"));
+}
+
+#[test]
+fn missing_inputs_exit_with_3() {
+    let dir = temp_dir("cli_missing_input");
+
+    let output = run_cli(&["convert".as_ref(), dir.join("missing").as_os_str(), "-q".as_ref()]);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn help_documents_options_and_exit_codes() {
+    let output = run_cli(&["convert", "--help"]);
+    let help = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(output.status.code(), Some(0));
+    for option in ["--output", "--no-dmg", "--xfbin", "--quiet", "--non-interactive"] {
+        assert!(help.contains(option), "{} missing from help", option);
+    }
+
+    let output = run_cli(&["--help"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Exit codes"));
+}
+
+#[test]
+fn extracted_folders_are_converted() {
+    let dir = temp_dir("cli_convert");
+    let input = dir.join("in").join("x");
+    let out = dir.join("out");
+    write_stream(&input, "x");
+
+    let output = run_cli(&["convert".as_ref(), input.as_os_str(), "-o".as_ref(), out.as_os_str(), "-q".as_ref()]);
+    let anm_dir = out.join("x_converted").join("[000] x (nuccChunkAnm)");
+    let anm = read_anm_file(anm_dir.join("x.anm"));
+    let page_written = anm_dir.join("_page.json").is_file();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(anm.unwrap().entries.len(), 1);
+    assert!(page_written);
+}
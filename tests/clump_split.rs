@@ -0,0 +1,347 @@
+use std::io::Cursor;
+
+use binrw::BinWriterExt;
+
+use anmstrm2anm::{
+    build_page::{anm_page, clump_page, dmg_page},
+    read_anm, removed_indices, split_clumps, split_every_clump,
+    structure::{
+        anm::{AnmClump, AnmCoord, AnmEntry, AnmEntryFormat, CoordParent},
+        page::{Chunk, Files, Page},
+    },
+    ClumpSelector, NuccAnm, SceneAnm,
+};
+
+fn clump(clump_index: u32, bones: &[u32], models: &[u32]) -> AnmClump {
+    AnmClump {
+        clump_index,
+        bone_material_count: bones.len() as u16,
+        model_count: models.len() as u16,
+        bone_material_indices: bones.to_vec(),
+        model_indices: models.to_vec(),
+    }
+}
+
+fn coord(clump_index: i16, coord_index: u16) -> AnmCoord {
+    AnmCoord { clump_index, coord_index }
+}
+
+fn entry(clump_index: i16, coord_index: u16) -> AnmEntry {
+    AnmEntry {
+        coord: coord(clump_index, coord_index),
+        entry_format: AnmEntryFormat::BONE as u16,
+        curve_count: 0,
+        curve_headers: Vec::new(),
+        curves: Vec::new(),
+    }
+}
+
+/// Three clumps of different sizes followed by two other entry indices (a camera and a light).
+fn anm() -> NuccAnm {
+    let clumps = vec![
+        clump(0, &[1, 2, 3], &[4]),
+        clump(5, &[6, 7], &[]),
+        clump(8, &[9], &[10]),
+    ];
+
+    let coord_parents = vec![
+        CoordParent { parent: coord(0, 0), child: coord(0, 1) },
+        CoordParent { parent: coord(0, 2), child: coord(1, 0) },
+        CoordParent { parent: coord(1, 0), child: coord(1, 1) },
+        CoordParent { parent: coord(2, 0), child: coord(2, 1) },
+    ];
+
+    let entries = vec![entry(0, 0), entry(1, 0), entry(1, 1), entry(2, 0), entry(-1, 0), entry(-1, 1)];
+
+    NuccAnm {
+        anm_length: 1000,
+        frame_size: 100,
+        entry_count: entries.len() as u16,
+        looped: 0,
+        clump_count: clumps.len() as u16,
+        other_entry_count: 2,
+        other_index_count: 0,
+        coord_count: coord_parents.len() as u16,
+        clumps,
+        other_entries_indices: vec![11, 12],
+        coord_parents,
+        entries,
+    }
+}
+
+fn coords(anm: &NuccAnm) -> Vec<(i16, u16)> {
+    anm.entries.iter().map(|entry| (entry.coord.clump_index, entry.coord.coord_index)).collect()
+}
+
+#[test]
+fn splitting_a_clump_moves_its_indices_out() {
+    let mut anm = anm();
+    let split = split_clumps(&mut anm, &[1]);
+
+    assert_eq!(split.clumps, [clump(0, &[1, 2], &[])]);
+    assert_eq!(split.clump_count, 1);
+    assert!(split.other_entries_indices.is_empty());
+    assert_eq!((split.other_entry_count, split.other_index_count), (0, 0));
+    assert_eq!(coords(&split), [(0, 0), (0, 1)]);
+    assert_eq!(split.coord_parents.len(), 1);
+    assert_eq!((split.coord_count, split.entry_count), (1, 2));
+
+    // Three indices were removed, so everything after the split clump moves down by three
+    assert_eq!(anm.clumps, [clump(0, &[1, 2, 3], &[4]), clump(5, &[6], &[7])]);
+    assert_eq!(anm.other_entries_indices, [8, 9]);
+    assert_eq!(coords(&anm), [(0, 0), (1, 0), (-1, 0), (-1, 1)]);
+
+    // The coord parent linking the removed clump to a kept clump goes with it
+    let parents: Vec<(i16, i16)> = anm.coord_parents.iter().map(|parent| (parent.parent.clump_index, parent.child.clump_index)).collect();
+    assert_eq!(parents, [(0, 0), (1, 1)]);
+    assert_eq!((anm.clump_count, anm.coord_count, anm.entry_count), (2, 2, 4));
+}
+
+#[test]
+fn splitting_several_clumps_counts_every_removed_index() {
+    let mut anm = anm();
+    let split = split_clumps(&mut anm, &[0, 2]);
+
+    assert_eq!(split.clumps, [clump(0, &[1, 2, 3], &[4]), clump(5, &[6], &[7])]);
+    assert_eq!(coords(&split), [(0, 0), (1, 0)]);
+
+    assert_eq!(anm.clumps, [clump(0, &[1, 2], &[])]);
+    assert_eq!(anm.other_entries_indices, [3, 4]);
+    assert_eq!(coords(&anm), [(0, 0), (0, 1), (-1, 0), (-1, 1)]);
+}
+
+#[test]
+fn clumps_are_selected_by_position_name_or_rule() {
+    let anm = anm();
+
+    let mut chunk_maps: Vec<Chunk> = (0..13).map(|index| Chunk {
+        name: format!("chunk{}", index),
+        types: "nuccChunkCoord".to_string(),
+        path: String::new(),
+    }).collect();
+    chunk_maps[5].name = "1cmnbod1".to_string();
+    let page = Page { chunk_maps, ..Page::default() };
+
+    assert_eq!(ClumpSelector::Positions(vec![2, 0]).select(&anm.clumps), [0, 2]);
+    assert_eq!(ClumpSelector::Name { pattern: "1cmn".to_string(), page }.select(&anm.clumps), [1]);
+    assert_eq!(ClumpSelector::BoneMaterialCount(1).select(&anm.clumps), [2]);
+    assert_eq!(ClumpSelector::Rule(|clump| clump.model_indices.is_empty()).select(&anm.clumps), [1]);
+    assert!(ClumpSelector::dmg().select(&anm.clumps).is_empty());
+}
+
+#[test]
+fn split_anms_read_back() {
+    let mut anm = anm();
+    let split = split_clumps(&mut anm, &[1]);
+
+    for anm in [anm, split] {
+        let mut data = Cursor::new(Vec::new());
+        data.write_be(&anm).unwrap();
+        let read = read_anm(&mut Cursor::new(data.into_inner())).unwrap();
+
+        assert_eq!(read.clumps, anm.clumps);
+        assert_eq!(read.other_entries_indices, anm.other_entries_indices);
+        assert_eq!(coords(&read), coords(&anm));
+    }
+}
+
+fn sorted_coords(anm: &NuccAnm) -> Vec<(i16, u16)> {
+    let mut coords = coords(anm);
+    coords.sort();
+    coords
+}
+
+#[test]
+fn removed_clumps_take_their_coords_along() {
+    let mut anm = anm();
+    anm.remove_clumps(&[0]);
+
+    assert_eq!(anm.clumps, [clump(0, &[1, 2], &[]), clump(3, &[4], &[5])]);
+    assert_eq!(anm.other_entries_indices, [6, 7]);
+    assert_eq!(coords(&anm), [(0, 0), (0, 1), (1, 0), (-1, 0), (-1, 1)]);
+    anm.validate().unwrap();
+}
+
+#[test]
+fn other_entries_can_be_removed() {
+    let mut anm = anm();
+    anm.remove_other_entries();
+
+    assert!(anm.other_entries_indices.is_empty());
+    assert_eq!(anm.clumps[2], clump(8, &[9], &[10]));
+    assert!(anm.entries.iter().all(|entry| entry.coord.clump_index >= 0));
+    anm.validate().unwrap();
+}
+
+#[test]
+fn inserting_split_clumps_restores_the_anm() {
+    let original = anm();
+
+    // Only the coord parent linking clump 0 to clump 1 is lost when the two end up in different anms
+    for (positions, lost_coord_parents) in [(vec![0], 1), (vec![1], 1), (vec![2], 0), (vec![0, 1], 0)] {
+        let mut anm = original.clone();
+        let split = split_clumps(&mut anm, &positions);
+        anm.insert_clumps(positions[0], split);
+
+        assert_eq!(anm.clumps, original.clumps, "{:?}", positions);
+        assert_eq!(anm.other_entries_indices, original.other_entries_indices);
+        assert_eq!(sorted_coords(&anm), sorted_coords(&original));
+        assert_eq!(anm.coord_parents.len(), original.coord_parents.len() - lost_coord_parents);
+        anm.validate().unwrap();
+    }
+}
+
+#[test]
+fn reordering_moves_coords_with_their_clumps() {
+    let mut anm = anm();
+    anm.reorder_clumps(&[2, 0, 1]).unwrap();
+
+    assert_eq!(anm.clumps[0], clump(8, &[9], &[10]));
+    assert_eq!(sorted_coords(&anm), [(-1, 0), (-1, 1), (0, 0), (1, 0), (2, 0), (2, 1)]);
+
+    let parents: Vec<(i16, i16)> = anm.coord_parents.iter().map(|parent| (parent.parent.clump_index, parent.child.clump_index)).collect();
+    assert_eq!(parents, [(1, 1), (1, 2), (2, 2), (0, 0)]);
+    anm.validate().unwrap();
+
+    assert!(anm.reorder_clumps(&[0, 0, 1]).is_err());
+    assert!(anm.reorder_clumps(&[0, 1]).is_err());
+}
+
+#[test]
+fn validation_catches_broken_references() {
+    anm().validate().unwrap();
+
+    let mut anm_with_stale_count = anm();
+    anm_with_stale_count.entry_count = 1;
+    assert!(anm_with_stale_count.validate().is_err());
+
+    let mut anm_with_shared_index = anm();
+    anm_with_shared_index.clumps[2].model_indices[0] = 4;
+    assert!(anm_with_shared_index.validate().is_err());
+
+    let mut anm_with_missing_clump = anm();
+    anm_with_missing_clump.entries[0].coord.clump_index = 3;
+    assert!(anm_with_missing_clump.validate().is_err());
+
+    let mut anm_with_missing_coord = anm();
+    anm_with_missing_coord.entries[1].coord.coord_index = 2;
+    assert!(anm_with_missing_coord.validate().is_err());
+}
+
+fn chunk(name: &str, types: &str) -> Chunk {
+    Chunk {
+        name: name.to_string(),
+        types: format!("nuccChunk{}", types),
+        path: "c/scene01.max".to_string(),
+    }
+}
+
+/// A page with a chunk map for every index of [`anm`], followed by the anmstrm chunks.
+fn page() -> Page {
+    let mut chunk_maps: Vec<Chunk> = ["1pl", "2pl", "3pl"]
+        .iter()
+        .zip([5, 3, 3])
+        .flat_map(|(name, count)| {
+            (0..count).map(move |index| match index {
+                0 => chunk(&format!("{}bod1", name), "Clump"),
+                _ => chunk(&format!("{} bone{}", name, index), "Coord"),
+            })
+        })
+        .collect();
+
+    chunk_maps.push(chunk("cam01", "Camera"));
+    chunk_maps.push(chunk("light01", "LightDirc"));
+    chunk_maps.push(chunk("scene01", "AnmStrm"));
+    chunk_maps.push(chunk("scene01", "AnmStrmFrame"));
+
+    let files = vec![
+        Files { file_name: "scene01.anmstrm".to_string(), chunk: chunk("scene01", "AnmStrm") },
+        Files { file_name: "scene01_0.anmstrmframe".to_string(), chunk: chunk("scene01", "AnmStrmFrame") },
+        Files { file_name: "cam01.camera".to_string(), chunk: chunk("cam01", "Camera") },
+    ];
+
+    Page { chunk_maps, files, ..Page::default() }
+}
+
+#[test]
+fn every_clump_gets_its_own_anm() {
+    let anms = split_every_clump(&anm(), SceneAnm::Separate);
+
+    let positions: Vec<Option<usize>> = anms.iter().map(|clump_anm| clump_anm.position).collect();
+    assert_eq!(positions, [Some(0), Some(1), Some(2), None]);
+
+    assert_eq!(anms[1].anm.clumps, [clump(0, &[1, 2], &[])]);
+    assert_eq!(coords(&anms[1].anm), [(0, 0), (0, 1)]);
+    assert_eq!(anms[2].anm.clumps, [clump(0, &[1], &[2])]);
+    assert_eq!(anms[2].removed_indices, [0, 1, 2, 3, 4, 5, 6, 7, 11, 12]);
+
+    let scene = &anms[3].anm;
+    assert!(scene.clumps.is_empty());
+    assert_eq!(scene.other_entries_indices, [0, 1]);
+    assert_eq!(coords(scene), [(-1, 0), (-1, 1)]);
+
+    for clump_anm in &anms {
+        clump_anm.anm.validate().unwrap();
+    }
+}
+
+#[test]
+fn scene_entries_can_go_to_a_clump() {
+    let anms = split_every_clump(&anm(), SceneAnm::Clump(2));
+
+    assert_eq!(anms.len(), 3);
+    assert_eq!(anms[2].anm.clumps, [clump(0, &[1], &[2])]);
+    assert_eq!(anms[2].anm.other_entries_indices, [3, 4]);
+    assert_eq!(coords(&anms[2].anm), [(0, 0), (-1, 0), (-1, 1)]);
+    assert!(anms[0].anm.other_entries_indices.is_empty());
+}
+
+#[test]
+fn clump_pages_keep_their_own_chunks() {
+    let page = page();
+    let anms = split_every_clump(&anm(), SceneAnm::Separate);
+
+    let body_page = clump_page(page.clone(), &anms[1].removed_indices, "_2plbod1");
+    let names: Vec<&str> = body_page.chunk_maps.iter().map(|chunk| chunk.name.as_str()).collect();
+    assert_eq!(names, ["2plbod1", "2pl bone1", "2pl bone2", "scene01_2plbod1"]);
+    assert_eq!(body_page.chunk_maps[3].types, "nuccChunkAnm");
+    assert_eq!(body_page.chunk_maps[3].path, "c/scene01_2plbod1.max");
+
+    let file_names: Vec<&str> = body_page.files.iter().map(|file| file.file_name.as_str()).collect();
+    assert_eq!(file_names, ["scene01_2plbod1.anm"]);
+
+    let scene_page = clump_page(page, &anms[3].removed_indices, "_scene");
+    let names: Vec<&str> = scene_page.chunk_maps.iter().map(|chunk| chunk.name.as_str()).collect();
+    assert_eq!(names, ["cam01", "light01", "scene01_scene"]);
+
+    let file_names: Vec<&str> = scene_page.files.iter().map(|file| file.file_name.as_str()).collect();
+    assert_eq!(file_names, ["scene01_scene.anm", "cam01.camera"]);
+}
+
+#[test]
+fn dmg_pages_follow_the_selected_clumps() {
+    let mut anm = anm();
+    let page = page();
+
+    let dmg_positions = ClumpSelector::Name { pattern: "3pl".to_string(), page: page.clone() }.select(&anm.clumps);
+    assert_eq!(dmg_positions, [2]);
+
+    let anm_page = anm_page(page.clone(), Some(&removed_indices(&anm, &[0, 1], true)));
+    let dmg_page = dmg_page(page, &removed_indices(&anm, &dmg_positions, false));
+    let dmg_anm = split_clumps(&mut anm, &dmg_positions);
+    assert_eq!(dmg_anm.clumps, [clump(0, &[1], &[2])]);
+
+    let names: Vec<&str> = anm_page.chunk_maps.iter().map(|chunk| chunk.name.as_str()).collect();
+    assert_eq!(
+        names,
+        ["1plbod1", "1pl bone1", "1pl bone2", "1pl bone3", "1pl bone4", "2plbod1", "2pl bone1", "2pl bone2", "cam01", "light01", "scene01"]
+    );
+    let file_names: Vec<&str> = anm_page.files.iter().map(|file| file.file_name.as_str()).collect();
+    assert_eq!(file_names, ["scene01.anm", "cam01.camera"]);
+
+    let names: Vec<&str> = dmg_page.chunk_maps.iter().map(|chunk| chunk.name.as_str()).collect();
+    assert_eq!(names, ["3plbod1", "3pl bone1", "3pl bone2", "scene01_dmg"]);
+    assert_eq!(dmg_page.chunk_maps[3].path, "c/scene01_dmg.max");
+    let file_names: Vec<&str> = dmg_page.files.iter().map(|file| file.file_name.as_str()).collect();
+    assert_eq!(file_names, ["scene01_dmg.anm"]);
+}
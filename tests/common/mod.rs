@@ -0,0 +1,272 @@
+//! Anmstrm fixtures shared by the integration tests.
+
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use binrw::BinWriterExt;
+
+use anmstrm2anm::{
+    convert_anmstrm, read_anm,
+    structure::{
+        anm::{AnmCoord, AnmEntryFormat, CoordParent},
+        anm_utils::*,
+        anmstrm::*,
+        page::{Chunk, Files},
+    },
+    ConvertOptions, NuccAnm, NuccAnmStrm, Page,
+};
+
+/// Builds an anmstrm and its frames. Frame infos, counts and the anm length (one frame past the last
+/// frame number) are filled in by [`StreamBuilder::build`].
+pub struct StreamBuilder {
+    anmstrm: NuccAnmStrm,
+    frames: Vec<NuccAnmStrmFrame>,
+}
+
+impl StreamBuilder {
+    /// An empty stream with a frame size of 100 ticks.
+    pub fn new() -> Self {
+        Self {
+            anmstrm: NuccAnmStrm {
+                anm_length: 0,
+                frame_size: 100,
+                frame_count: 0,
+                is_looped: 0,
+                clump_count: 0,
+                other_entry_count: 0,
+                other_index_count: 0,
+                coord_count: 0,
+                clumps: Vec::new(),
+                other_entries_indices: Vec::new(),
+                coord_parents: Vec::new(),
+                frames: Vec::new(),
+            },
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn frame_size(mut self, frame_size: u32) -> Self {
+        self.anmstrm.frame_size = frame_size;
+        self
+    }
+
+    pub fn clump(mut self, clump_index: u32, bone_material_indices: &[u32]) -> Self {
+        self.anmstrm.clumps.push(AnmStrmClump {
+            clump_index,
+            bone_material_count: bone_material_indices.len() as u16,
+            model_count: 0,
+            bone_material_indices: bone_material_indices.to_vec(),
+            model_indices: Vec::new(),
+            unknown: Vec::new(),
+        });
+        self
+    }
+
+    pub fn other_entries(mut self, indices: &[u32]) -> Self {
+        self.anmstrm.other_entries_indices = indices.to_vec();
+        self
+    }
+
+    pub fn coord_parent(mut self, parent: (i16, u16), child: (i16, u16)) -> Self {
+        self.anmstrm.coord_parents.push(CoordParent {
+            parent: coord(parent.0, parent.1),
+            child: coord(child.0, child.1),
+        });
+        self
+    }
+
+    pub fn frame(mut self, frame_number: u32, entries: Vec<AnmStrmEntry>) -> Self {
+        self.frames.push(NuccAnmStrmFrame {
+            frame_number,
+            entry_count: entries.len() as u16,
+            unknown: 0,
+            entries,
+        });
+        self
+    }
+
+    /// Adds frames numbered 0, 1, 2... holding the entries `entries` returns for each.
+    pub fn frames(mut self, frame_count: u32, entries: impl Fn(u32) -> Vec<AnmStrmEntry>) -> Self {
+        for frame in 0..frame_count {
+            self = self.frame(frame, entries(frame));
+        }
+        self
+    }
+
+    pub fn build(self) -> (NuccAnmStrm, Vec<NuccAnmStrmFrame>) {
+        let Self { mut anmstrm, frames } = self;
+
+        let last_frame = frames.iter().map(|frame| frame.frame_number).max().unwrap_or(0);
+        anmstrm.anm_length = (last_frame + 1) * anmstrm.frame_size;
+        anmstrm.clump_count = anmstrm.clumps.len() as u16;
+        anmstrm.other_entry_count = anmstrm.other_entries_indices.len() as u16;
+        anmstrm.coord_count = anmstrm.coord_parents.len() as u16;
+        anmstrm.update_frame_infos(&frames);
+
+        (anmstrm, frames)
+    }
+
+    /// Builds the stream and converts it into a single anm.
+    pub fn convert(self, options: &ConvertOptions) -> NuccAnm {
+        let (anmstrm, frames) = self.build();
+
+        convert_anmstrm(&anmstrm, frames, options).unwrap().remove(0)
+    }
+}
+
+/// Converts into a single anm without progress output.
+pub fn options() -> ConvertOptions {
+    ConvertOptions {
+        split_dmg: false,
+        show_progress: false,
+        ..ConvertOptions::default()
+    }
+}
+
+/// The stream written as the data of its anmstrm and anmstrm frame chunks.
+pub fn stream_bytes(anmstrm: &NuccAnmStrm, frames: &[NuccAnmStrmFrame]) -> (Vec<u8>, Vec<Vec<u8>>) {
+    let mut data = Cursor::new(Vec::new());
+    data.write_be(anmstrm).unwrap();
+
+    let frames = frames
+        .iter()
+        .map(|frame| {
+            let mut data = Cursor::new(Vec::new());
+            data.write_be(frame).unwrap();
+            data.into_inner()
+        })
+        .collect();
+
+    (data.into_inner(), frames)
+}
+
+/// A new empty folder in the system temp folder, named after the test.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("anmstrm2anm_{}_{}", std::process::id(), name));
+
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Writes the stream as the `[000] <name> (nuccChunkAnmStrmFrame)` chunk folder of an extracted XFBIN
+/// folder at `xfbin_dir`, with its chunk files and `_page.json`.
+pub fn write_xfbin_folder(xfbin_dir: &Path, name: &str, anmstrm: &NuccAnmStrm, frames: &[NuccAnmStrmFrame]) {
+    let chunk_dir = xfbin_dir.join(format!("[000] {} (nuccChunkAnmStrmFrame)", name));
+    fs::create_dir_all(&chunk_dir).unwrap();
+
+    let (anmstrm_data, frame_datas) = stream_bytes(anmstrm, frames);
+    fs::write(chunk_dir.join(format!("{}.anmstrm", name)), anmstrm_data).unwrap();
+    for (index, frame_data) in frame_datas.iter().enumerate() {
+        fs::write(chunk_dir.join(format!("{}_{}.anmstrmframe", name, index)), frame_data).unwrap();
+    }
+
+    let chunk = |types: &str| Chunk {
+        name: name.to_string(),
+        types: types.to_string(),
+        path: format!("c/{0}/{0}.max", name),
+    };
+    let file = |file_name: String, chunk: Chunk| Files { file_name, chunk };
+
+    let page = Page {
+        chunk_maps: vec![
+            Chunk::empty("nuccChunkNull"),
+            chunk("nuccChunkAnmStrm"),
+            chunk("nuccChunkAnmStrmFrame"),
+            Chunk::empty("nuccChunkPage"),
+        ],
+        chunk_references: Vec::new(),
+        files: [file(format!("{}.anmstrm", name), chunk("nuccChunkAnmStrm"))]
+            .into_iter()
+            .chain((0..frames.len()).map(|index| file(format!("{}_{}.anmstrmframe", name, index), chunk("nuccChunkAnmStrmFrame"))))
+            .collect(),
+    };
+    page.to_json_file(chunk_dir.join("_page.json")).unwrap();
+}
+
+/// Runs the anmstrm2anm binary with the arguments.
+pub fn run_cli<S: AsRef<std::ffi::OsStr>>(args: &[S]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_anmstrm2anm")).args(args).output().unwrap()
+}
+
+/// Writes the anm and reads it back.
+pub fn write_read(anm: &NuccAnm) -> NuccAnm {
+    let mut data = Cursor::new(Vec::new());
+    data.write_be(anm).unwrap();
+    read_anm(&mut Cursor::new(data.into_inner())).unwrap()
+}
+
+pub fn coord(clump_index: i16, coord_index: u16) -> AnmCoord {
+    AnmCoord { clump_index, coord_index }
+}
+
+/// An entry at the coord, its format and size taken from the entry data.
+pub fn entry(clump_index: i16, coord_index: u16, entry_data: Entry) -> AnmStrmEntry {
+    let entry_format = match &entry_data {
+        Entry::Bone(_) => AnmEntryFormat::BONE,
+        Entry::Camera(_) => AnmEntryFormat::CAMERA,
+        Entry::Material(_) => AnmEntryFormat::MATERIAL,
+        Entry::LightDirc(_) => AnmEntryFormat::LIGHTDIRC,
+        Entry::LightPoint(_) => AnmEntryFormat::LIGHTPOINT,
+        Entry::Ambient(_) => AnmEntryFormat::AMBIENT,
+        Entry::MorphModel(_) => AnmEntryFormat::MORPHMODEL,
+        Entry::Unknown(_) => panic!("unknown entries need a format, use unknown_entry"),
+    };
+
+    AnmStrmEntry {
+        coord: coord(clump_index, coord_index),
+        entry_format: entry_format as u16,
+        entry_size: entry_data.entry_size(),
+        entry_data,
+    }
+}
+
+pub fn unknown_entry(clump_index: i16, coord_index: u16, entry_format: u16, data: Vec<u8>) -> AnmStrmEntry {
+    AnmStrmEntry {
+        coord: coord(clump_index, coord_index),
+        entry_format,
+        entry_size: data.len() as u16,
+        entry_data: Entry::Unknown(data),
+    }
+}
+
+pub fn vector3([x, y, z]: [f32; 3]) -> Vector3 {
+    Vector3 { x, y, z }
+}
+
+pub fn vector4([x, y, z, w]: [f32; 4]) -> Vector4 {
+    Vector4 { x, y, z, w }
+}
+
+pub const IDENTITY: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+pub fn bone(location: [f32; 3], rotation: [f32; 4], scale: [f32; 3]) -> Entry {
+    Entry::Bone(AnmEntryBone {
+        frame_count: 1,
+        location: vector3(location),
+        rotation: vector4(rotation),
+        scale: vector3(scale),
+        toggled: 0.0,
+    })
+}
+
+/// A bone at `location` without rotation or scale.
+pub fn bone_at(location: [f32; 3]) -> Entry {
+    bone(location, IDENTITY, [1.0; 3])
+}
+
+pub fn camera(location: [f32; 3], rotation: [f32; 4], fov: f32) -> Entry {
+    Entry::Camera(AnmEntryCamera {
+        frame_count: 1,
+        location: vector3(location),
+        rotation: vector4(rotation),
+        fov,
+        scale: vector3([1.0; 3]),
+    })
+}
@@ -0,0 +1,135 @@
+use anmstrm2anm::{
+    collapse_constant_curves, drop_rest_entries, evaluate_curve,
+    structure::{anm::Curve, anm_utils::*, anmstrm::*},
+    ConvertOptions, NuccAnm,
+};
+
+mod common;
+use common::{bone, bone_at, entry, options, vector3, write_read, StreamBuilder};
+
+const QUARTER_TURN: [f32; 4] = [0.0, 0.0, std::f32::consts::FRAC_1_SQRT_2, std::f32::consts::FRAC_1_SQRT_2];
+
+/// A stream with a bone moving along x, a bone held away from the origin, a bone at rest, a bone held
+/// at a quarter turn and a constant ambient light.
+fn convert(options: ConvertOptions) -> NuccAnm {
+    let ambient = Entry::Ambient(AnmEntryAmbient {
+        frame_count: 1,
+        color: vector3([1.0, 0.5, 0.0]),
+        intensity: 0.5,
+    });
+
+    StreamBuilder::new()
+        .frames(5, |frame| {
+            vec![
+                entry(0, 0, bone_at([frame as f32, 0.0, 0.0])),
+                entry(0, 1, bone_at([0.0, 2.0, 0.0])),
+                entry(0, 2, bone_at([0.0; 3])),
+                entry(0, 3, bone([0.0; 3], QUARTER_TURN, [1.0; 3])),
+                entry(-1, 0, ambient.clone()),
+            ]
+        })
+        .convert(&options)
+}
+
+fn frame_counts(anm: &NuccAnm, clump_index: i16, coord_index: u16) -> Vec<u16> {
+    let entry = anm
+        .entries
+        .iter()
+        .find(|entry| entry.coord.clump_index == clump_index && entry.coord.coord_index == coord_index)
+        .unwrap();
+
+    entry.curve_headers.iter().map(|header| header.frame_count).collect()
+}
+
+#[test]
+fn constant_channels_become_single_values() {
+    let anm = convert(options());
+
+    // Location keyframes plus the null keyframe, then the constant rotation, scale and toggled curves
+    assert_eq!(frame_counts(&anm, 0, 0), [6, 1, 1, 1]);
+    assert_eq!(frame_counts(&anm, 0, 1), [2, 1, 1, 1]);
+    assert_eq!(frame_counts(&anm, -1, 0), [1, 1]);
+}
+
+#[test]
+fn constant_channels_can_be_kept() {
+    let anm = convert(ConvertOptions {
+        collapse_constant_curves: false,
+        ..options()
+    });
+
+    assert_eq!(frame_counts(&anm, 0, 1), [6, 5, 5, 5]);
+}
+
+#[test]
+fn collapsed_anm_reads_back() {
+    let read = write_read(&convert(options()));
+
+    let ambient = read.entries.iter().find(|entry| entry.coord.clump_index == -1).unwrap();
+    match &ambient.curves[0] {
+        Curve::RGB(values) => assert_eq!(values, &[RGB { r: 255, g: 127, b: 0 }]),
+        other => panic!("unexpected curve {:?}", other),
+    }
+    assert_eq!(ambient.curve_headers[0].curve_size, 4);
+}
+
+#[test]
+fn collapsed_keyframes_keep_their_null_keyframe() {
+    let read = write_read(&convert(options()));
+
+    let held = read.entries.iter().find(|entry| entry.coord.clump_index == 0 && entry.coord.coord_index == 1).unwrap();
+    match &held.curves[0] {
+        Curve::KeyframeVector3(keyframes) => {
+            let frames: Vec<i32> = keyframes.iter().map(|keyframe| keyframe.frame).collect();
+            assert_eq!(frames, [0, -1]);
+            assert!(keyframes.iter().all(|keyframe| keyframe.value == vector3([0.0, 2.0, 0.0])));
+        }
+        other => panic!("unexpected curve {:?}", other),
+    }
+    assert_eq!(held.curve_headers[0].curve_size, 32);
+
+    assert_eq!(evaluate_curve(&held.curves[0], 3.0, read.frame_size).unwrap(), [0.0, 2.0, 0.0]);
+}
+
+#[test]
+fn collapsing_twice_changes_nothing() {
+    let mut anm = convert(options());
+
+    assert_eq!(collapse_constant_curves(&mut anm).unwrap(), 0);
+}
+
+fn coords(anm: &NuccAnm) -> Vec<(i16, u16)> {
+    anm.entries.iter().map(|entry| (entry.coord.clump_index, entry.coord.coord_index)).collect()
+}
+
+#[test]
+fn rest_entries_are_dropped() {
+    let anm = convert(ConvertOptions {
+        drop_rest_entries: true,
+        ..options()
+    });
+
+    assert!(!coords(&anm).contains(&(0, 2)));
+}
+
+#[test]
+fn constant_bones_away_from_rest_are_kept() {
+    let anm = convert(ConvertOptions {
+        drop_rest_entries: true,
+        ..options()
+    });
+
+    assert_eq!(coords(&anm), [(-1, 0), (0, 0), (0, 1), (0, 3)]);
+}
+
+#[test]
+fn moving_entries_are_not_at_rest() {
+    let mut anm = convert(ConvertOptions {
+        collapse_constant_curves: false,
+        ..options()
+    });
+
+    assert_eq!(drop_rest_entries(&mut anm), 1);
+    assert_eq!(anm.entries.len(), 4);
+    assert_eq!(anm.entry_count, 4);
+}
@@ -0,0 +1,140 @@
+use std::io::Cursor;
+
+use binrw::{BinReaderExt, BinWriterExt};
+
+use anmstrm2anm::structure::anm::{AnmCurveFormat, AnmEntry, Curve};
+
+/// A bone entry holding the curve under test followed by a FLOAT1 curve with a single value,
+/// so reading past or short of the padding shows up in the second curve.
+fn entry_bytes(curve_format: AnmCurveFormat, frame_count: u16, data: &[u8], padding: usize) -> Vec<u8> {
+    let curve_size = (data.len() + padding) as u16;
+
+    let mut bytes = Vec::new();
+    bytes.extend(0i16.to_be_bytes()); // clump index
+    bytes.extend(1u16.to_be_bytes()); // coord index
+    bytes.extend(1u16.to_be_bytes()); // entry format
+    bytes.extend(2u16.to_be_bytes()); // curve count
+
+    for value in [0, curve_format as u16, frame_count, curve_size] {
+        bytes.extend(value.to_be_bytes());
+    }
+    for value in [1, AnmCurveFormat::FLOAT1 as u16, 1, 4] {
+        bytes.extend(value.to_be_bytes());
+    }
+
+    bytes.extend(data);
+    bytes.extend(vec![0; padding]);
+    bytes.extend(1.5f32.to_be_bytes());
+
+    bytes
+}
+
+fn read_entry(bytes: &[u8]) -> AnmEntry {
+    let mut reader = Cursor::new(bytes);
+    let entry: AnmEntry = reader.read_be().unwrap();
+
+    assert_eq!(reader.position() as usize, bytes.len(), "entry not fully read");
+    entry
+}
+
+fn write_entry(entry: &AnmEntry) -> Vec<u8> {
+    let mut writer = Cursor::new(Vec::new());
+    writer.write_be(entry).unwrap();
+    writer.into_inner()
+}
+
+fn assert_round_trip(bytes: &[u8]) -> AnmEntry {
+    let entry = read_entry(bytes);
+
+    match &entry.curves[1] {
+        Curve::Float(values) => assert_eq!(values, &[1.5]),
+        other => panic!("padding not skipped, read {:?}", other),
+    }
+
+    assert_eq!(write_entry(&entry), bytes);
+    entry
+}
+
+fn shorts(frame_count: u16) -> Vec<u8> {
+    (0..frame_count as i16).flat_map(|value| (value * 100 - 7).to_be_bytes()).collect()
+}
+
+#[test]
+fn short_odd_frame_count() {
+    let entry = assert_round_trip(&entry_bytes(AnmCurveFormat::SHORT1, 3, &shorts(3), 2));
+
+    match &entry.curves[0] {
+        Curve::Short(values) => assert_eq!(values, &[-7, 93, 193]),
+        other => panic!("unexpected curve {:?}", other),
+    }
+}
+
+#[test]
+fn short_even_frame_count() {
+    let entry = assert_round_trip(&entry_bytes(AnmCurveFormat::SHORT1, 2, &shorts(2), 0));
+
+    match &entry.curves[0] {
+        Curve::Short(values) => assert_eq!(values, &[-7, 93]),
+        other => panic!("unexpected curve {:?}", other),
+    }
+}
+
+#[test]
+fn short_single_frame() {
+    assert_round_trip(&entry_bytes(AnmCurveFormat::SHORT1, 1, &shorts(1), 2));
+}
+
+#[test]
+fn vector3_short_odd_frame_count() {
+    let entry = assert_round_trip(&entry_bytes(AnmCurveFormat::SHORT3, 3, &shorts(9), 2));
+
+    match &entry.curves[0] {
+        Curve::Vector3Short(values) => {
+            assert_eq!(values.len(), 3);
+            assert_eq!((values[2].x, values[2].y, values[2].z), (593, 693, 793));
+        }
+        other => panic!("unexpected curve {:?}", other),
+    }
+}
+
+#[test]
+fn vector3_short_even_frame_count() {
+    let entry = assert_round_trip(&entry_bytes(AnmCurveFormat::SHORT3, 4, &shorts(12), 0));
+
+    match &entry.curves[0] {
+        Curve::Vector3Short(values) => assert_eq!(values.len(), 4),
+        other => panic!("unexpected curve {:?}", other),
+    }
+}
+
+#[test]
+fn rgb_every_padding_length() {
+    for frame_count in 1..=8u16 {
+        let data: Vec<u8> = (0..frame_count as u8 * 3).collect();
+        let padding = frame_count as usize % 4;
+
+        let entry = assert_round_trip(&entry_bytes(AnmCurveFormat::BYTE3, frame_count, &data, padding));
+
+        match &entry.curves[0] {
+            Curve::RGB(values) => {
+                assert_eq!(values.len(), frame_count as usize);
+                let last = values.last().unwrap();
+                assert_eq!((last.r, last.g, last.b), (data[data.len() - 3], data[data.len() - 2], data[data.len() - 1]));
+            }
+            other => panic!("unexpected curve {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn curve_size_includes_padding() {
+    for (curve_format, frame_count, data, padding) in [
+        (AnmCurveFormat::SHORT1, 5, shorts(5), 2),
+        (AnmCurveFormat::SHORT3, 5, shorts(15), 2),
+        (AnmCurveFormat::BYTE3, 5, vec![7; 15], 1),
+    ] {
+        let entry = read_entry(&entry_bytes(curve_format, frame_count, &data, padding));
+
+        assert_eq!(entry.curves[0].curve_size().unwrap() as usize, data.len() + padding);
+    }
+}
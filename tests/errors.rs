@@ -0,0 +1,92 @@
+use std::{fs, io::ErrorKind};
+
+use anmstrm2anm::{
+    collect_files, convert_anmstrm, parse_anmstrm_frame_files, read_anmstrm_file, sort_anmstrm_frame_filepaths, Error, Page,
+};
+
+mod common;
+use common::{bone_at, entry, options, stream_bytes, temp_dir, StreamBuilder};
+
+#[test]
+fn malformed_frame_files_report_their_path_and_offset() {
+    let dir = temp_dir("malformed_frame");
+    let (anmstrm, frames) = StreamBuilder::new().frames(2, |frame| vec![entry(0, 0, bone_at([frame as f32, 0.0, 0.0]))]).build();
+    let (_, frame_datas) = stream_bytes(&anmstrm, &frames);
+
+    let paths = [dir.join("x_0.anmstrmframe"), dir.join("x_1.anmstrmframe")];
+    fs::write(&paths[0], &frame_datas[0]).unwrap();
+    fs::write(&paths[1], &frame_datas[1][..20]).unwrap();
+
+    let result = parse_anmstrm_frame_files(&paths, &options());
+    fs::remove_dir_all(&dir).unwrap();
+
+    match result {
+        Err(Error::Parse { path: Some(path), offset, .. }) => {
+            assert_eq!(path, paths[1]);
+            assert!(offset > 0 && offset <= 20, "offset {:#x}", offset);
+        }
+        other => panic!("unexpected result {:?}", other.map(|frames| frames.len())),
+    }
+}
+
+#[test]
+fn frame_file_names_without_a_number_are_rejected() {
+    let mut paths = vec!["x_1.anmstrmframe", "x_first.anmstrmframe", "x_0.anmstrmframe"];
+
+    let result = sort_anmstrm_frame_filepaths(&mut paths);
+    assert!(matches!(result, Err(Error::InvalidFrameFileName(path)) if path.ends_with("x_first.anmstrmframe")));
+}
+
+#[test]
+fn frame_files_sort_by_their_number() {
+    let mut paths = vec!["x_10.anmstrmframe", "x_2.anmstrmframe", "x_0.anmstrmframe"];
+
+    sort_anmstrm_frame_filepaths(&mut paths).unwrap();
+    assert_eq!(paths, ["x_0.anmstrmframe", "x_2.anmstrmframe", "x_10.anmstrmframe"]);
+}
+
+#[test]
+fn malformed_page_json_is_an_error() {
+    let dir = temp_dir("malformed_page");
+    let path = dir.join("_page.json");
+    fs::write(&path, r#"{ "Chunk Maps": [ "#).unwrap();
+
+    let result = Page::from_json_file(&path);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(matches!(result, Err(Error::PageJson { path: error_path, .. }) if error_path == path));
+}
+
+#[test]
+fn missing_files_and_folders_keep_their_path() {
+    let dir = temp_dir("missing_files");
+    let missing = dir.join("missing");
+
+    match read_anmstrm_file(missing.join("x.anmstrm")) {
+        Err(Error::File { path, source }) => {
+            assert_eq!(path, missing.join("x.anmstrm"));
+            assert_eq!(source.kind(), ErrorKind::NotFound);
+        }
+        other => panic!("unexpected result {:?}", other.map(|anmstrm| anmstrm.frame_count)),
+    }
+
+    let result = collect_files!(&missing, "anmstrmframe");
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(matches!(result, Err(Error::File { path, .. }) if path == missing));
+}
+
+#[test]
+fn frames_with_a_different_entry_count_are_an_error() {
+    let (anmstrm, frames) = StreamBuilder::new()
+        .frame(0, vec![entry(0, 0, bone_at([0.0; 3])), entry(0, 1, bone_at([0.0; 3]))])
+        .frame(1, vec![entry(0, 0, bone_at([1.0, 0.0, 0.0]))])
+        .build();
+
+    let result = convert_anmstrm(&anmstrm, frames, &options());
+    assert!(
+        matches!(result, Err(Error::CountMismatch { expected: 2, found: 1, .. })),
+        "{:?}",
+        result.map(|anms| anms.len())
+    );
+}
@@ -0,0 +1,192 @@
+use anmstrm2anm::{
+    evaluate_anmstrm, evaluate_coord, evaluate_curve, evaluate_entry,
+    structure::{
+        anm::{AnmCoord, AnmEntry, AnmEntryFormat, Curve, CurveHeader, NuccAnm},
+        anm_utils::*,
+        anmstrm::*,
+    },
+    EntryValue, NuccAnmStrm,
+};
+
+mod common;
+use common::{bone_at, entry, vector3, StreamBuilder};
+
+fn close(actual: &[f32], expected: &[f32]) -> bool {
+    actual.len() == expected.len() && actual.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-4)
+}
+
+fn anm_entry(entry_format: AnmEntryFormat, curves: Vec<(u16, Curve)>) -> AnmEntry {
+    let curve_headers = curves
+        .iter()
+        .map(|(curve_index, curve)| CurveHeader {
+            curve_index: *curve_index,
+            curve_format: curve.get_curve_format(),
+            frame_count: curve.get_frame_count(),
+            curve_size: curve.curve_size().unwrap(),
+        })
+        .collect();
+
+    AnmEntry {
+        coord: AnmCoord { clump_index: 0, coord_index: 2 },
+        entry_format: entry_format as u16,
+        curve_count: curves.len() as u16,
+        curve_headers,
+        curves: curves.into_iter().map(|(_, curve)| curve).collect(),
+    }
+}
+
+fn bone() -> AnmEntry {
+    anm_entry(
+        AnmEntryFormat::BONE,
+        vec![
+            (
+                0,
+                Curve::KeyframeVector3(vec![
+                    KeyframeVector3 { frame: 0, value: Vector3 { x: 0.0, y: 0.0, z: 0.0 } },
+                    KeyframeVector3 { frame: 200, value: Vector3 { x: 2.0, y: 4.0, z: -2.0 } },
+                    KeyframeVector3 { frame: -1, value: Vector3 { x: 9.0, y: 9.0, z: 9.0 } },
+                ]),
+            ),
+            (
+                1,
+                Curve::QuaternionShort(vec![
+                    QuaternionShort { x: 0, y: 0, z: 0, w: 32767 },
+                    QuaternionShort { x: 0, y: 0, z: 32767, w: 0 },
+                ]),
+            ),
+            (
+                2,
+                Curve::Vector3Short(vec![Vector3Short { x: 4096, y: 8192, z: 2048 }]),
+            ),
+        ],
+    )
+}
+
+#[test]
+fn keyframes_interpolate_and_ignore_the_terminator() {
+    let entry = bone();
+    let curve = &entry.curves[0];
+
+    assert!(close(&evaluate_curve(curve, 0.5, 100).unwrap(), &[0.5, 1.0, -0.5]));
+    assert!(close(&evaluate_curve(curve, 2.0, 100).unwrap(), &[2.0, 4.0, -2.0]));
+
+    // Past the last keyframe the value holds instead of running into the -1 key
+    assert!(close(&evaluate_curve(curve, 37.0, 100).unwrap(), &[2.0, 4.0, -2.0]));
+}
+
+#[test]
+fn short_curves_are_dequantized() {
+    let entry = bone();
+
+    assert!(close(&evaluate_curve(&entry.curves[1], 0.0, 100).unwrap(), &[0.0, 0.0, 0.0, 1.0]));
+    assert!(close(&evaluate_curve(&entry.curves[2], 5.0, 100).unwrap(), &[1.0, 2.0, 0.5]));
+}
+
+#[test]
+fn per_frame_rotations_slerp_between_frames() {
+    let entry = bone();
+    let half = std::f32::consts::FRAC_1_SQRT_2;
+
+    assert!(close(&evaluate_curve(&entry.curves[1], 0.5, 100).unwrap(), &[0.0, 0.0, half, half]));
+}
+
+#[test]
+fn bone_transform_at_time() {
+    let anm = NuccAnm {
+        anm_length: 300,
+        frame_size: 100,
+        entry_count: 1,
+        looped: 0,
+        clump_count: 0,
+        other_entry_count: 0,
+        other_index_count: 0,
+        coord_count: 0,
+        clumps: Vec::new(),
+        other_entries_indices: Vec::new(),
+        coord_parents: Vec::new(),
+        entries: vec![bone()],
+    };
+
+    match evaluate_coord(&anm, &AnmCoord { clump_index: 0, coord_index: 2 }, 1.0).unwrap() {
+        EntryValue::Bone { location, rotation, scale, toggled } => {
+            assert!(close(&[location.x, location.y, location.z], &[1.0, 2.0, -1.0]));
+            assert!(close(&[rotation.x, rotation.y, rotation.z, rotation.w], &[0.0, 0.0, 1.0, 0.0]));
+            assert!(close(&[scale.x, scale.y, scale.z], &[1.0, 2.0, 0.5]));
+            assert_eq!(toggled, 0.0);
+        }
+        other => panic!("unexpected value {:?}", other),
+    }
+
+    assert!(evaluate_coord(&anm, &AnmCoord { clump_index: 0, coord_index: 7 }, 1.0).is_none());
+}
+
+#[test]
+fn missing_channels_take_rest_values() {
+    let camera = anm_entry(AnmEntryFormat::CAMERA, Vec::new());
+
+    match evaluate_entry(&camera, 0.0, 100).unwrap() {
+        EntryValue::Camera { rotation, fov, .. } => {
+            assert_eq!((rotation.w, fov), (1.0, 45.0));
+        }
+        other => panic!("unexpected value {:?}", other),
+    }
+}
+
+/// A stream with a bone moving along x, keyed on frames 0, 2 and 6, and a point light only in the first frame.
+fn bone_stream() -> (NuccAnmStrm, Vec<NuccAnmStrmFrame>) {
+    let light = Entry::LightPoint(AnmEntryLightPoint {
+        frame_count: 1,
+        color: vector3([1.0, 0.5, 0.0]),
+        position: vector3([0.0, 3.0, 0.0]),
+        intensity: 2.0,
+        radius: 10.0,
+        falloff: 1.0,
+    });
+
+    StreamBuilder::new()
+        .frame(0, vec![entry(0, 1, bone_at([0.0; 3])), entry(-1, 0, light)])
+        .frame(2, vec![entry(0, 1, bone_at([2.0, 0.0, 0.0]))])
+        .frame(6, vec![entry(0, 1, bone_at([10.0, 0.0, 0.0]))])
+        .build()
+}
+
+fn bone_x(values: &[(AnmCoord, EntryValue)]) -> f32 {
+    match values.iter().find(|(coord, _)| coord.coord_index == 1 && coord.clump_index == 0) {
+        Some((_, EntryValue::Bone { location, .. })) => location.x,
+        other => panic!("unexpected value {:?}", other),
+    }
+}
+
+#[test]
+fn stream_frames_are_located_by_frame_number() {
+    let (anmstrm, frames) = bone_stream();
+
+    assert_eq!(bone_x(&evaluate_anmstrm(&anmstrm, &frames, 2.0)), 2.0);
+    assert_eq!(bone_x(&evaluate_anmstrm(&anmstrm, &frames, 6.0)), 10.0);
+}
+
+#[test]
+fn stream_frames_interpolate_between_frame_numbers() {
+    let (anmstrm, frames) = bone_stream();
+
+    assert!(close(&[bone_x(&evaluate_anmstrm(&anmstrm, &frames, 1.5))], &[1.5]));
+    assert!(close(&[bone_x(&evaluate_anmstrm(&anmstrm, &frames, 3.0))], &[4.0]));
+
+    // Before the first and past the last frame the pose holds
+    assert_eq!(bone_x(&evaluate_anmstrm(&anmstrm, &frames, -1.0)), 0.0);
+    assert_eq!(bone_x(&evaluate_anmstrm(&anmstrm, &frames, 9.5)), 10.0);
+}
+
+#[test]
+fn stream_coords_missing_from_the_next_frame_hold() {
+    let (anmstrm, frames) = bone_stream();
+    let values = evaluate_anmstrm(&anmstrm, &frames, 1.0);
+
+    assert_eq!(values.len(), 2);
+    match values.iter().find(|(coord, _)| coord.clump_index == -1) {
+        Some((_, EntryValue::LightPoint { intensity, position, .. })) => {
+            assert_eq!((*intensity, position.y), (2.0, 3.0));
+        }
+        other => panic!("unexpected value {:?}", other),
+    }
+}
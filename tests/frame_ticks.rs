@@ -0,0 +1,140 @@
+use anmstrm2anm::{
+    convert_anmstrm, evaluate_curve,
+    structure::{anm::Curve, anmstrm::*},
+    ConvertOptions, Error, NuccAnm,
+};
+
+mod common;
+use common::{camera, entry, options, StreamBuilder, IDENTITY};
+
+/// A stream with a moving camera and a changing material on the given frame numbers.
+fn stream(frame_size: u32, frame_numbers: &[u16]) -> StreamBuilder {
+    let mut stream = StreamBuilder::new().frame_size(frame_size);
+
+    for (frame, frame_number) in frame_numbers.iter().enumerate() {
+        let material = Entry::Material(AnmEntryMaterial {
+            frame_count: 1,
+            ambient_color: [frame as f32; 16],
+        });
+
+        stream = stream.frame(
+            *frame_number as u32,
+            vec![entry(-1, 0, camera([frame as f32, 0.0, 0.0], IDENTITY, 45.0 + frame as f32)), entry(0, 1, material)],
+        );
+    }
+
+    stream
+}
+
+fn convert(frame_size: u32, frame_numbers: &[u16], frame_ticks: Option<u32>) -> NuccAnm {
+    stream(frame_size, frame_numbers).convert(&ConvertOptions { frame_ticks, ..options() })
+}
+
+fn assert_invalid(anmstrm: &NuccAnmStrm, frames: Vec<NuccAnmStrmFrame>) {
+    let result = convert_anmstrm(anmstrm, frames, &options());
+    assert!(matches!(result, Err(Error::InvalidAnm(_))), "{:?}", result.map(|anms| anms.len()));
+}
+
+fn keyframe_times(anm: &NuccAnm, clump_index: i16, curve: usize) -> Vec<i32> {
+    let entry = anm.entries.iter().find(|entry| entry.coord.clump_index == clump_index).unwrap();
+
+    match &entry.curves[curve] {
+        Curve::KeyframeVector3(keyframes) => keyframes.iter().map(|keyframe| keyframe.frame).collect(),
+        Curve::KeyframeFloat(keyframes) => keyframes.iter().map(|keyframe| keyframe.frame).collect(),
+        other => panic!("unexpected curve {:?}", other),
+    }
+}
+
+fn material_values(anm: &NuccAnm, curve: usize) -> Vec<f32> {
+    let entry = anm.entries.iter().find(|entry| entry.coord.clump_index == 0).unwrap();
+
+    match &entry.curves[curve] {
+        Curve::Float(values) => values.clone(),
+        other => panic!("unexpected curve {:?}", other),
+    }
+}
+
+#[test]
+fn keyframes_follow_the_frame_size() {
+    let anm = convert(160, &[0, 1, 2], None);
+
+    assert_eq!(keyframe_times(&anm, -1, 0), [0, 160, 320, -1]);
+    assert_eq!(keyframe_times(&anm, -1, 2), [0, 160, 320, -1]);
+    assert_eq!((anm.frame_size, anm.anm_length), (160, 480));
+}
+
+#[test]
+fn keyframes_follow_skipped_frame_numbers() {
+    let anm = convert(100, &[0, 2, 5], None);
+
+    assert_eq!(keyframe_times(&anm, -1, 0), [0, 200, 500, -1]);
+    assert_eq!((anm.frame_size, anm.anm_length), (100, 600));
+}
+
+#[test]
+fn per_frame_curves_hold_their_value_over_skipped_frames() {
+    let anm = convert(100, &[0, 2, 5], None);
+
+    assert_eq!(material_values(&anm, 2), [0.0, 0.0, 1.0, 1.0, 1.0, 2.0]);
+
+    let header = &anm.entries.iter().find(|entry| entry.coord.clump_index == 0).unwrap().curve_headers[2];
+    assert_eq!((header.frame_count, header.curve_size), (6, 24));
+
+    // Each frame number evaluates to its own frame's values, keyframed or not
+    let material = anm.entries.iter().find(|entry| entry.coord.clump_index == 0).unwrap();
+    for (frame, frame_number) in [0, 2, 5].into_iter().enumerate() {
+        assert_eq!(evaluate_curve(&material.curves[0], frame_number as f32, anm.frame_size).unwrap(), [frame as f32]);
+        assert_eq!(evaluate_curve(&material.curves[2], frame_number as f32, anm.frame_size).unwrap(), [frame as f32]);
+    }
+}
+
+#[test]
+fn frame_ticks_override_the_frame_size() {
+    let anm = convert(100, &[0, 1, 2], Some(40));
+
+    assert_eq!(keyframe_times(&anm, -1, 0), [0, 40, 80, -1]);
+    assert_eq!((anm.frame_size, anm.anm_length), (40, 120));
+    assert_eq!(material_values(&anm, 2), [0.0, 1.0, 2.0]);
+}
+
+#[test]
+fn frame_ticks_override_a_missing_frame_size() {
+    let anm = convert(0, &[0, 1, 3], Some(50));
+
+    assert_eq!(keyframe_times(&anm, -1, 0), [0, 50, 150, -1]);
+    assert_eq!((anm.frame_size, anm.anm_length), (50, 200));
+    assert_eq!(material_values(&anm, 2).len(), 4);
+}
+
+#[test]
+fn material_keys_are_held_for_half_a_frame() {
+    let anm = convert(160, &[0, 1, 2], None);
+
+    assert_eq!(keyframe_times(&anm, 0, 0), [0, 80, 160, 240, 320, 400, -1]);
+}
+
+#[test]
+fn out_of_order_frame_numbers_are_rejected() {
+    let (anmstrm, frames) = stream(100, &[0, 2, 1]).build();
+    assert_invalid(&anmstrm, frames);
+}
+
+#[test]
+fn duplicate_frame_numbers_are_rejected() {
+    let (anmstrm, frames) = stream(100, &[0, 1, 1, 2]).build();
+    assert_invalid(&anmstrm, frames);
+}
+
+#[test]
+fn frame_numbers_past_the_anm_length_are_rejected() {
+    let (mut anmstrm, frames) = stream(100, &[0, 1, 50_000]).build();
+    anmstrm.anm_length = 300;
+    assert_invalid(&anmstrm, frames);
+}
+
+#[test]
+fn frame_numbers_disagreeing_with_the_frame_infos_are_rejected() {
+    let (mut anmstrm, frames) = stream(100, &[0, 1, 2]).build();
+    anmstrm.frames[1].frame_number = 5;
+    assert_invalid(&anmstrm, frames);
+}
@@ -0,0 +1,186 @@
+use anmstrm2anm::{
+    evaluate_curve, reduce_keyframes,
+    structure::{
+        anm::{AnmCoord, AnmCurveFormat, AnmEntry, AnmEntryFormat, Curve, CurveHeader, NuccAnm},
+        anm_utils::*,
+    },
+    KeyframeTolerance, ReductionReport,
+};
+
+fn anm(curves: Vec<Curve>) -> NuccAnm {
+    let mut curves = curves;
+    curves.iter_mut().for_each(Curve::append_null_keyframe);
+
+    let curve_headers = curves
+        .iter()
+        .enumerate()
+        .map(|(curve_index, curve)| CurveHeader {
+            curve_index: curve_index as u16,
+            curve_format: curve.get_curve_format(),
+            frame_count: curve.get_frame_count(),
+            curve_size: curve.curve_size().unwrap(),
+        })
+        .collect();
+
+    NuccAnm {
+        anm_length: 1000,
+        frame_size: 100,
+        entry_count: 1,
+        looped: 0,
+        clump_count: 0,
+        other_entry_count: 0,
+        other_index_count: 0,
+        coord_count: 0,
+        clumps: Vec::new(),
+        other_entries_indices: Vec::new(),
+        coord_parents: Vec::new(),
+        entries: vec![AnmEntry {
+            coord: AnmCoord { clump_index: 0, coord_index: 0 },
+            entry_format: AnmEntryFormat::BONE as u16,
+            curve_count: curves.len() as u16,
+            curve_headers,
+            curves,
+        }],
+    }
+}
+
+fn locations(x: impl Fn(f32) -> f32) -> Curve {
+    Curve::KeyframeVector3(
+        (0..10)
+            .map(|frame| KeyframeVector3 {
+                frame: frame * 100,
+                value: Vector3 { x: x(frame as f32), y: 1.0, z: 0.0 },
+            })
+            .collect(),
+    )
+}
+
+fn frames(curve: &Curve) -> Vec<i32> {
+    match curve {
+        Curve::KeyframeVector3(keyframes) => keyframes.iter().map(|keyframe| keyframe.frame).collect(),
+        Curve::KeyframeVector4(keyframes) => keyframes.iter().map(|keyframe| keyframe.frame).collect(),
+        Curve::KeyframeFloat(keyframes) => keyframes.iter().map(|keyframe| keyframe.frame).collect(),
+        other => panic!("unexpected curve {:?}", other),
+    }
+}
+
+#[test]
+fn linear_curves_keep_their_ends_and_null_keyframe() {
+    let mut anm = anm(vec![locations(|frame| frame * 2.0)]);
+    let report = reduce_keyframes(&mut anm, &KeyframeTolerance::uniform(0.001)).unwrap();
+
+    assert_eq!(frames(&anm.entries[0].curves[0]), [0, 900, -1]);
+    assert_eq!(report.keyframes_before, 11);
+    assert_eq!(report.keyframes_removed, 8);
+    assert_eq!(report.bytes_saved, 8 * 16);
+
+    let header = &anm.entries[0].curve_headers[0];
+    assert_eq!((header.frame_count, header.curve_size), (3, 48));
+}
+
+#[test]
+fn reduced_curves_stay_within_tolerance() {
+    let tolerance = 0.05;
+    let original = locations(|frame| (frame * 0.3).sin());
+
+    let mut anm = anm(vec![original.clone()]);
+    let report = reduce_keyframes(&mut anm, &KeyframeTolerance::uniform(tolerance)).unwrap();
+    assert!(report.keyframes_removed > 0);
+
+    let reduced = &anm.entries[0].curves[0];
+    for frame in 0..10 {
+        let expected = evaluate_curve(&original, frame as f32, 100).unwrap();
+        let actual = evaluate_curve(reduced, frame as f32, 100).unwrap();
+
+        assert!((expected[0] - actual[0]).abs() <= tolerance, "frame {}: {:?} vs {:?}", frame, actual, expected);
+    }
+}
+
+#[test]
+fn zero_tolerance_keeps_changing_keyframes() {
+    let mut anm = anm(vec![locations(|frame| frame * frame)]);
+    let report = reduce_keyframes(&mut anm, &KeyframeTolerance::uniform(0.0)).unwrap();
+
+    assert_eq!(report.keyframes_removed, 0);
+    assert_eq!(frames(&anm.entries[0].curves[0]).len(), 11);
+}
+
+#[test]
+fn rotations_are_rebuilt_by_slerp() {
+    // A constant speed turn around z, which slerp rebuilds exactly but lerp doesn't
+    let rotations = Curve::KeyframeVector4(
+        (0..7)
+            .map(|frame| {
+                let half_angle = frame as f32 * std::f32::consts::PI / 16.0;
+                KeyframeVector4 {
+                    frame: frame * 100,
+                    value: Vector4 { x: 0.0, y: 0.0, z: half_angle.sin(), w: half_angle.cos() },
+                }
+            })
+            .collect(),
+    );
+
+    let mut anm = anm(vec![rotations]);
+    reduce_keyframes(&mut anm, &KeyframeTolerance { vector4: 0.001, ..KeyframeTolerance::uniform(0.0) }).unwrap();
+
+    assert_eq!(frames(&anm.entries[0].curves[0]), [0, 600, -1]);
+}
+
+#[test]
+fn per_frame_curves_are_left_alone() {
+    let mut anm = anm(vec![Curve::Float(vec![1.0; 10]), locations(|_| 3.0)]);
+    let report = reduce_keyframes(&mut anm, &KeyframeTolerance::uniform(0.01)).unwrap();
+
+    assert_eq!(anm.entries[0].curves[0].get_frame_count(), 10);
+    assert_eq!(frames(&anm.entries[0].curves[1]), [0, 900, -1]);
+    assert_eq!(report.keyframes_before, 11);
+}
+
+/// A per-frame turn around z by `step` radians a frame, plus `wobble` on alternate frames.
+fn short_rotations(step: f32, wobble: f32) -> Curve {
+    Curve::QuaternionShort(
+        (0..20)
+            .map(|frame| {
+                let half_angle = (frame as f32 * step + (frame % 2) as f32 * wobble) / 2.0;
+                QuaternionShort {
+                    x: 0,
+                    y: 0,
+                    z: (half_angle.sin() * 32767.0).round() as i16,
+                    w: (half_angle.cos() * 32767.0).round() as i16,
+                }
+            })
+            .collect(),
+    )
+}
+
+#[test]
+fn per_frame_rotations_become_keyframes() {
+    let original = short_rotations(0.1, 0.0);
+    let mut anm = anm(vec![original.clone()]);
+    let tolerance = KeyframeTolerance { vector4: 0.001, ..KeyframeTolerance::uniform(0.0) };
+    let report = reduce_keyframes(&mut anm, &tolerance).unwrap();
+
+    let entry = &anm.entries[0];
+    assert_eq!(frames(&entry.curves[0]), [0, 1900, -1]);
+    assert_eq!(entry.curve_headers[0].curve_format, AnmCurveFormat::INT1_FLOAT4 as u16);
+    assert_eq!((entry.curve_headers[0].frame_count, entry.curve_headers[0].curve_size), (3, 60));
+    assert_eq!(report.keyframes_removed, 17);
+    assert_eq!(report.bytes_saved, 20 * 8 - 60);
+
+    for frame in 0..20 {
+        let expected = evaluate_curve(&original, frame as f32, 100).unwrap();
+        let actual = evaluate_curve(&entry.curves[0], frame as f32, 100).unwrap();
+
+        assert!(expected.iter().zip(&actual).all(|(a, b)| (a - b).abs() <= 0.001), "frame {}: {:?} vs {:?}", frame, actual, expected);
+    }
+}
+
+#[test]
+fn per_frame_rotations_stay_when_keyframes_are_larger() {
+    let mut anm = anm(vec![short_rotations(0.1, 0.2)]);
+    let report = reduce_keyframes(&mut anm, &KeyframeTolerance::uniform(0.001)).unwrap();
+
+    assert!(matches!(anm.entries[0].curves[0], Curve::QuaternionShort(_)));
+    assert_eq!(anm.entries[0].curve_headers[0].curve_format, AnmCurveFormat::SHORT4 as u16);
+    assert_eq!(report, ReductionReport::default());
+}
@@ -0,0 +1,49 @@
+use std::io::Cursor;
+
+use binrw::BinWriterExt;
+
+use anmstrm2anm::{convert_anmstrm, convert_anmstrm_bytes, read_anmstrm, Error, NuccAnm, NuccAnmStrm, NuccAnmStrmFrame};
+
+mod common;
+use common::{bone_at, camera, entry, options, stream_bytes, StreamBuilder, IDENTITY};
+
+/// A stream with a bone moving along x and a camera turning its field of view.
+fn stream() -> (NuccAnmStrm, Vec<NuccAnmStrmFrame>) {
+    StreamBuilder::new()
+        .frames(4, |frame| {
+            vec![
+                entry(0, 0, bone_at([frame as f32, 0.0, 0.0])),
+                entry(-1, 0, camera([0.0; 3], IDENTITY, 45.0 + frame as f32)),
+            ]
+        })
+        .build()
+}
+
+fn anm_bytes(anm: &NuccAnm) -> Vec<u8> {
+    let mut data = Cursor::new(Vec::new());
+    data.write_be(anm).unwrap();
+    data.into_inner()
+}
+
+#[test]
+fn bytes_convert_like_parsed_streams() {
+    let (anmstrm, frames) = stream();
+    let (anmstrm_data, frame_datas) = stream_bytes(&anmstrm, &frames);
+
+    let from_bytes = convert_anmstrm_bytes(&anmstrm_data, &frame_datas, &options()).unwrap();
+    let parsed = convert_anmstrm(&read_anmstrm(&mut Cursor::new(&anmstrm_data)).unwrap(), frames, &options()).unwrap();
+
+    assert_eq!(from_bytes.len(), 1);
+    assert_eq!(from_bytes[0].entries.len(), 2);
+    assert_eq!(anm_bytes(&from_bytes[0]), anm_bytes(&parsed[0]));
+}
+
+#[test]
+fn truncated_bytes_are_an_error() {
+    let (anmstrm, frames) = stream();
+    let (anmstrm_data, mut frame_datas) = stream_bytes(&anmstrm, &frames);
+    frame_datas[2].truncate(10);
+
+    let result = convert_anmstrm_bytes(&anmstrm_data, &frame_datas, &options());
+    assert!(matches!(result, Err(Error::Parse { path: None, .. })), "{:?}", result.map(|anms| anms.len()));
+}
@@ -0,0 +1,115 @@
+use anmstrm2anm::{
+    build_page::anm_page,
+    convert_anm,
+    structure::{
+        anm::{AnmCurveFormat, AnmEntryFormat, Curve},
+        anmstrm::*,
+        page::{Chunk, ChunkReference, Files, Page},
+    },
+};
+
+mod common;
+use common::{entry, options, StreamBuilder};
+
+const WEIGHTS: [[f32; 3]; 3] = [[0.0, 0.5, 1.0], [0.25, 0.5, 0.75], [1.0, 0.0, 0.5]];
+
+fn morph_stream() -> StreamBuilder {
+    StreamBuilder::new().frames(WEIGHTS.len() as u32, |frame| {
+        let weights = WEIGHTS[frame as usize];
+
+        vec![entry(0, 3, Entry::MorphModel(AnmEntryMorphModel {
+            frame_count: weights.len() as i32,
+            morph_weight: weights.to_vec(),
+        }))]
+    })
+}
+
+#[test]
+fn morph_weights_become_morphmodel_entries() {
+    let anm = morph_stream().convert(&options());
+
+    assert_eq!(anm.entries.len(), 1);
+
+    let entry = &anm.entries[0];
+    assert_eq!(entry.entry_format, AnmEntryFormat::MORPHMODEL as u16);
+    assert_eq!(entry.coord.coord_index, 3);
+    assert_eq!(entry.curve_count, 3);
+
+    for (target, (header, curve)) in entry.curve_headers.iter().zip(&entry.curves).enumerate() {
+        assert_eq!(header.curve_index, target as u16);
+        assert_eq!(header.curve_format, AnmCurveFormat::FLOAT1ALT as u16);
+        assert_eq!(header.frame_count, WEIGHTS.len() as u16);
+
+        let expected: Vec<f32> = WEIGHTS.iter().map(|weights| weights[target]).collect();
+        match curve {
+            Curve::Float(values) => assert_eq!(values, &expected),
+            other => panic!("unexpected curve {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn morphmodel_entries_convert_back_to_morph_weights() {
+    let anm = morph_stream().convert(&options());
+
+    let (_, frames) = convert_anm(&anm).unwrap();
+    assert_eq!(frames.len(), WEIGHTS.len());
+
+    for (frame, weights) in frames.iter().zip(WEIGHTS) {
+        match &frame.entries[0].entry_data {
+            Entry::MorphModel(morph) => {
+                assert_eq!(morph.frame_count, 3);
+                assert_eq!(morph.morph_weight, weights.to_vec());
+            }
+            other => panic!("unexpected entry {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn anm_page_keeps_morphmodel_chunks() {
+    let chunk = |name: &str, types: &str| Chunk {
+        name: name.to_string(),
+        types: types.to_string(),
+        path: "c/test.max".to_string(),
+    };
+
+    let morph = chunk("face", "nuccChunkMorphModel");
+
+    let page = Page {
+        chunk_maps: vec![
+            Chunk::empty("nuccChunkNull"),
+            chunk("test", "nuccChunkAnmStrm"),
+            chunk("test", "nuccChunkAnmStrmFrame"),
+            morph.clone(),
+            Chunk::empty("nuccChunkPage"),
+        ],
+        chunk_references: vec![ChunkReference {
+            name: "face".to_string(),
+            chunk: morph.clone(),
+        }],
+        files: vec![
+            Files {
+                file_name: "test.anmstrm".to_string(),
+                chunk: chunk("test", "nuccChunkAnmStrm"),
+            },
+            Files {
+                file_name: "test_0.anmstrmframe".to_string(),
+                chunk: chunk("test", "nuccChunkAnmStrmFrame"),
+            },
+            Files {
+                file_name: "face.morphmodel".to_string(),
+                chunk: morph.clone(),
+            },
+        ],
+    };
+
+    let page = anm_page(page, Some(&[]));
+
+    assert!(page.chunk_maps.contains(&morph));
+    assert!(!page.chunk_maps.iter().any(|map| map.types == "nuccChunkAnmStrmFrame"));
+    assert!(page.chunk_references.iter().any(|reference| reference.chunk == morph));
+
+    let file_names: Vec<&str> = page.files.iter().map(|file| file.file_name.as_str()).collect();
+    assert_eq!(file_names, ["test.anm", "face.morphmodel"]);
+}
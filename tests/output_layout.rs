@@ -0,0 +1,56 @@
+use std::path::{Component, Path};
+
+use anmstrm2anm::output::OutputLayout;
+
+mod common;
+use common::{bone_at, entry, run_cli, temp_dir, write_xfbin_folder, StreamBuilder};
+
+fn assert_no_backslashes(path: &Path) {
+    for component in path.components() {
+        if let Component::Normal(name) = component {
+            assert!(!name.to_string_lossy().contains('\\'), "{} has a backslash in {:?}", path.display(), name);
+        }
+    }
+}
+
+#[test]
+fn paths_are_joined_per_component() {
+    let out = Path::new("out").join("cutscenes");
+    let layout = OutputLayout::new(&out, "x");
+    let root = out.join("x_converted");
+
+    assert_eq!(layout.root(), root);
+    assert_eq!(layout.anm_dir(0, ""), root.join("[000] x (nuccChunkAnm)"));
+    assert_eq!(layout.anm_file(1, "_dmg"), root.join("[001] x_dmg (nuccChunkAnm)").join("x_dmg.anm"));
+    assert_eq!(layout.page_file(12, "_dmg"), root.join("[012] x_dmg (nuccChunkAnm)").join("_page.json"));
+    assert_eq!(layout.anmstrm_file(), root.join("[000] x (nuccChunkAnmStrmFrame)").join("x.anmstrm"));
+    assert_eq!(layout.anmstrm_frame_file(3), root.join("[000] x (nuccChunkAnmStrmFrame)").join("x_3.anmstrmframe"));
+    assert_eq!(layout.anmstrm_page_file(), root.join("[000] x (nuccChunkAnmStrmFrame)").join("_page.json"));
+    assert_eq!(layout.xfbin_file(), root.join("x.xfbin"));
+
+    for path in [layout.anm_file(1, "_dmg"), layout.page_file(0, ""), layout.anmstrm_frame_file(0), layout.xfbin_file()] {
+        assert_no_backslashes(&path);
+    }
+}
+
+#[test]
+fn converted_folders_are_written_as_a_tree() {
+    let dir = temp_dir("output_layout");
+    let out = dir.join("out");
+    let (anmstrm, frames) = StreamBuilder::new()
+        .clump(1, &[2])
+        .frames(2, |frame| vec![entry(0, 0, bone_at([frame as f32, 0.0, 0.0]))])
+        .build();
+    write_xfbin_folder(&dir.join("x"), "x", &anmstrm, &frames);
+
+    let output = run_cli(&["convert".as_ref(), dir.join("x").as_os_str(), "-o".as_ref(), out.as_os_str(), "--xfbin".as_ref(), "-q".as_ref()]);
+    let layout = OutputLayout::new(&out, "x");
+    let written = [layout.anm_file(0, ""), layout.page_file(0, ""), layout.xfbin_file()].map(|path| path.is_file());
+    let out_entries = std::fs::read_dir(&out).unwrap().count();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(written, [true; 3]);
+    // Only the `x_converted` folder, no files named after a joined path
+    assert_eq!(out_entries, 1);
+}
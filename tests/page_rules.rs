@@ -0,0 +1,229 @@
+use std::{fs, path::PathBuf};
+
+use anmstrm2anm::{
+    page_rules::{apply_rules, RuleContext},
+    structure::page::{Chunk, ChunkReference, Files, Page},
+    Error, PageRules,
+};
+
+fn chunk(name: &str, types: &str, path: &str) -> Chunk {
+    Chunk {
+        name: name.to_string(),
+        types: types.to_string(),
+        path: path.to_string(),
+    }
+}
+
+/// The page of an anmstrm with two frames, a model, and the DMG clump's model.
+fn anmstrm_page() -> Page {
+    let anmstrm = chunk("d01_anm", "nuccChunkAnmStrm", "c/d01/d01.max");
+    let frame = chunk("d01_anm", "nuccChunkAnmStrmFrame", "c/d01/d01.max");
+
+    Page {
+        chunk_maps: vec![
+            Chunk::empty("nuccChunkNull"),
+            anmstrm.clone(),
+            frame.clone(),
+            chunk("1nrtbod1", "nuccChunkClump", "c/1nrt/1nrtbod1.max"),
+            chunk("1cmnbod1", "nuccChunkClump", "c/1cmn/1cmnbod1.max"),
+            Chunk::empty("nuccChunkPage"),
+        ],
+        chunk_references: vec![
+            ChunkReference {
+                name: "1nrt".to_string(),
+                chunk: chunk("1nrtbod1", "nuccChunkClump", "c/1nrt/1nrtbod1.max"),
+            },
+            ChunkReference {
+                name: "1cmn".to_string(),
+                chunk: chunk("1cmnbod1", "nuccChunkClump", "c/1cmn/1cmnbod1.max"),
+            },
+        ],
+        files: vec![
+            Files {
+                file_name: "d01_anm.anmstrm".to_string(),
+                chunk: anmstrm,
+            },
+            Files {
+                file_name: "d01_anm_0.anmstrmframe".to_string(),
+                chunk: frame.clone(),
+            },
+            Files {
+                file_name: "d01_anm_1.anmstrmframe".to_string(),
+                chunk: frame,
+            },
+        ],
+    }
+}
+
+fn rules(json: &str) -> PageRules {
+    serde_json::from_str(json).unwrap()
+}
+
+fn types(chunks: &[Chunk]) -> Vec<&str> {
+    chunks.iter().map(|chunk| chunk.types.as_str()).collect()
+}
+
+fn file_names(page: &Page) -> Vec<&str> {
+    page.files.iter().map(|file| file.file_name.as_str()).collect()
+}
+
+fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("anmstrm2anm_{}_{}", std::process::id(), name))
+}
+
+#[test]
+fn default_rules_round_trip_through_json() {
+    let path = temp_file("default_rules.json");
+
+    PageRules::default().to_json_file(&path).unwrap();
+    let rules = PageRules::from_json_file(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(rules, PageRules::default());
+}
+
+#[test]
+fn default_rules_build_the_anm_and_dmg_pages() {
+    let rules = PageRules::default();
+
+    let anm = rules.anm_page(anmstrm_page(), Some(&[4]));
+    assert_eq!(
+        types(&anm.chunk_maps),
+        ["nuccChunkNull", "nuccChunkAnm", "nuccChunkClump", "nuccChunkPage"]
+    );
+    assert_eq!(anm.chunk_references.len(), 1);
+    assert_eq!(file_names(&anm), ["d01_anm.anm"]);
+
+    let dmg = rules.dmg_page(anmstrm_page(), &[3]);
+    assert!(dmg.chunk_maps.iter().any(|chunk| chunk.name == "d01_anm_dmg"));
+    assert!(dmg.chunk_maps.iter().all(|chunk| !chunk.name.starts_with("1nrt")));
+
+    let anmstrm = rules.anmstrm_page(rules.anm_page(anmstrm_page(), None), 2);
+    assert_eq!(types(&anmstrm.chunk_maps), types(&anmstrm_page().chunk_maps));
+    assert_eq!(file_names(&anmstrm), file_names(&anmstrm_page()));
+}
+
+#[test]
+fn custom_rules_retain_remove_and_rewrite() {
+    let rules = rules(
+        r#"{
+            "Anm": [
+                { "Retain": { "Target": "Chunk Maps", "Any": [{ "Type": "Null|Page" }, { "Name": "^d01" }] } },
+                { "Remove": { "Target": "Chunk Maps", "Any": [{ "Type": "Frame$" }] } },
+                { "Remove": { "Target": "Chunk References", "Any": [{ "Reference Name": "1nrt", "Path": "1nrt" }] } },
+                { "Remove": { "Target": "Chunks", "Any": [{ "File Name": "frame$" }] } },
+                { "Rewrite": { "Target": "Chunks", "Matching": { "Type": "AnmStrm" }, "Edits": [
+                    { "Set": { "Field": "Type", "Value": "nuccChunkAnm" } },
+                    { "Replace": { "Field": "Path", "Pattern": "c/(\\w+)/", "With": "anm/${1}_" } },
+                    { "Replace": { "Field": "File Name", "Pattern": "\\.anmstrm$", "With": ".anm" } }
+                ] } }
+            ],
+            "Anm Without DMG": [],
+            "DMG": [],
+            "Clump": [],
+            "AnmStrm": []
+        }"#,
+    );
+
+    let page = rules.anm_page(anmstrm_page(), Some(&[]));
+
+    assert_eq!(types(&page.chunk_maps), ["nuccChunkNull", "nuccChunkAnmStrm", "nuccChunkPage"]);
+    assert_eq!(page.chunk_references.len(), 1);
+    assert_eq!(page.chunk_references[0].name, "1cmn");
+
+    assert_eq!(file_names(&page), ["d01_anm.anm"]);
+    assert_eq!(page.files[0].chunk, chunk("d01_anm", "nuccChunkAnm", "anm/d01_d01.max"));
+}
+
+#[test]
+fn split_chunks_are_removed_and_suffixes_filled_in() {
+    let rules = rules(
+        r#"{
+            "Anm": [],
+            "Anm Without DMG": [],
+            "DMG": [],
+            "Clump": [
+                { "Remove Split Chunks": { "Keep": [{ "Type": "AnmStrm" }] } },
+                { "Rewrite": { "Target": "Chunks", "Matching": { "File Name": "\\.anmstrm$" }, "Edits": [
+                    { "Replace": { "Field": "File Name", "Pattern": "\\.anmstrm$", "With": "{Suffix}.anm" } },
+                    { "Set": { "Field": "Name", "Value": "d01{Suffix}" } }
+                ] } }
+            ],
+            "AnmStrm": []
+        }"#,
+    );
+
+    // The anmstrm and frame chunks at 1 and 2 are kept, the 1nrt clump at 3 is removed
+    let page = rules.clump_page(anmstrm_page(), &[1, 2, 3], "_1cmn");
+
+    assert_eq!(
+        types(&page.chunk_maps),
+        ["nuccChunkNull", "nuccChunkAnmStrm", "nuccChunkAnmStrmFrame", "nuccChunkClump", "nuccChunkPage"]
+    );
+    assert_eq!(page.chunk_maps[3].name, "1cmnbod1");
+    assert_eq!(page.chunk_references.len(), 1);
+    assert_eq!(page.chunk_references[0].name, "1cmn");
+
+    assert_eq!(file_names(&page), ["d01_anm_1cmn.anm", "d01_anm_0.anmstrmframe", "d01_anm_1.anmstrmframe"]);
+    assert_eq!(page.files[0].chunk.name, "d01_1cmn");
+}
+
+#[test]
+fn first_only_rewrites_a_single_item() {
+    let rewrite = |first_only: bool| {
+        rules(&format!(
+            r#"{{
+                "Anm": [
+                    {{ "Rewrite": {{ "Target": "Chunk Maps", "Matching": {{ "Type": "Clump" }}, "First Only": {}, "Edits": [
+                        {{ "Replace": {{ "Field": "Name", "Pattern": "$", "With": "_x" }} }}
+                    ] }} }}
+                ],
+                "Anm Without DMG": [],
+                "DMG": [],
+                "Clump": [],
+                "AnmStrm": []
+            }}"#,
+            first_only
+        ))
+        .anm_page(anmstrm_page(), None)
+    };
+
+    let names = |page: &Page| -> Vec<String> {
+        page.chunk_maps.iter().filter(|chunk| chunk.types == "nuccChunkClump").map(|chunk| chunk.name.clone()).collect()
+    };
+
+    assert_eq!(names(&rewrite(true)), ["1nrtbod1_x", "1cmnbod1"]);
+    assert_eq!(names(&rewrite(false)), ["1nrtbod1_x", "1cmnbod1_x"]);
+}
+
+#[test]
+fn frame_chunks_follow_the_anmstrm() {
+    let mut page = anmstrm_page();
+    page.chunk_maps.retain(|chunk| chunk.types != "nuccChunkAnmStrmFrame");
+    page.files.truncate(1);
+
+    let rules = rules(r#"{ "Anm": [], "Anm Without DMG": [], "DMG": [], "Clump": [], "AnmStrm": ["Add Frame Chunks"] }"#);
+    let page = apply_rules(&rules.anmstrm, page, &RuleContext { frame_count: 3, ..RuleContext::default() });
+
+    assert_eq!(types(&page.chunk_maps)[1..3], ["nuccChunkAnmStrm", "nuccChunkAnmStrmFrame"]);
+    assert_eq!(
+        file_names(&page),
+        ["d01_anm.anmstrm", "d01_anm_0.anmstrmframe", "d01_anm_1.anmstrmframe", "d01_anm_2.anmstrmframe"]
+    );
+    assert_eq!(page.files[3].chunk.types, "nuccChunkAnmStrmFrame");
+}
+
+#[test]
+fn invalid_patterns_are_rejected() {
+    let path = temp_file("invalid_rules.json");
+    fs::write(
+        &path,
+        r#"{ "Anm": [{ "Remove": { "Target": "Chunks", "Any": [{ "Name": "(" }] } }], "Anm Without DMG": [], "DMG": [], "Clump": [], "AnmStrm": [] }"#,
+    )
+    .unwrap();
+
+    let result = PageRules::from_json_file(&path);
+    fs::remove_file(&path).unwrap();
+
+    assert!(matches!(result, Err(Error::PageRulesJson { .. })), "{:?}", result);
+}
@@ -0,0 +1,61 @@
+use anmstrm2anm::{evaluate_curve, structure::anm::Curve, ConvertOptions};
+
+mod common;
+use common::{bone, entry, options, StreamBuilder};
+
+/// Converts one bone with the given rotation per frame and returns its SHORT4 rotation curve.
+fn convert_rotations(rotations: &[[f32; 4]]) -> Curve {
+    let options = ConvertOptions {
+        collapse_constant_curves: false,
+        ..options()
+    };
+
+    let mut anm = StreamBuilder::new()
+        .frames(rotations.len() as u32, |frame| vec![entry(0, 0, bone([0.0; 3], rotations[frame as usize], [1.0; 3]))])
+        .convert(&options);
+
+    anm.entries.remove(0).curves.remove(1)
+}
+
+fn shorts(curve: &Curve) -> Vec<[i16; 4]> {
+    match curve {
+        Curve::QuaternionShort(values) => values.iter().map(|value| [value.x, value.y, value.z, value.w]).collect(),
+        other => panic!("unexpected curve {:?}", other),
+    }
+}
+
+#[test]
+fn rotations_are_normalized() {
+    let curve = convert_rotations(&[[0.0, 0.0, 0.0, 2.0], [0.0, 3.0, 0.0, 4.0]]);
+
+    assert_eq!(shorts(&curve), [[0, 0, 0, 32767], [0, 19660, 0, 26214]]);
+}
+
+#[test]
+fn degenerate_rotations_become_identity() {
+    let curve = convert_rotations(&[[0.0, 0.0, 0.0, 0.0], [f32::NAN, 0.0, 0.0, 1.0]]);
+
+    assert_eq!(shorts(&curve), [[0, 0, 0, 32767], [0, 0, 0, 32767]]);
+}
+
+#[test]
+fn rotations_are_rounded() {
+    let half = std::f32::consts::FRAC_1_SQRT_2;
+    let curve = convert_rotations(&[[half, 0.0, 0.0, half]]);
+
+    // 0.70710677 * 32767 = 23169.77, truncating would give 23169
+    assert_eq!(shorts(&curve), [[23170, 0, 0, 23170]]);
+}
+
+#[test]
+fn sign_flips_between_frames_are_undone() {
+    let (sin, cos) = (0.1f32.sin(), 0.1f32.cos());
+    let curve = convert_rotations(&[[0.0, 0.0, sin, cos], [0.0, 0.0, -sin, -cos], [0.0, 0.0, sin, cos]]);
+
+    let values = shorts(&curve);
+    assert!(values.iter().all(|value| value[3] > 0), "{:?}", values);
+
+    // Halfway between the frames the rotation stays put instead of spinning around
+    let halfway = evaluate_curve(&curve, 0.5, 100).unwrap();
+    assert!((halfway[2] - sin).abs() < 1e-3 && (halfway[3] - cos).abs() < 1e-3, "{:?}", halfway);
+}
@@ -0,0 +1,60 @@
+use anmstrm2anm::{
+    convert_anm,
+    structure::{
+        anm::{AnmCurveFormat, Curve},
+        anmstrm::Entry,
+    },
+    NuccAnm,
+};
+
+mod common;
+use common::{bone, entry, options, write_read, StreamBuilder, IDENTITY};
+
+/// Two bones scaling up over four frames, the second one past what SHORT3 can hold.
+fn convert() -> NuccAnm {
+    let scaled = |coord_index: u16, scale: f32| entry(0, coord_index, bone([0.0; 3], IDENTITY, [scale, 1.0, -scale]));
+
+    StreamBuilder::new()
+        .frames(4, |frame| vec![scaled(0, 1.0 + frame as f32), scaled(1, 1.0 + frame as f32 * 3.5)])
+        .convert(&options())
+}
+
+#[test]
+fn scales_in_range_stay_short3() {
+    let anm = convert();
+    let entry = anm.entries.iter().find(|entry| entry.coord.coord_index == 0).unwrap();
+
+    assert_eq!(entry.curve_headers[2].curve_format, AnmCurveFormat::SHORT3 as u16);
+    assert!(matches!(entry.curves[2], Curve::Vector3Short(_)));
+}
+
+#[test]
+fn scales_out_of_range_become_float_curves() {
+    let anm = convert();
+    let entry = anm.entries.iter().find(|entry| entry.coord.coord_index == 1).unwrap();
+
+    assert_eq!(entry.curve_headers[2].curve_format, AnmCurveFormat::FLOAT3ALT2 as u16);
+    assert_eq!(entry.curve_headers[2].curve_size, 4 * 12);
+
+    match &entry.curves[2] {
+        Curve::Vector3(values) => {
+            let x: Vec<f32> = values.iter().map(|value| value.x).collect();
+            assert_eq!(x, [1.0, 4.5, 8.0, 11.5]);
+            assert_eq!(values[3].z, -11.5);
+        }
+        other => panic!("unexpected curve {:?}", other),
+    }
+}
+
+#[test]
+fn float_scales_read_back_and_convert_back() {
+    let anm = write_read(&convert());
+
+    let (_, frames) = convert_anm(&anm).unwrap();
+    let entry = frames[3].entries.iter().find(|entry| entry.coord.coord_index == 1).unwrap();
+
+    match &entry.entry_data {
+        Entry::Bone(bone) => assert_eq!((bone.scale.x, bone.scale.z), (11.5, -11.5)),
+        other => panic!("unexpected entry {:?}", other),
+    }
+}
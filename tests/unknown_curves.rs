@@ -0,0 +1,71 @@
+use std::io::Cursor;
+
+use binrw::BinWriterExt;
+
+use anmstrm2anm::{
+    read_anm,
+    structure::anm::{AnmCurveFormat, Curve},
+};
+
+const UNKNOWN_FORMAT: u16 = 0x30;
+
+/// An anm with one entry holding a curve of a format we don't model, 2 frames in 12 bytes,
+/// followed by a FLOAT1 curve with a single value.
+fn anm_bytes() -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend(200u32.to_be_bytes()); // anm length
+    bytes.extend(100u32.to_be_bytes()); // frame size
+    for value in [1u16, 0, 0, 0, 0, 0] {
+        // entry count, looped, clump count, other entry count, other index count, coord count
+        bytes.extend(value.to_be_bytes());
+    }
+
+    bytes.extend(0i16.to_be_bytes()); // clump index
+    bytes.extend(1u16.to_be_bytes()); // coord index
+    bytes.extend(1u16.to_be_bytes()); // entry format
+    bytes.extend(2u16.to_be_bytes()); // curve count
+
+    for value in [0, UNKNOWN_FORMAT, 2, 12] {
+        bytes.extend(value.to_be_bytes());
+    }
+    for value in [1, AnmCurveFormat::FLOAT1 as u16, 1, 4] {
+        bytes.extend(value.to_be_bytes());
+    }
+
+    bytes.extend(raw_data());
+    bytes.extend(1.5f32.to_be_bytes());
+
+    bytes
+}
+
+fn raw_data() -> Vec<u8> {
+    (1..=12).collect()
+}
+
+#[test]
+fn unknown_curve_formats_are_read_as_raw_bytes() {
+    let anm = read_anm(&mut Cursor::new(anm_bytes())).unwrap();
+    let entry = &anm.entries[0];
+
+    match &entry.curves[0] {
+        Curve::Unknown { curve_format, frame_count, data } => {
+            assert_eq!((*curve_format, *frame_count), (UNKNOWN_FORMAT, 2));
+            assert_eq!(data, &raw_data());
+        }
+        other => panic!("unexpected curve {:?}", other),
+    }
+
+    // A frame count, not the byte count of the data
+    assert_eq!(entry.curves[0].get_frame_count(), 2);
+    assert!(matches!(&entry.curves[1], Curve::Float(values) if values == &[1.5]));
+}
+
+#[test]
+fn unknown_curves_keep_their_format_and_bytes_on_write() {
+    let anm = read_anm(&mut Cursor::new(anm_bytes())).unwrap();
+
+    let mut data = Cursor::new(Vec::new());
+    data.write_be(&anm).unwrap();
+
+    assert_eq!(data.into_inner(), anm_bytes());
+}
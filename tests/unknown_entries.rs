@@ -0,0 +1,76 @@
+use std::io::Cursor;
+
+use binrw::BinWriterExt;
+
+use anmstrm2anm::{
+    convert_anmstrm,
+    structure::anm::{AnmEntry, Curve, CurveHeader, RAW_CURVE_FORMAT},
+    ConvertOptions, Error, NuccAnm,
+};
+
+mod common;
+use common::{coord, options, unknown_entry, write_read, StreamBuilder};
+
+/// A stream with one entry of an unknown format holding `size` bytes on every frame.
+fn convert(frame_count: u32, size: usize) -> Result<NuccAnm, Error> {
+    let (anmstrm, frames) = StreamBuilder::new()
+        .frames(frame_count, |frame| vec![unknown_entry(0, 0, 99, vec![frame as u8; size])])
+        .build();
+
+    let options = ConvertOptions {
+        keep_unknown_entries: true,
+        ..options()
+    };
+
+    convert_anmstrm(&anmstrm, frames, &options).map(|mut anms| anms.remove(0))
+}
+
+#[test]
+fn unknown_entries_pass_through_as_raw_curves() {
+    let anm = write_read(&convert(3, 6).unwrap());
+
+    let entry = &anm.entries[0];
+    assert_eq!(entry.entry_format, 99);
+    assert_eq!(entry.curve_headers[0].curve_format, RAW_CURVE_FORMAT);
+    assert_eq!((entry.curve_headers[0].frame_count, entry.curve_headers[0].curve_size), (3, 18));
+
+    match &entry.curves[0] {
+        Curve::Unknown { curve_format, frame_count, data } => {
+            assert_eq!((*curve_format, *frame_count), (RAW_CURVE_FORMAT, 3));
+            assert_eq!(data[..], [[0; 6], [1; 6], [2; 6]].concat());
+        }
+        other => panic!("unexpected curve {:?}", other),
+    }
+}
+
+#[test]
+fn unknown_entries_too_large_for_a_curve_are_an_error() {
+    let result = convert(2, 40_000);
+
+    assert!(
+        matches!(result, Err(Error::CurveTooLarge { curve_format: RAW_CURVE_FORMAT, size: 80_000 })),
+        "{:?}",
+        result.map(|anm| anm.entries.len())
+    );
+}
+
+#[test]
+fn writing_an_oversized_curve_fails() {
+    let curve = Curve::Unknown { curve_format: 0x30, frame_count: 1, data: vec![0; 70_000] };
+    assert!(matches!(curve.curve_size(), Err(Error::CurveTooLarge { curve_format: 0x30, size: 70_000 })));
+
+    let entry = AnmEntry {
+        coord: coord(0, 0),
+        entry_format: 99,
+        curve_count: 1,
+        curve_headers: vec![CurveHeader {
+            curve_index: 0,
+            curve_format: 0x30,
+            frame_count: 1,
+            curve_size: 0,
+        }],
+        curves: vec![curve],
+    };
+
+    assert!(Cursor::new(Vec::new()).write_be(&entry).is_err());
+}
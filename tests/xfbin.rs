@@ -0,0 +1,232 @@
+use std::io::Cursor;
+
+use binrw::BinReaderExt;
+
+use anmstrm2anm::{
+    structure::{
+        page::{Chunk, ChunkReference},
+        xfbin::{NuccChunkTable, NuccHeader},
+    },
+    xfbin::{XfbinChunk, XfbinPage, NUCC_HEADER_SIZE},
+    Error, Xfbin,
+};
+
+fn chunk(name: &str, types: &str, path: &str) -> Chunk {
+    Chunk {
+        name: name.to_string(),
+        types: types.to_string(),
+        path: path.to_string(),
+    }
+}
+
+fn types(chunks: &[Chunk]) -> Vec<&str> {
+    chunks.iter().map(|chunk| chunk.types.as_str()).collect()
+}
+
+fn push_u32(data: &mut Vec<u8>, values: &[u32]) {
+    for value in values {
+        data.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn push_strings(data: &mut Vec<u8>, strings: &[&str]) {
+    for string in strings {
+        data.extend_from_slice(string.as_bytes());
+        data.push(0);
+    }
+}
+
+/// Null, page and index chunk maps plus an anmstrm with one frame and a referenced clump, all in one page.
+fn minimal_xfbin() -> Vec<u8> {
+    let types = ["nuccChunkNull", "nuccChunkAnmStrm", "nuccChunkAnmStrmFrame", "nuccChunkClump", "nuccChunkPage", "nuccChunkIndex"];
+    let paths = ["", "c/x/x.max"];
+    let names = ["", "x_anm", "xbod1", "page0", "index"];
+    let size = |strings: &[&str]| strings.iter().map(|string| string.len() as u32 + 1).sum::<u32>();
+
+    let mut table = Vec::new();
+    push_u32(&mut table, &[types.len() as u32, size(&types), paths.len() as u32, size(&paths)]);
+    push_u32(&mut table, &[names.len() as u32, size(&names), 6, 6 * 12, 6, 1]);
+    push_strings(&mut table, &types);
+    push_strings(&mut table, &paths);
+    push_strings(&mut table, &names);
+    table.resize(table.len().next_multiple_of(4), 0);
+
+    // Chunk maps: (type, path, name)
+    push_u32(&mut table, &[0, 0, 0, 1, 1, 1, 2, 1, 1, 3, 1, 2, 4, 0, 3, 5, 0, 4]);
+    // Reference named "xbod1" to the clump map
+    push_u32(&mut table, &[2, 3]);
+    // The page's chunk maps, in page order
+    push_u32(&mut table, &[0, 1, 2, 3, 4, 5]);
+
+    let mut data = b"NUCC".to_vec();
+    push_u32(&mut data, &[0x79, 0, 0, table.len() as u32, 3]);
+    data.extend_from_slice(&[0, 0x79, 0, 0]);
+    data.extend_from_slice(&table);
+
+    // Chunks: size, page-local chunk map index, version and unknown, then the data
+    let mut push_chunk = |map_index: u32, chunk_data: &[u8]| {
+        push_u32(&mut data, &[chunk_data.len() as u32, map_index, 0x0079_0000]);
+        data.extend_from_slice(chunk_data);
+    };
+    push_chunk(0, &[]);
+    push_chunk(1, &[1, 2, 3, 4]);
+    push_chunk(2, &[5, 6]);
+    push_chunk(4, &[0, 0, 0, 6, 0, 0, 0, 1]);
+
+    data
+}
+
+#[test]
+fn minimal_xfbin_is_read_into_a_page() {
+    let xfbin = Xfbin::read(&mut Cursor::new(minimal_xfbin())).unwrap();
+
+    assert_eq!(xfbin.pages.len(), 1);
+    let page = &xfbin.pages[0];
+
+    assert_eq!(
+        page.chunk_maps,
+        [
+            Chunk::empty("nuccChunkNull"),
+            chunk("x_anm", "nuccChunkAnmStrm", "c/x/x.max"),
+            chunk("x_anm", "nuccChunkAnmStrmFrame", "c/x/x.max"),
+            chunk("xbod1", "nuccChunkClump", "c/x/x.max"),
+            chunk("page0", "nuccChunkPage", ""),
+            chunk("index", "nuccChunkIndex", ""),
+        ]
+    );
+
+    assert_eq!(page.chunk_references.len(), 1);
+    assert_eq!(page.chunk_references[0].name, "xbod1");
+    assert_eq!(page.chunk_references[0].chunk, chunk("xbod1", "nuccChunkClump", "c/x/x.max"));
+
+    let chunks: Vec<(&str, &[u8])> = page.chunks.iter().map(|chunk| (chunk.chunk.types.as_str(), chunk.data.as_slice())).collect();
+    assert_eq!(chunks, [("nuccChunkAnmStrm", &[1, 2, 3, 4][..]), ("nuccChunkAnmStrmFrame", &[5, 6][..])]);
+
+    let anmstrms = xfbin.anmstrms();
+    assert_eq!(anmstrms.len(), 1);
+    assert_eq!(anmstrms[0].anmstrm.chunk.name, "x_anm");
+    assert_eq!(anmstrms[0].frames.len(), 1);
+
+    assert_eq!(page.to_page().files.iter().map(|file| file.file_name.as_str()).collect::<Vec<_>>(), ["x_anm.anmstrm", "x_anm_0.anmstrmframe"]);
+}
+
+#[test]
+fn chunk_map_indices_out_of_range_are_rejected() {
+    let mut data = minimal_xfbin();
+    // Point the anmstrm chunk at a chunk map the page doesn't have
+    let anmstrm_header = data.len() - (12 + 8) - (12 + 2) - (12 + 4);
+    data[anmstrm_header + 4..anmstrm_header + 8].copy_from_slice(&40u32.to_be_bytes());
+
+    let result = Xfbin::read(&mut Cursor::new(data));
+    assert!(matches!(result, Err(Error::InvalidXfbin { .. })), "{:?}", result);
+}
+
+#[test]
+fn truncated_chunks_are_rejected() {
+    let mut data = minimal_xfbin();
+    // Cut the page chunk's data short
+    data.truncate(data.len() - 4);
+
+    let result = Xfbin::read(&mut Cursor::new(data));
+    assert!(matches!(result, Err(Error::InvalidXfbin { .. })), "{:?}", result);
+}
+
+#[test]
+fn chunk_sizes_past_the_end_of_the_file_are_rejected() {
+    let mut data = minimal_xfbin();
+    let anmstrm_header = data.len() - (12 + 8) - (12 + 2) - (12 + 4);
+    data[anmstrm_header..anmstrm_header + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+
+    let result = Xfbin::read(&mut Cursor::new(data));
+    assert!(matches!(result, Err(Error::InvalidXfbin { .. })), "{:?}", result);
+}
+
+#[test]
+fn files_without_the_nucc_magic_are_rejected() {
+    let mut data = minimal_xfbin();
+    data[..4].copy_from_slice(b"NUCK");
+
+    assert!(matches!(Xfbin::read(&mut Cursor::new(data)), Err(Error::Parse { .. })));
+}
+
+/// Two pages sharing a clump chunk map, the first without null, page and index maps.
+fn two_pages() -> Xfbin {
+    let clump = chunk("xbod1", "nuccChunkClump", "c/x/x.max");
+    let data_chunk = |chunk: Chunk, data: &[u8]| XfbinChunk { chunk, data: data.to_vec() };
+
+    Xfbin {
+        pages: vec![
+            XfbinPage {
+                chunk_maps: vec![chunk("x_anm", "nuccChunkAnmStrm", "c/x/x.max"), clump.clone()],
+                chunk_references: vec![ChunkReference {
+                    name: "xbod1".to_string(),
+                    chunk: clump.clone(),
+                }],
+                chunks: vec![
+                    data_chunk(chunk("x_anm", "nuccChunkAnmStrm", "c/x/x.max"), &[1, 2, 3]),
+                    data_chunk(chunk("x_anm", "nuccChunkAnmStrmFrame", "c/x/x.max"), &[4]),
+                    data_chunk(chunk("x_anm", "nuccChunkAnmStrmFrame", "c/x/x.max"), &[5, 6]),
+                ],
+            },
+            XfbinPage {
+                chunk_maps: vec![Chunk::empty("nuccChunkNull"), chunk("y_anm", "nuccChunkAnm", "c/x/y.max"), clump],
+                chunk_references: Vec::new(),
+                chunks: vec![data_chunk(chunk("y_anm", "nuccChunkAnm", "c/x/y.max"), &[7; 9])],
+            },
+        ],
+    }
+}
+
+#[test]
+fn written_xfbin_reads_back() {
+    let mut data = Cursor::new(Vec::new());
+    two_pages().write(&mut data).unwrap();
+    let data = data.into_inner();
+
+    let mut reader = Cursor::new(&data);
+    let header: NuccHeader = reader.read_be().unwrap();
+    let table: NuccChunkTable = reader.read_be().unwrap();
+
+    assert_eq!(&data[..4], b"NUCC");
+    assert_eq!((header.version, header.version2, header.min_page_size), (0x79, 0x79, 3));
+    assert_eq!(reader.position(), NUCC_HEADER_SIZE + header.chunk_table_size as u64);
+
+    // Maps both pages declare, like the clump and the added null, page and index maps, are stored once
+    assert_eq!(table.chunk_map_count, 7);
+    assert_eq!(table.chunk_map_size, 7 * 12);
+    assert_eq!(table.chunk_map_indices_count, 6 + 5);
+    assert_eq!(table.chunk_map_references_count, 1);
+    assert_eq!(table.chunk_types.len() as u32, table.chunk_type_count);
+    assert_eq!(table.chunk_names.iter().map(|name| name.to_string().len() as u32 + 1).sum::<u32>(), table.chunk_name_size);
+
+    let xfbin = Xfbin::read(&mut Cursor::new(&data)).unwrap();
+    let expected = two_pages();
+    assert_eq!(xfbin.pages.len(), 2);
+
+    assert_eq!(
+        xfbin.pages[0].chunk_maps,
+        [
+            chunk("x_anm", "nuccChunkAnmStrm", "c/x/x.max"),
+            chunk("xbod1", "nuccChunkClump", "c/x/x.max"),
+            Chunk::empty("nuccChunkNull"),
+            chunk("x_anm", "nuccChunkAnmStrmFrame", "c/x/x.max"),
+            Chunk::empty("nuccChunkPage"),
+            chunk("index", "nuccChunkIndex", ""),
+        ]
+    );
+    assert_eq!(xfbin.pages[1].chunk_maps[..3], expected.pages[1].chunk_maps[..]);
+    assert_eq!(types(&xfbin.pages[1].chunk_maps[3..]), ["nuccChunkPage", "nuccChunkIndex"]);
+
+    assert_eq!(xfbin.pages[0].chunk_references.len(), 1);
+    assert_eq!(xfbin.pages[0].chunk_references[0].name, "xbod1");
+    assert_eq!(xfbin.pages[0].chunk_references[0].chunk, expected.pages[0].chunk_references[0].chunk);
+    assert!(xfbin.pages[1].chunk_references.is_empty());
+
+    for (page, expected) in xfbin.pages.iter().zip(&expected.pages) {
+        let chunks: Vec<(&Chunk, &[u8])> = page.chunks.iter().map(|chunk| (&chunk.chunk, chunk.data.as_slice())).collect();
+        let expected: Vec<(&Chunk, &[u8])> = expected.chunks.iter().map(|chunk| (&chunk.chunk, chunk.data.as_slice())).collect();
+        assert_eq!(chunks, expected);
+    }
+
+    assert_eq!(xfbin.anmstrms()[0].frames.len(), 2);
+}